@@ -0,0 +1,95 @@
+// Times decoding of a large synthetic source map, run with `cargo bench -p core`. Criterion is not
+// used to keep dependencies minimal, so results are plain averages over a few iterations.
+use std::{hint::black_box, time::Instant};
+
+use core::parser::SourceMapping;
+
+const LINES: usize = 20_000;
+const SEGMENTS_PER_LINE: usize = 100;
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    let json = large_source_map();
+
+    // Warm-up, so that allocations of the first run are not measured.
+    black_box(SourceMapping::from_json(&json, "bench.js.map".to_owned()).unwrap());
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(SourceMapping::from_json(black_box(&json), "bench.js.map".to_owned()).unwrap());
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+
+    println!(
+        "decode_mappings: {} segments ({:.1} MB) in {elapsed:.2?}, {:.0} MB/s",
+        LINES * SEGMENTS_PER_LINE,
+        json.len() as f64 / 1e6,
+        json.len() as f64 / elapsed.as_secs_f64() / 1e6
+    );
+}
+
+// Mappings resemble minified code: short generated spans with sources, lines and columns jumping
+// back and forth, and every third segment naming an identifier.
+fn large_source_map() -> String {
+    let mut mappings = String::new();
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut random = |bound: i64| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as i64
+    };
+
+    // Source fields are relative to the previous segment and must never become negative.
+    let mut previous = [0i64; 4];
+
+    for line in 0..LINES {
+        if line > 0 {
+            mappings.push(';');
+        }
+
+        for segment in 0..SEGMENTS_PER_LINE {
+            if segment > 0 {
+                mappings.push(',');
+            }
+
+            encode_vlq(&mut mappings, random(40) + 1);
+
+            let fields = if segment % 3 == 0 { 4 } else { 3 };
+            let values = [
+                random(3),
+                (previous[1] + random(200) - 100).max(0),
+                (previous[2] + random(80) - 40).max(0),
+                random(6),
+            ];
+
+            for (value, previous) in values.iter().zip(&mut previous).take(fields) {
+                encode_vlq(&mut mappings, value - *previous);
+                *previous = *value;
+            }
+        }
+    }
+
+    format!(
+        r#"{{"version":3,"sources":["a.js","b.js","c.js"],"names":["a","b","c","d","e","f"],"mappings":"{mappings}"}}"#
+    )
+}
+
+fn encode_vlq(output: &mut String, value: i64) {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut value = if value < 0 { (-value << 1) | 1 } else { value << 1 };
+
+    loop {
+        let mut digit = value & 0b11111;
+        value >>= 5;
+        if value > 0 {
+            digit |= 0b100000;
+        }
+        output.push(BASE64[digit as usize] as char);
+
+        if value == 0 {
+            break;
+        }
+    }
+}
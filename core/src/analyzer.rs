@@ -1,11 +1,40 @@
-use anyhow::{anyhow, Context, Result};
+use std::{collections::HashMap, fmt, ops::Range};
 
-use super::parser::{SourceMapping, EMPTY_MAPPING};
+use super::{
+    compression::{estimate_compressed_sizes, CompressedEstimate, CompressionInfo, SizeMetric},
+    discovery::BundleKind,
+    error::Error,
+    lexer::{Lexer, TokenKind},
+    lines::{split_lines, ColumnCursor},
+    parser::{Mapping, SourceMapping, EMPTY_MAPPING},
+    wasm::{self, parse_sections, WasmError, WasmErrorKind, CODE_SECTION_ID, DATA_SECTION_ID},
+};
+
+type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Debug)]
 pub struct SourceMappingFileInfo {
     pub bytes: u32,
     pub file: u32,
+    // Bytes attributed to original identifiers within this file, sorted by size descending.
+    pub info_by_name: Vec<SourceMappingNameInfo>,
+    // Present only when compressed sizes were requested.
+    pub compressed: Option<CompressedEstimate>,
+}
+
+impl SourceMappingFileInfo {
+    pub fn size(&self, metric: SizeMetric) -> Option<u64> {
+        match metric {
+            SizeMetric::Raw => Some(self.bytes as u64),
+            _ => self.compressed.and_then(|estimate| estimate.size.get(metric)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SourceMappingNameInfo {
+    pub bytes: u32,
+    pub name: u32,
 }
 
 #[derive(Debug)]
@@ -13,84 +42,1468 @@ pub struct SourceMappingInfo {
     pub source_mapping: SourceMapping,
     pub sum_bytes: u32,
     pub info_by_file: Vec<SourceMappingFileInfo>,
+    // Bytes attributed to original identifiers across all files, sorted by size descending.
+    // Only spans starting at named mappings count, which usually cover just the identifier token
+    // rather than the whole function or component it names.
+    pub info_by_name: Vec<SourceMappingNameInfo>,
+    // Bytes of generated file known not to come from any source, sorted by size descending.
+    pub unmapped: Vec<UnmappedInfo>,
+    pub coverage: CoverageInfo,
+    // Invalid mappings that were skipped or clamped, always empty in strict mode.
+    pub warnings: Vec<AnalyzeError>,
+    // Present only when compressed sizes were requested.
+    pub compression: Option<CompressionInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum UnmappedCategory {
+    Whitespace,
+    // Both regular comments and license banners.
+    Comments,
+    // sourceMappingURL comments other than the annotation at the end of file, which is not
+    // counted in the file size, e.g. leftovers of concatenated files.
+    SourceMapComment,
+    Strings,
+    // Module wrappers and loader code generated by bundlers.
+    BundlerRuntime,
+    Unknown,
+    // Parts of code section not covered by mappings, e.g. function headers and locals.
+    WasmCode,
+    WasmData,
+    WasmNames,
+    WasmCustom(String),
+    // Module header, types, imports, exports and other sections describing the module.
+    WasmDeclarations,
+}
+
+impl UnmappedCategory {
+    pub fn label(&self) -> String {
+        match self {
+            UnmappedCategory::Whitespace => "whitespace".to_owned(),
+            UnmappedCategory::Comments => "comments".to_owned(),
+            UnmappedCategory::SourceMapComment => "source map comments".to_owned(),
+            UnmappedCategory::Strings => "string literals".to_owned(),
+            UnmappedCategory::BundlerRuntime => "bundler runtime".to_owned(),
+            UnmappedCategory::Unknown => "unknown".to_owned(),
+            UnmappedCategory::WasmCode => "code without mappings".to_owned(),
+            UnmappedCategory::WasmData => "data".to_owned(),
+            UnmappedCategory::WasmNames => "names".to_owned(),
+            UnmappedCategory::WasmCustom(name) => format!("custom section {name}"),
+            UnmappedCategory::WasmDeclarations => "declarations".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnmappedInfo {
+    pub category: UnmappedCategory,
+    pub bytes: u32,
+}
+
+// How much of the generated file is covered directly by spans of mappings with sources.
+#[derive(Debug, Default)]
+pub struct CoverageInfo {
+    pub mapped_bytes: u32,
+    // Ranges outside of mapped spans, sorted by size descending.
+    pub gaps: Vec<CoverageGap>,
+    // For WebAssembly modules the whole module is treated as a single line.
+    pub lines: Vec<LineCoverage>,
+}
+
+impl CoverageInfo {
+    pub fn gap_bytes(&self, kind: GapKind) -> u32 {
+        self.gaps
+            .iter()
+            .filter(|gap| gap.kind == kind)
+            .map(|gap| gap.bytes)
+            .sum()
+    }
+
+    pub fn total_bytes(&self) -> u32 {
+        self.mapped_bytes
+            .saturating_add(self.gaps.iter().map(|gap| gap.bytes).sum())
+    }
+
+    // Number of non-empty lines with none, less than half, at least half and all of their bytes
+    // covered by mapped spans.
+    pub fn lines_by_density(&self) -> [usize; 4] {
+        let mut buckets = [0; 4];
+
+        for line in self.lines.iter().filter(|line| line.bytes > 0) {
+            let bucket = match line.mapped_bytes {
+                0 => 0,
+                bytes if bytes * 2 < line.bytes => 1,
+                bytes if bytes < line.bytes => 2,
+                _ => 3,
+            };
+            buckets[bucket] += 1;
+        }
+
+        buckets
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapKind {
+    // Not attributed to any source.
+    Unmapped,
+    // Attributed to a source only by the attribution strategy, e.g. bytes before the first
+    // mapping in a line.
+    Attributed,
+}
+
+impl GapKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            GapKind::Unmapped => "unmapped",
+            GapKind::Attributed => "attributed by strategy",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoverageGap {
+    pub kind: GapKind,
+    pub bytes: u32,
+    // Position of the first byte, 0-based. Column is counted in UTF-16 code units like in source
+    // maps, for WebAssembly modules it is the byte offset within the module.
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineCoverage {
+    // Size of the line including line terminator.
+    pub bytes: u32,
+    pub mapped_bytes: u32,
+    // Number of mappings starting in the line, including mappings without source.
+    pub mappings: u32,
 }
 
 impl SourceMappingInfo {
     pub fn get_file_name(&self, file: u32) -> &str {
-        &self.source_mapping.sources[file as usize]
+        &self.source_mapping.source_paths[file as usize]
+    }
+
+    pub fn get_full_file_name(&self, file: u32) -> String {
+        self.source_mapping.full_source_path(file)
+    }
+
+    // Names of invalid mappings might be out of range, unless analyzed in strict mode.
+    pub fn get_name(&self, name: u32) -> Option<&str> {
+        self.source_mapping.names.get(name as usize).map(|name| name.as_str())
+    }
+
+    pub fn unmapped_bytes(&self) -> u32 {
+        self.unmapped.iter().map(|info| info.bytes).sum()
+    }
+
+    // Size of the whole file without sourceMappingURL annotation.
+    pub fn file_size(&self, metric: SizeMetric) -> Option<u64> {
+        match metric {
+            SizeMetric::Raw => Some(self.source_mapping.actual_source_file_len()),
+            _ => self.compression.as_ref().and_then(|info| info.total.get(metric)),
+        }
+    }
+
+    pub fn sum_size(&self, metric: SizeMetric) -> Option<u64> {
+        match metric {
+            SizeMetric::Raw => Some(self.sum_bytes as u64),
+            _ => self.info_by_file.iter().map(|info| info.size(metric)).sum(),
+        }
+    }
+
+    pub fn unmapped_size(&self, metric: SizeMetric) -> Option<u64> {
+        match metric {
+            SizeMetric::Raw => Some(self.unmapped_bytes() as u64),
+            _ => self
+                .compression
+                .as_ref()
+                .and_then(|info| info.unmapped.size.get(metric)),
+        }
+    }
+
+    // Compressed sizes are estimated only for whole sources and unmapped bytes, so parts of them,
+    // like identifiers or unmapped categories, get a share proportional to their raw size.
+    pub fn part_size(&self, bytes: u32, whole_bytes: u32, whole_size: Option<u64>, metric: SizeMetric) -> Option<u64> {
+        match metric {
+            SizeMetric::Raw => Some(bytes as u64),
+            _ if whole_bytes == 0 => Some(0),
+            _ => whole_size.map(|size| size * bytes as u64 / whole_bytes as u64),
+        }
+    }
+}
+
+// Errors caused by mappings that do not match the generated file or the source map itself.
+// Lines and columns are 0-based in the fields, but presented as 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnalyzeError {
+    SourceOutOfRange {
+        line: u32,
+        column: u32,
+        source: u32,
+        sources_len: usize,
+    },
+    NameOutOfRange {
+        line: u32,
+        column: u32,
+        name: u32,
+        names_len: usize,
+    },
+    LineOutOfRange {
+        line: u32,
+        lines_len: usize,
+    },
+    ColumnOutOfRange {
+        line: u32,
+        column: u32,
+    },
+    MappingsNotOrdered {
+        line: u32,
+        column: u32,
+    },
+}
+
+impl fmt::Display for AnalyzeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalyzeError::SourceOutOfRange {
+                line,
+                column,
+                source,
+                sources_len,
+            } => write!(
+                f,
+                "Mapping at {}:{} references source {source}, but there are only {sources_len} sources",
+                line + 1,
+                column + 1
+            ),
+            AnalyzeError::NameOutOfRange {
+                line,
+                column,
+                name,
+                names_len,
+            } => write!(
+                f,
+                "Mapping at {}:{} references name {name}, but there are only {names_len} names",
+                line + 1,
+                column + 1
+            ),
+            AnalyzeError::LineOutOfRange { line, lines_len } => write!(
+                f,
+                "Mapping points to line {}, but generated file has only {lines_len} lines",
+                line + 1
+            ),
+            AnalyzeError::ColumnOutOfRange { line, column } => write!(
+                f,
+                "Mapping points to column {}, which is past the end of line {}",
+                column + 1,
+                line + 1
+            ),
+            AnalyzeError::MappingsNotOrdered { line, column } => write!(
+                f,
+                "Mapping at {}:{} precedes the previous mapping, mappings must be ordered",
+                line + 1,
+                column + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AnalyzeError {}
+
+// Decides which bytes of generated JavaScript and CSS are attributed to sources. Mappings mark only
+// the start of a span, so bytes before the first mapping in a line have no obvious owner.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AttributionStrategy {
+    // Bytes from the start of a line until its first mapping and line terminators go to the
+    // neighbouring mappings in the same line.
+    #[default]
+    LineStart,
+    // Only spans starting at mappings are attributed, leading bytes and line terminators are
+    // reported as unmapped.
+    Strict,
+    // Bytes between spans of two mappings with sources, including whole lines without mappings,
+    // are split evenly between both of them.
+    SplitGaps,
+}
+
+impl AttributionStrategy {
+    pub const ALL: [AttributionStrategy; 3] = [
+        AttributionStrategy::LineStart,
+        AttributionStrategy::Strict,
+        AttributionStrategy::SplitGaps,
+    ];
+
+    // Name used in the CLI.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AttributionStrategy::LineStart => "line-start",
+            AttributionStrategy::Strict => "strict",
+            AttributionStrategy::SplitGaps => "split-gaps",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AttributionStrategy> {
+        Self::ALL.into_iter().find(|strategy| strategy.name() == name)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AttributionStrategy::LineStart => "line start",
+            AttributionStrategy::Strict => "strict",
+            AttributionStrategy::SplitGaps => "split gaps",
+        }
+    }
+
+    pub fn next(&self) -> AttributionStrategy {
+        match self {
+            AttributionStrategy::LineStart => AttributionStrategy::Strict,
+            AttributionStrategy::Strict => AttributionStrategy::SplitGaps,
+            AttributionStrategy::SplitGaps => AttributionStrategy::LineStart,
+        }
     }
 }
 
-pub fn calculate_size_by_file(file_contents: &str, source_mapping: SourceMapping) -> Result<SourceMappingInfo> {
-    let file_lines = file_contents.lines().collect::<Vec<&str>>();
+#[derive(Debug, Clone, Default)]
+pub struct AnalyzeOptions {
+    pub attribution: AttributionStrategy,
+    // Fails on the first invalid mapping instead of skipping it with a warning.
+    pub strict: bool,
+    // Estimates gzip and brotli contribution of each source, which takes much longer than the
+    // analysis itself.
+    pub compression: bool,
+}
 
-    let mut sum_bytes = 0u32;
-    let mut info_by_file = source_mapping
-        .sources
+pub fn calculate_size_by_file(
+    file_contents: &[u8],
+    source_mapping: SourceMapping,
+    options: &AnalyzeOptions,
+) -> Result<SourceMappingInfo> {
+    if source_mapping.kind == BundleKind::WebAssembly {
+        return calculate_size_by_wasm_module(file_contents, source_mapping, options);
+    }
+
+    let file_lines = split_lines(file_contents);
+
+    let mut sizes = SizeAccumulator::new(&source_mapping, options);
+    let mut warnings = Warnings::new(options);
+    // Ranges attributed to sources, including bytes attributed by the strategy.
+    let mut mapped_ranges: Vec<Range<usize>> = vec![];
+    // Ranges covered by spans of mappings with sources.
+    let mut span_ranges: Vec<Range<usize>> = vec![];
+    let mut line_coverage = file_lines
         .iter()
-        .enumerate()
-        .map(|(file, _)| SourceMappingFileInfo {
-            bytes: 0,
-            file: file as u32,
+        .map(|line| LineCoverage {
+            bytes: (line.terminator_end - line.start) as u32,
+            ..LineCoverage::default()
         })
         .collect::<Vec<_>>();
-
     let mut prev_mapping = &EMPTY_MAPPING;
+    // Previous mapping with source along with the end of its span, used for splitting gaps.
+    let mut prev_span: Option<(&Mapping, usize)> = None;
+    let mut column_cursor = ColumnCursor::new(&[]);
+    let mut cursor_line = None;
     let mappings = &source_mapping.mappings;
     for (index, mapping) in mappings.iter().enumerate() {
-        let info = info_by_file.get_mut(mapping.src_file as usize).unwrap();
-        let mut bytes = 0u32;
+        if mapping.gen_line < prev_mapping.gen_line {
+            warnings.report(AnalyzeError::MappingsNotOrdered {
+                line: mapping.gen_line,
+                column: mapping.gen_column,
+            })?;
+            continue;
+        }
 
-        if index == 0
-            || //mapping.src_file() != prev_mapping.src_file() ||
-            mapping.gen_line
-                != prev_mapping.gen_line
+        if let Some(coverage) = line_coverage.get_mut(mapping.gen_line as usize) {
+            coverage.mappings += 1;
+        }
+
+        if !mapping.has_source {
+            // Generated code following this mapping does not come from any source.
+            prev_mapping = mapping;
+            prev_span = None;
+            continue;
+        }
+
+        let line = file_lines
+            .get(mapping.gen_line as usize)
+            .ok_or(AnalyzeError::LineOutOfRange {
+                line: mapping.gen_line,
+                lines_len: file_lines.len(),
+            });
+
+        let (Some(()), Some(line)) = (
+            warnings.tolerate(validate_source(mapping, &source_mapping))?,
+            warnings.tolerate(line)?,
+        ) else {
+            prev_mapping = mapping;
+            prev_span = None;
+            continue;
+        };
+        warnings.tolerate(validate_name(mapping, &source_mapping))?;
+
+        let is_first_on_line = index == 0 || mapping.gen_line != prev_mapping.gen_line;
+
+        // Mappings without source are skipped, so cursor might still point to some previous line.
+        if cursor_line != Some(mapping.gen_line) {
+            column_cursor = ColumnCursor::new(&file_contents[line.start..line.end]);
+            cursor_line = Some(mapping.gen_line);
+        }
+
+        let mut byte_offset = |column: u32| {
+            column_cursor.byte_offset(column).ok_or(AnalyzeError::ColumnOutOfRange {
+                line: mapping.gen_line,
+                column,
+            })
+        };
+
+        let Some(mapping_start) = warnings.tolerate(byte_offset(mapping.gen_column))? else {
+            prev_mapping = mapping;
+            prev_span = None;
+            continue;
+        };
+
+        // Span of the last mapping in a line runs until the end of line, spans of invalid next
+        // mappings are clamped to it as well.
+        let line_len = line.end - line.start;
+        let (mapping_end, is_last_on_line) = match mappings.get(index + 1) {
+            Some(next_mapping) if next_mapping.gen_line == mapping.gen_line => (
+                warnings
+                    .tolerate_next(next_mapping, byte_offset(next_mapping.gen_column))?
+                    .unwrap_or(line_len),
+                false,
+            ),
+            _ => (line_len, true),
+        };
+
+        // This happens for invalid sourcemaps, e.g. when mapping points to columns in the generated
+        // file in a different order than they appear in.
+        if mapping_end < mapping_start {
+            warnings.report(AnalyzeError::MappingsNotOrdered {
+                line: mapping.gen_line,
+                column: mapping.gen_column,
+            })?;
+        }
+
+        // Spans are clamped so that they never overlap spans of previous mappings.
+        let span_start = (line.start + mapping_start).max(span_ranges.last().map_or(0, |range| range.end));
+        let mut range = span_start..(line.start + mapping_end).max(span_start);
+
+        line_coverage[mapping.gen_line as usize].mapped_bytes += range.len() as u32;
+        push_range(&mut span_ranges, range.clone());
+
+        match options.attribution {
+            AttributionStrategy::LineStart => {
+                if is_first_on_line {
+                    // Source maps usually skip keywords and other non-identifier tokens,
+                    // so if either file or line has changed compared to the previous mapping
+                    // we probably should add the amount of bytes from the start of line.
+                    // E.g. function example() {} --> mapping might point to "example",
+                    // but "function" was skipped.
+                    range.start = line.start;
+                }
+
+                if is_last_on_line {
+                    range.end = line.terminator_end;
+                }
+            }
+            AttributionStrategy::Strict => {}
+            AttributionStrategy::SplitGaps => {
+                if let Some((gap_mapping, gap_start)) = prev_span.filter(|(_, end)| *end < range.start) {
+                    let gap_middle = gap_start + (range.start - gap_start) / 2;
+                    sizes.add(gap_mapping, gap_start..gap_middle);
+                    sizes.add(mapping, gap_middle..range.start);
+                    push_range(&mut mapped_ranges, gap_start..range.start);
+                }
+            }
+        }
+
+        sizes.add(mapping, range.clone());
+        prev_span = Some((mapping, range.end));
+        push_range(&mut mapped_ranges, range);
+
+        prev_mapping = mapping;
+    }
+
+    let unmapped = classify_unmapped(file_contents, &source_mapping, &mapped_ranges);
+
+    let annotation = find_annotation(file_contents, source_mapping.source_map_len as usize);
+    let compression = sizes.estimate_compressed_sizes(file_contents, &mapped_ranges, annotation.clone());
+    let gaps = find_gaps(&span_ranges, &mapped_ranges, file_contents.len(), annotation);
+
+    // Gaps are ordered, so positions are resolved walking the file forward.
+    let mut line_index = 0;
+    let mut cursor_line = None;
+    let gaps = gaps
+        .into_iter()
+        .map(|(range, kind)| {
+            line_index += file_lines[line_index..].partition_point(|line| line.terminator_end <= range.start);
+            let line = file_lines[line_index];
+
+            if cursor_line != Some(line_index) {
+                column_cursor = ColumnCursor::new(&file_contents[line.start..line.end]);
+                cursor_line = Some(line_index);
+            }
+
+            CoverageGap {
+                kind,
+                bytes: range.len() as u32,
+                line: line_index as u32,
+                column: column_cursor.column(range.start - line.start),
+            }
+        })
+        .collect();
+
+    let coverage = CoverageInfo {
+        mapped_bytes: span_ranges.iter().map(|range| range.len() as u32).sum(),
+        gaps: sorted_gaps(gaps),
+        lines: line_coverage,
+    };
+
+    Ok(sizes.finish(source_mapping, unmapped, coverage, compression, warnings))
+}
+
+fn push_range(ranges: &mut Vec<Range<usize>>, range: Range<usize>) {
+    match ranges.last_mut() {
+        Some(last_range) if last_range.end == range.start => last_range.end = range.end,
+        _ => ranges.push(range),
+    }
+}
+
+// WebAssembly source maps have a single line, with columns being byte offsets into the code
+// section. Everything outside of the code section is reported as unmapped, split by sections.
+fn calculate_size_by_wasm_module(
+    file_contents: &[u8],
+    source_mapping: SourceMapping,
+    options: &AnalyzeOptions,
+) -> Result<SourceMappingInfo> {
+    let sections = parse_sections(file_contents)?;
+    let code_section = sections
+        .iter()
+        .find(|section| section.id == CODE_SECTION_ID)
+        .ok_or(WasmError {
+            offset: file_contents.len(),
+            kind: WasmErrorKind::MissingCodeSection,
+        })?;
+    let code_len = code_section.end - code_section.payload_start;
+
+    let mut sizes = SizeAccumulator::new(&source_mapping, options);
+    let mut warnings = Warnings::new(options);
+    let mut span_ranges: Vec<Range<usize>> = vec![];
+    let mappings = &source_mapping.mappings;
+    for (index, mapping) in mappings.iter().enumerate() {
+        if mapping.gen_line != 0 {
+            warnings.report(AnalyzeError::LineOutOfRange {
+                line: mapping.gen_line,
+                lines_len: 1,
+            })?;
+            continue;
+        }
+
+        if !mapping.has_source || warnings.tolerate(validate_source(mapping, &source_mapping))?.is_none() {
+            continue;
+        }
+        warnings.tolerate(validate_name(mapping, &source_mapping))?;
+
+        let byte_offset = |column: u32| {
+            Some(column as usize)
+                .filter(|offset| *offset <= code_len)
+                .ok_or(AnalyzeError::ColumnOutOfRange { line: 0, column })
+        };
+
+        let Some(mapping_start) = warnings.tolerate(byte_offset(mapping.gen_column))? else {
+            continue;
+        };
+        let mapping_end = match mappings
+            .get(index + 1)
+            .filter(|next_mapping| next_mapping.gen_line == 0)
         {
-            // Source maps usually skip keywords and other non-identifier tokens,
-            // so if either file or line has changed compared to the previous mapping
-            // we probably should add the amount of characters from the start of line.
-            // E.g. function example() {} --> mapping might point to "example",
-            // but "function" was skipped.
-            bytes += mapping.gen_column;
+            Some(next_mapping) => warnings
+                .tolerate_next(next_mapping, byte_offset(next_mapping.gen_column))?
+                .unwrap_or(code_len),
+            None => code_len,
+        };
+
+        if mapping_end < mapping_start {
+            warnings.report(AnalyzeError::MappingsNotOrdered {
+                line: mapping.gen_line,
+                column: mapping.gen_column,
+            })?;
         }
 
-        let line = file_lines[mapping.gen_line as usize];
+        let span_start =
+            (code_section.payload_start + mapping_start).max(span_ranges.last().map_or(0, |range| range.end));
+        let range = span_start..(code_section.payload_start + mapping_end).max(span_start);
+
+        sizes.add(mapping, range.clone());
+        push_range(&mut span_ranges, range);
+    }
 
-        let mapping_end_column = {
-            let next_mapping = mappings.get(index + 1);
+    let mut bytes_by_category: HashMap<UnmappedCategory, u32> = HashMap::new();
+    bytes_by_category.insert(UnmappedCategory::WasmDeclarations, wasm::HEADER_LEN as u32);
 
-            match next_mapping {
-                Some(next_mapping) => {
-                    if next_mapping.gen_line != mapping.gen_line {
-                        line.len() as u32
-                    } else {
-                        next_mapping.gen_column
+    for section in &sections {
+        let category = match (section.id, section.name) {
+            (_, Some(wasm::SOURCE_MAPPING_URL_SECTION)) => continue,
+            (_, Some(wasm::NAME_SECTION)) => UnmappedCategory::WasmNames,
+            (_, Some(name)) => UnmappedCategory::WasmCustom(name.to_owned()),
+            (CODE_SECTION_ID, _) => UnmappedCategory::WasmCode,
+            (DATA_SECTION_ID, _) => UnmappedCategory::WasmData,
+            _ => UnmappedCategory::WasmDeclarations,
+        };
+
+        let bytes = bytes_by_category.entry(category).or_default();
+        *bytes = bytes.saturating_add(section.len() as u32);
+    }
+
+    // Only the part of code section that is not covered by any mapping remains unmapped.
+    if let Some(code_bytes) = bytes_by_category.get_mut(&UnmappedCategory::WasmCode) {
+        *code_bytes = code_bytes.saturating_sub(sizes.sum_bytes);
+    }
+
+    let annotation = sections
+        .iter()
+        .find(|section| section.name == Some(wasm::SOURCE_MAPPING_URL_SECTION))
+        .map(|section| section.start..section.end);
+    let compression = sizes.estimate_compressed_sizes(file_contents, &span_ranges, annotation.clone());
+    let gaps = find_gaps(&span_ranges, &span_ranges, file_contents.len(), annotation)
+        .into_iter()
+        .map(|(range, kind)| CoverageGap {
+            kind,
+            bytes: range.len() as u32,
+            line: 0,
+            column: range.start as u32,
+        })
+        .collect();
+
+    let coverage = CoverageInfo {
+        mapped_bytes: sizes.sum_bytes,
+        gaps: sorted_gaps(gaps),
+        lines: vec![LineCoverage {
+            bytes: file_contents
+                .len()
+                .saturating_sub(source_mapping.source_map_len as usize) as u32,
+            mapped_bytes: sizes.sum_bytes,
+            mappings: mappings.len() as u32,
+        }],
+    };
+
+    Ok(sizes.finish(
+        source_mapping,
+        sorted_unmapped_infos(bytes_by_category),
+        coverage,
+        compression,
+        warnings,
+    ))
+}
+
+// Identifiers found only in code generated by bundlers and transpilers.
+const RUNTIME_IDENTIFIERS: [&str; 16] = [
+    "__esModule",
+    "__commonJS",
+    "__toESM",
+    "__toCommonJS",
+    "__export",
+    "__reExport",
+    "__require",
+    "__defProp",
+    "__getOwnPropNames",
+    "__importDefault",
+    "__importStar",
+    "__exportStar",
+    "_interopRequireDefault",
+    "_interopRequireWildcard",
+    "parcelRequire",
+    "webpackJsonp",
+];
+const RUNTIME_IDENTIFIER_PREFIXES: [&str; 6] = [
+    "__webpack_",
+    "__unused_webpack_",
+    "webpackChunk",
+    "__vite",
+    "__turbopack",
+    "__parcel",
+];
+// Identifiers that commonly appear in module wrappers, along with minified parameter names.
+const WRAPPER_IDENTIFIERS: [&str; 12] = [
+    "function", "return", "var", "let", "const", "use", "strict", "Object", "this", "void", "true", "false",
+];
+
+// Splits bytes not attributed to any source by the kind of tokens they belong to. Code is assumed
+// to be bundler runtime when it mentions runtime identifiers or looks like a module wrapper, code
+// without any identifiers, e.g. closing brackets, is not attributed to anything.
+fn classify_unmapped(
+    file_contents: &[u8],
+    source_mapping: &SourceMapping,
+    mapped_ranges: &[Range<usize>],
+) -> Vec<UnmappedInfo> {
+    // Mapped ranges are sorted and disjoint, so unmapped ranges are simply the gaps between them.
+    let file_end = file_contents.len()..file_contents.len();
+    let unmapped_ranges = mapped_ranges
+        .iter()
+        .chain([&file_end])
+        .scan(0, |prev_end, mapped| {
+            let range = *prev_end..mapped.start;
+            *prev_end = mapped.end;
+            Some(range)
+        })
+        .filter(|range| !range.is_empty());
+
+    let mut bytes_by_category: HashMap<UnmappedCategory, u32> = HashMap::new();
+    let mut add_bytes = |category: UnmappedCategory, bytes: usize| {
+        let category_bytes = bytes_by_category.entry(category).or_default();
+        *category_bytes = category_bytes.saturating_add(bytes as u32);
+    };
+
+    let is_css = source_mapping.kind == BundleKind::Css;
+    let mut tokens = Lexer::new(file_contents, source_mapping.kind).peekable();
+    for range in unmapped_ranges {
+        let mut code_run = CodeRun::default();
+
+        while tokens.next_if(|token| token.end <= range.start).is_some() {}
+
+        while let Some(token) = tokens.peek().copied().filter(|token| token.start < range.end) {
+            let bytes = token.end.min(range.end) - token.start.max(range.start);
+
+            match token.kind {
+                TokenKind::Whitespace => {
+                    add_bytes(UnmappedCategory::Whitespace, bytes);
+
+                    // Code is judged line by line, so that a single unknown identifier does not
+                    // take over the whole range.
+                    let whitespace = &file_contents[token.start..token.end];
+                    if whitespace.contains(&b'\n') || whitespace.contains(&b'\r') {
+                        let code_run = std::mem::take(&mut code_run);
+                        add_bytes(code_run.category(is_css), code_run.bytes);
                     }
                 }
-                None => line.len() as u32,
+                TokenKind::Comment => add_bytes(UnmappedCategory::Comments, bytes),
+                TokenKind::SourceMapComment => add_bytes(UnmappedCategory::SourceMapComment, bytes),
+                TokenKind::String => add_bytes(UnmappedCategory::Strings, bytes),
+                TokenKind::Identifier => {
+                    let identifier = String::from_utf8_lossy(&file_contents[token.start..token.end]);
+
+                    if RUNTIME_IDENTIFIERS.contains(&identifier.as_ref())
+                        || RUNTIME_IDENTIFIER_PREFIXES
+                            .iter()
+                            .any(|prefix| identifier.starts_with(prefix))
+                    {
+                        code_run.has_runtime_identifier = true;
+                    } else if WRAPPER_IDENTIFIERS.contains(&identifier.as_ref()) {
+                        code_run.has_wrapper_identifier = true;
+                    } else if identifier.len() > 2 {
+                        code_run.has_other_identifier = true;
+                    }
+
+                    code_run.bytes += bytes;
+                }
+                TokenKind::Code => code_run.bytes += bytes,
             }
+
+            // Token continues in the next range.
+            if token.end > range.end {
+                break;
+            }
+
+            tokens.next();
+        }
+
+        add_bytes(code_run.category(is_css), code_run.bytes);
+    }
+
+    // Annotation at the end of file is already excluded from the file size.
+    if let Some(bytes) = bytes_by_category.get_mut(&UnmappedCategory::SourceMapComment) {
+        *bytes = bytes.saturating_sub(source_mapping.source_map_len as u32);
+    }
+
+    sorted_unmapped_infos(bytes_by_category)
+}
+
+// Code within a single line of an unmapped range.
+#[derive(Default)]
+struct CodeRun {
+    bytes: usize,
+    has_runtime_identifier: bool,
+    has_wrapper_identifier: bool,
+    has_other_identifier: bool,
+}
+
+impl CodeRun {
+    fn category(&self, is_css: bool) -> UnmappedCategory {
+        let is_wrapper = self.has_wrapper_identifier && !self.has_other_identifier;
+
+        if !is_css && (self.has_runtime_identifier || is_wrapper) {
+            UnmappedCategory::BundlerRuntime
+        } else {
+            UnmappedCategory::Unknown
+        }
+    }
+}
+
+// Splits bytes not covered by spans into gaps, separating parts attributed by the strategy from
+// unmapped ones. Excluded range, i.e. sourceMappingURL annotation, is not reported at all.
+fn find_gaps(
+    span_ranges: &[Range<usize>],
+    mapped_ranges: &[Range<usize>],
+    file_len: usize,
+    excluded: Option<Range<usize>>,
+) -> Vec<(Range<usize>, GapKind)> {
+    let mut gaps = vec![];
+    let mut mapped_ranges = mapped_ranges.iter().peekable();
+    for gap in uncovered_ranges(span_ranges, file_len, excluded) {
+        let mut position = gap.start;
+        while position < gap.end {
+            while mapped_ranges.next_if(|mapped| mapped.end <= position).is_some() {}
+
+            let end = match mapped_ranges.peek() {
+                Some(mapped) if mapped.start <= position => {
+                    let end = mapped.end.min(gap.end);
+                    gaps.push((position..end, GapKind::Attributed));
+                    end
+                }
+                Some(mapped) if mapped.start < gap.end => {
+                    gaps.push((position..mapped.start, GapKind::Unmapped));
+                    mapped.start
+                }
+                _ => {
+                    gaps.push((position..gap.end, GapKind::Unmapped));
+                    gap.end
+                }
+            };
+            position = end;
+        }
+    }
+
+    gaps
+}
+
+// Ranges of the file outside of given ranges and the excluded range.
+fn uncovered_ranges(ranges: &[Range<usize>], file_len: usize, excluded: Option<Range<usize>>) -> Vec<Range<usize>> {
+    let mut covered_ranges = ranges.iter().cloned().chain(excluded).collect::<Vec<_>>();
+    covered_ranges.sort_by_key(|range| range.start);
+    covered_ranges.push(file_len..file_len);
+
+    let mut prev_end = 0;
+    covered_ranges
+        .into_iter()
+        .filter_map(|covered| {
+            let range = prev_end..covered.start;
+            prev_end = prev_end.max(covered.end);
+            (!range.is_empty()).then_some(range)
+        })
+        .collect()
+}
+
+fn sorted_gaps(mut gaps: Vec<CoverageGap>) -> Vec<CoverageGap> {
+    gaps.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then(a.line.cmp(&b.line))
+            .then(a.column.cmp(&b.column))
+    });
+
+    gaps
+}
+
+// Range of sourceMappingURL annotation, which is already excluded from the file size. Only the
+// annotation closest to the end of file is taken into account.
+fn find_annotation(file_contents: &[u8], annotation_len: usize) -> Option<Range<usize>> {
+    if annotation_len == 0 {
+        return None;
+    }
+
+    let url_start = file_contents
+        .windows(b"sourceMappingURL=".len())
+        .rposition(|window| window == b"sourceMappingURL=")?;
+    let start = file_contents[..url_start]
+        .windows(2)
+        .rposition(|window| window == b"//" || window == b"/*")?;
+
+    Some(start..(start + annotation_len).min(file_contents.len()))
+}
+
+fn sorted_unmapped_infos(bytes_by_category: HashMap<UnmappedCategory, u32>) -> Vec<UnmappedInfo> {
+    let mut unmapped = bytes_by_category
+        .into_iter()
+        .filter(|(_, bytes)| *bytes > 0)
+        .map(|(category, bytes)| UnmappedInfo { category, bytes })
+        .collect::<Vec<_>>();
+    unmapped.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.category.cmp(&b.category)));
+
+    unmapped
+}
+
+fn validate_source(mapping: &Mapping, source_mapping: &SourceMapping) -> Result<(), AnalyzeError> {
+    if mapping.src_file as usize >= source_mapping.sources.len() {
+        return Err(AnalyzeError::SourceOutOfRange {
+            line: mapping.gen_line,
+            column: mapping.gen_column,
+            source: mapping.src_file,
+            sources_len: source_mapping.sources.len(),
+        });
+    }
+
+    Ok(())
+}
+
+fn validate_name(mapping: &Mapping, source_mapping: &SourceMapping) -> Result<(), AnalyzeError> {
+    if let Some(name) = mapping.name.filter(|name| *name as usize >= source_mapping.names.len()) {
+        return Err(AnalyzeError::NameOutOfRange {
+            line: mapping.gen_line,
+            column: mapping.gen_column,
+            name,
+            names_len: source_mapping.names.len(),
+        });
+    }
+
+    Ok(())
+}
+
+// Invalid mappings either fail the analysis in strict mode, or are collected as warnings.
+struct Warnings {
+    strict: bool,
+    warnings: Vec<AnalyzeError>,
+}
+
+impl Warnings {
+    fn new(options: &AnalyzeOptions) -> Self {
+        Warnings {
+            strict: options.strict,
+            warnings: vec![],
+        }
+    }
+
+    // Returns None for tolerated errors, so that the caller can skip or clamp the mapping.
+    fn tolerate<T>(&mut self, result: Result<T, AnalyzeError>) -> Result<Option<T>, AnalyzeError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if self.strict => Err(err),
+            Err(err) => {
+                self.warnings.push(err);
+                Ok(None)
+            }
+        }
+    }
+
+    // Errors of the next mapping are reported once it is processed, unless it has no source and
+    // would be skipped.
+    fn tolerate_next<T>(
+        &mut self,
+        next_mapping: &Mapping,
+        result: Result<T, AnalyzeError>,
+    ) -> Result<Option<T>, AnalyzeError> {
+        match next_mapping.has_source {
+            true => Ok(result.ok()),
+            false => self.tolerate(result),
+        }
+    }
+
+    fn report(&mut self, err: AnalyzeError) -> Result<(), AnalyzeError> {
+        self.tolerate(Err::<(), _>(err)).map(drop)
+    }
+}
+
+// Sums bytes attributed to sources and identifiers. Mappings are expected to have valid sources,
+// names out of range are ignored.
+struct SizeAccumulator {
+    names_len: usize,
+    sum_bytes: u32,
+    info_by_file: Vec<SourceMappingFileInfo>,
+    bytes_by_file_and_name: HashMap<(u32, u32), u32>,
+    // Attributed ranges of each file, collected only for estimating compressed sizes.
+    ranges_by_file: Option<Vec<Vec<Range<usize>>>>,
+}
+
+impl SizeAccumulator {
+    fn new(source_mapping: &SourceMapping, options: &AnalyzeOptions) -> Self {
+        SizeAccumulator {
+            names_len: source_mapping.names.len(),
+            sum_bytes: 0,
+            info_by_file: (0..source_mapping.sources.len() as u32)
+                .map(|file| SourceMappingFileInfo {
+                    bytes: 0,
+                    file,
+                    info_by_name: vec![],
+                    compressed: None,
+                })
+                .collect(),
+            bytes_by_file_and_name: HashMap::new(),
+            ranges_by_file: options.compression.then(|| vec![vec![]; source_mapping.sources.len()]),
+        }
+    }
+
+    fn add(&mut self, mapping: &Mapping, range: Range<usize>) {
+        let bytes = range.len() as u32;
+
+        if let Some(ranges_by_file) = &mut self.ranges_by_file {
+            push_range(&mut ranges_by_file[mapping.src_file as usize], range);
+        }
+
+        let info = &mut self.info_by_file[mapping.src_file as usize];
+        info.bytes = info.bytes.saturating_add(bytes);
+        self.sum_bytes = self.sum_bytes.saturating_add(bytes);
+
+        if let Some(name) = mapping.name.filter(|name| (*name as usize) < self.names_len) {
+            let name_bytes = self.bytes_by_file_and_name.entry((mapping.src_file, name)).or_default();
+            *name_bytes = name_bytes.saturating_add(bytes);
+        }
+    }
+
+    // Bytes outside of covered ranges are estimated together as unmapped.
+    fn estimate_compressed_sizes(
+        &mut self,
+        file_contents: &[u8],
+        covered_ranges: &[Range<usize>],
+        excluded: Option<Range<usize>>,
+    ) -> Option<CompressionInfo> {
+        let ranges_by_file = self.ranges_by_file.take()?;
+        let unmapped_ranges = uncovered_ranges(covered_ranges, file_contents.len(), excluded.clone());
+
+        let (info, estimates) = estimate_compressed_sizes(file_contents, &ranges_by_file, &unmapped_ranges, excluded);
+
+        for (file_info, estimate) in self.info_by_file.iter_mut().zip(estimates) {
+            file_info.compressed = Some(estimate);
+        }
+
+        Some(info)
+    }
+
+    fn finish(
+        mut self,
+        source_mapping: SourceMapping,
+        unmapped: Vec<UnmappedInfo>,
+        coverage: CoverageInfo,
+        compression: Option<CompressionInfo>,
+        warnings: Warnings,
+    ) -> SourceMappingInfo {
+        let info_by_name = calculate_size_by_name(&mut self.info_by_file, self.bytes_by_file_and_name);
+
+        SourceMappingInfo {
+            source_mapping,
+            sum_bytes: self.sum_bytes,
+            info_by_file: self.info_by_file,
+            info_by_name,
+            unmapped,
+            coverage,
+            warnings: warnings.warnings,
+            compression,
+        }
+    }
+}
+
+// Distributes bytes of named mappings into per file lists and returns totals per identifier.
+// The same identifier might appear in multiple files, e.g. commonly used function names.
+fn calculate_size_by_name(
+    info_by_file: &mut [SourceMappingFileInfo],
+    bytes_by_file_and_name: HashMap<(u32, u32), u32>,
+) -> Vec<SourceMappingNameInfo> {
+    let mut bytes_by_name: HashMap<u32, u32> = HashMap::new();
+
+    for ((file, name), bytes) in bytes_by_file_and_name {
+        let name_bytes = bytes_by_name.entry(name).or_default();
+        *name_bytes = name_bytes.saturating_add(bytes);
+        info_by_file[file as usize]
+            .info_by_name
+            .push(SourceMappingNameInfo { bytes, name });
+    }
+
+    for info in info_by_file.iter_mut() {
+        sort_name_infos(&mut info.info_by_name);
+    }
+
+    let mut info_by_name = bytes_by_name
+        .into_iter()
+        .map(|(name, bytes)| SourceMappingNameInfo { bytes, name })
+        .collect::<Vec<_>>();
+    sort_name_infos(&mut info_by_name);
+
+    info_by_name
+}
+
+fn sort_name_infos(name_infos: &mut [SourceMappingNameInfo]) {
+    name_infos.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.name.cmp(&b.name)));
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use crate::{
+        analyzer::{
+            calculate_size_by_file, AnalyzeError, AnalyzeOptions, AttributionStrategy, GapKind, SourceMappingInfo,
+            UnmappedCategory,
+        },
+        compression::SizeMetric,
+        error::Error,
+        parser::SourceMapping,
+        wasm::tests::example_module,
+    };
+
+    fn analyze(contents: &[u8], json: &str, strict: bool) -> Result<SourceMappingInfo, Error> {
+        let source_mapping = SourceMapping::from_json(json, "bundle.js.map".to_owned())?;
+        let options = AnalyzeOptions {
+            strict,
+            ..AnalyzeOptions::default()
         };
 
-        bytes += mapping_end_column.checked_sub(mapping.gen_column).with_context(|| {
-            // This only happens in my test project where sourcemap is invalid, e.g. it maps
-            // inexistent columns in generated file to inexistent columns in source file.
-            anyhow!(
-                "Subtraction with overflow: calculating bytes for path {}, operation: {} - {}",
-                source_mapping.file,
-                mapping_end_column,
-                mapping.gen_column,
-            )
-        })?;
+        calculate_size_by_file(contents, source_mapping, &options)
+    }
 
-        info.bytes += bytes;
-        sum_bytes += bytes;
+    #[test]
+    fn counts_bytes_for_multibyte_characters_and_line_terminators() {
+        // Emoji takes 4 bytes but only 2 columns, 0xFF is not valid UTF-8 and takes 1 column.
+        let contents = b"a=\"\xF0\x9F\x98\x80\";b=1;\r\nc=\xFF;d=2;\n";
 
-        prev_mapping = mapping;
+        let source_mapping = SourceMapping::from_json(
+            r#"{"version":3,"sources":["a.js","b.js"],"names":[],"mappings":"AAAA,OCAA;AAAA,IDAA"}"#,
+            "bundle.js.map".to_owned(),
+        )
+        .unwrap();
+
+        let info = calculate_size_by_file(contents, source_mapping, &AnalyzeOptions::default()).unwrap();
+
+        let bytes = info.info_by_file.iter().map(|info| info.bytes).collect::<Vec<_>>();
+        assert_eq!(bytes, [9 + 5, 6 + 4]);
+        assert_eq!(info.sum_bytes as usize, contents.len());
     }
 
-    Ok(SourceMappingInfo {
-        source_mapping,
-        sum_bytes,
-        info_by_file,
-    })
+    #[test]
+    fn converts_columns_of_lines_starting_without_source() {
+        // Columns of the second line would be misread using bytes of the first one.
+        let contents = "a=\"😀😀\";\nbbbbbbbb=1;\n";
+
+        let source_mapping = SourceMapping::from_json(
+            r#"{"version":3,"sources":["a.js","b.js"],"names":[],"mappings":"AAAA;A,QCAA"}"#,
+            "bundle.js.map".to_owned(),
+        )
+        .unwrap();
+
+        let info = calculate_size_by_file(contents.as_bytes(), source_mapping, &AnalyzeOptions::default()).unwrap();
+
+        let bytes = info.info_by_file.iter().map(|info| info.bytes).collect::<Vec<_>>();
+        assert_eq!(bytes, [14, 3 + 1]);
+        assert!(info.warnings.is_empty());
+    }
+
+    #[test]
+    fn reports_mappings_inconsistent_with_generated_file() {
+        let test_cases = [
+            (
+                r#"{"sources":["a.js"],"names":[],"mappings":"AAAA,ACAA"}"#,
+                AnalyzeError::SourceOutOfRange {
+                    line: 0,
+                    column: 0,
+                    source: 1,
+                    sources_len: 1,
+                },
+            ),
+            (
+                r#"{"sources":["a.js"],"names":["a"],"mappings":"AAAAC"}"#,
+                AnalyzeError::NameOutOfRange {
+                    line: 0,
+                    column: 0,
+                    name: 1,
+                    names_len: 1,
+                },
+            ),
+            (
+                r#"{"sources":["a.js"],"names":[],"mappings":";;;AAAA"}"#,
+                AnalyzeError::LineOutOfRange { line: 3, lines_len: 2 },
+            ),
+            (
+                r#"{"sources":["a.js"],"names":[],"mappings":"AAAA,gBAAA"}"#,
+                AnalyzeError::ColumnOutOfRange { line: 0, column: 16 },
+            ),
+            (
+                r#"{"sources":["a.js"],"names":[],"mappings":"EAAA,DAAA"}"#,
+                AnalyzeError::MappingsNotOrdered { line: 0, column: 2 },
+            ),
+        ];
+
+        for (json, expected) in test_cases {
+            let error = analyze(b"a=1;\nb=2;", json, true).unwrap_err();
+            assert!(
+                matches!(&error, Error::InconsistentMapping(error) if *error == expected),
+                "{json}"
+            );
+
+            let info = analyze(b"a=1;\nb=2;", json, false).unwrap();
+            assert_eq!(info.warnings, [expected], "{json}");
+        }
+    }
+
+    #[test]
+    fn skips_invalid_mappings_unless_strict() {
+        // Each map has a valid mapping besides the invalid one.
+        let test_cases = [
+            (
+                r#"{"sources":["a.js"],"names":[],"mappings":"AAAA,CCAA"}"#,
+                AnalyzeError::SourceOutOfRange {
+                    line: 0,
+                    column: 1,
+                    source: 1,
+                    sources_len: 1,
+                },
+            ),
+            (
+                r#"{"sources":["a.js"],"names":[],"mappings":"AAAA;;;AAAA"}"#,
+                AnalyzeError::LineOutOfRange { line: 3, lines_len: 2 },
+            ),
+            (
+                r#"{"sources":["a.js"],"names":["a"],"mappings":"AAAAC"}"#,
+                AnalyzeError::NameOutOfRange {
+                    line: 0,
+                    column: 0,
+                    name: 1,
+                    names_len: 1,
+                },
+            ),
+        ];
+
+        for (json, expected) in test_cases {
+            assert!(analyze(b"a=1;\nb=2;", json, true).is_err(), "{json}");
+
+            // Without strict mode the rest of the file is still analyzed.
+            let info = analyze(b"a=1;\nb=2;", json, false).unwrap();
+            assert_eq!(info.warnings, [expected], "{json}");
+            assert!(info.sum_bytes > 0, "{json}");
+        }
+    }
+
+    #[test]
+    fn does_not_panic_on_mutated_input() {
+        let contents = "a=\"😀\";b=1;\r\nc=2;d=3;\n".as_bytes();
+        let json = r#"{"version":3,"sources":["a.js","b.js"],"names":["a","c"],"mappings":"AAAAA,OCAA;AAAAC,IDAA"}"#;
+        let indexed_json = r#"{"version":3,"sections":[{"offset":{"line":0,"column":4},"map":{"sources":["a.js"],"names":["a"],"mappings":"AAAAA;AACA"}}]}"#;
+
+        // Simple deterministic xorshift, so that failures are reproducible.
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut random = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+
+        let alphabet = b"AaBbCgzZ+/09,;\"{}[]:-!\\\r\n\x00\xFF";
+
+        for _ in 0..5000 {
+            let mut contents = contents.to_vec();
+            let mut json = if random(2) == 0 { json } else { indexed_json }.as_bytes().to_vec();
+
+            for _ in 0..random(4) + 1 {
+                let target = if random(3) == 0 { &mut contents } else { &mut json };
+                let position = random(target.len() + 1);
+                let byte = alphabet[random(alphabet.len())];
+
+                match random(3) {
+                    0 if position < target.len() => target[position] = byte,
+                    1 if position < target.len() => {
+                        target.remove(position);
+                    }
+                    _ => target.insert(position, byte),
+                }
+            }
+
+            let json = String::from_utf8_lossy(&json);
+            let _ = analyze(&contents, &json, true);
+
+            if let Ok(info) = analyze(&contents, &json, false) {
+                assert_eq!(info.sum_bytes + info.unmapped_bytes(), contents.len() as u32);
+                assert_eq!(info.coverage.total_bytes(), contents.len() as u32);
+            }
+        }
+    }
+
+    #[test]
+    fn attributes_wasm_code_section() {
+        let contents = example_module("module.wasm.map");
+
+        // Columns are offsets into code section: 3 points to the first instruction.
+        let source_mapping = SourceMapping::from_json(
+            r#"{"version":3,"file":"module.wasm","sources":["a.rs","b.rs"],"names":["f"],"mappings":"GAAA,CCAAA,EAAA"}"#,
+            "module.wasm.map".to_owned(),
+        )
+        .unwrap();
+
+        let info = calculate_size_by_file(&contents, source_mapping, &AnalyzeOptions::default()).unwrap();
+
+        let bytes = info.info_by_file.iter().map(|info| info.bytes).collect::<Vec<_>>();
+        assert_eq!(bytes, [1, 3]);
+        assert_eq!(info.info_by_name[0].bytes, 2);
+        assert_eq!(info.get_name(info.info_by_name[0].name), Some("f"));
+        assert_eq!(info.get_name(1), None);
+
+        let unmapped = info
+            .unmapped
+            .iter()
+            .map(|info| (info.category.clone(), info.bytes))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unmapped,
+            [
+                (UnmappedCategory::WasmDeclarations, 18),
+                (UnmappedCategory::WasmData, 11),
+                (UnmappedCategory::WasmNames, 11),
+                (UnmappedCategory::WasmCode, 5),
+            ]
+        );
+
+        // sourceMappingURL section at the end is not reported.
+        let gaps = info
+            .coverage
+            .gaps
+            .iter()
+            .map(|gap| (gap.bytes, gap.column))
+            .collect::<Vec<_>>();
+        assert_eq!(gaps, [(23, 0), (22, 27)]);
+        assert_eq!(info.coverage.mapped_bytes, 4);
+    }
+
+    #[test]
+    fn attributes_bytes_by_strategy() {
+        let contents = "var a=1;\nfoo();\n  b=2;\n";
+
+        // "a=1;" comes from a.js and "b=2;" from b.js, the line in between has no mappings.
+        let json = r#"{"version":3,"sources":["a.js","b.js"],"names":[],"mappings":"IAAA;;ECAA"}"#;
+
+        let test_cases = [
+            (AttributionStrategy::LineStart, [9, 7]),
+            (AttributionStrategy::Strict, [4, 4]),
+            (AttributionStrategy::SplitGaps, [4 + 5, 5 + 4]),
+        ];
+
+        for (attribution, expected) in test_cases {
+            let mut source_mapping = SourceMapping::from_json(json, "bundle.js.map".to_owned()).unwrap();
+            source_mapping.source_file_len = contents.len() as u64;
+
+            let options = AnalyzeOptions {
+                attribution,
+                ..AnalyzeOptions::default()
+            };
+            let info = calculate_size_by_file(contents.as_bytes(), source_mapping, &options).unwrap();
+
+            let bytes = info.info_by_file.iter().map(|info| info.bytes).collect::<Vec<_>>();
+            assert_eq!(bytes, expected, "{attribution:?}");
+            assert_eq!(info.sum_bytes + info.unmapped_bytes(), contents.len() as u32);
+        }
+    }
+
+    #[test]
+    fn estimates_compressed_sizes_of_sources() {
+        let contents = "var a=1;\nfoo();\n  b=2;\n";
+        let json = r#"{"version":3,"sources":["a.js","b.js"],"names":[],"mappings":"IAAA;;ECAA"}"#;
+
+        for compression in [false, true] {
+            let mut source_mapping = SourceMapping::from_json(json, "bundle.js.map".to_owned()).unwrap();
+            source_mapping.source_file_len = contents.len() as u64;
+
+            let options = AnalyzeOptions {
+                compression,
+                ..AnalyzeOptions::default()
+            };
+            let info = calculate_size_by_file(contents.as_bytes(), source_mapping, &options).unwrap();
+
+            for metric in [SizeMetric::Gzip, SizeMetric::Brotli] {
+                let sizes = [
+                    info.sum_size(metric),
+                    info.unmapped_size(metric),
+                    info.file_size(metric),
+                ];
+
+                match sizes {
+                    [Some(sum), Some(unmapped), Some(total)] => {
+                        assert!(compression);
+                        assert!(sum > 0 && sum + unmapped <= total, "{metric:?} {sizes:?}");
+                    }
+                    _ => assert_eq!(sizes, [None; 3]),
+                }
+            }
+
+            assert_eq!(info.sum_size(SizeMetric::Raw), Some(16));
+        }
+    }
+
+    #[test]
+    fn reports_coverage_gaps() {
+        let contents = "var a=1;\nfoo();\n  é=2;\n";
+
+        let source_mapping = SourceMapping::from_json(
+            r#"{"version":3,"sources":["a.js","b.js"],"names":[],"mappings":"IAAA;;ECAA"}"#,
+            "bundle.js.map".to_owned(),
+        )
+        .unwrap();
+
+        let info = calculate_size_by_file(contents.as_bytes(), source_mapping, &AnalyzeOptions::default()).unwrap();
+        let coverage = &info.coverage;
+
+        assert_eq!(coverage.mapped_bytes, 4 + 5);
+        assert_eq!(coverage.total_bytes() as usize, contents.len());
+        assert_eq!(coverage.gap_bytes(GapKind::Unmapped), 7);
+
+        let gaps = coverage
+            .gaps
+            .iter()
+            .map(|gap| (gap.kind, gap.bytes, gap.line, gap.column))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            gaps,
+            [
+                (GapKind::Unmapped, 7, 1, 0),
+                (GapKind::Attributed, 4, 0, 0),
+                (GapKind::Attributed, 2, 2, 0),
+                (GapKind::Attributed, 1, 0, 8),
+                (GapKind::Attributed, 1, 2, 6),
+            ]
+        );
+
+        let lines = coverage
+            .lines
+            .iter()
+            .map(|line| (line.bytes, line.mapped_bytes, line.mappings))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [(9, 4, 1), (7, 0, 0), (8, 5, 1), (0, 0, 0)]);
+        assert_eq!(coverage.lines_by_density(), [1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn classifies_unmapped_bytes() {
+        let contents = concat!(
+            "/*! License */\n",
+            "(()=>{var __webpack_modules__={1:function(e){a=1;}};})();\n",
+            "\"str\";foo=1;\n",
+            "require(x);\n",
+            "//# sourceMappingURL=a.js.map",
+        );
+
+        // Only "a=1;" comes from a source.
+        let mut source_mapping = SourceMapping::from_json(
+            r#"{"version":3,"sources":["a.js"],"names":[],"mappings":";A,6CAAA,I"}"#,
+            "a.js.map".to_owned(),
+        )
+        .unwrap();
+        source_mapping.source_file_len = contents.len() as u64;
+        source_mapping.source_map_len = "//# sourceMappingURL=a.js.map".len() as u64;
+
+        let info = calculate_size_by_file(contents.as_bytes(), source_mapping, &AnalyzeOptions::default()).unwrap();
+        assert_eq!(info.sum_bytes, 4);
+
+        let unmapped = info
+            .unmapped
+            .iter()
+            .map(|info| (info.category.clone(), info.bytes))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unmapped,
+            [
+                (UnmappedCategory::BundlerRuntime, 44),
+                (UnmappedCategory::Unknown, 7 + 8 + 11),
+                (UnmappedCategory::Comments, 14),
+                (UnmappedCategory::Whitespace, 5),
+                (UnmappedCategory::Strings, 5),
+            ]
+        );
+        assert_eq!(
+            info.sum_bytes + info.unmapped_bytes(),
+            info.source_mapping.actual_source_file_len() as u32
+        );
+    }
 }
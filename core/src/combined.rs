@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    analyzer::SourceMappingInfo,
+    parser::{common_directory, relative_to},
+};
+
+// Source found in one or more bundles, with bytes summed up across all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CombinedSource {
+    // Resolved source path, same as in other cross-bundle reports.
+    pub path: String,
+    pub bytes: u64,
+    pub bundles: usize,
+}
+
+// All bundles of a run merged into a single source tree, as if they were one file.
+#[derive(Debug, Clone, Default)]
+pub struct CombinedInfo {
+    pub bundles: usize,
+    // Sizes of the generated files without sourceMappingURL annotation.
+    pub file_bytes: u64,
+    pub sum_bytes: u64,
+    pub unmapped_bytes: u64,
+    // Deepest directory containing all sources.
+    pub sources_root: String,
+    // Largest first.
+    pub sources: Vec<CombinedSource>,
+}
+
+impl CombinedInfo {
+    pub fn relative_path<'source>(&self, source: &'source CombinedSource) -> &'source str {
+        relative_to(&source.path, &self.sources_root)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CombinedAnalysis {
+    bundles: usize,
+    file_bytes: u64,
+    sum_bytes: u64,
+    unmapped_bytes: u64,
+    // Bytes and number of bundles, keyed by resolved source path.
+    sources: HashMap<String, (u64, usize)>,
+}
+
+impl CombinedAnalysis {
+    pub fn add(&mut self, info: &SourceMappingInfo) {
+        self.bundles += 1;
+        self.file_bytes += info.source_mapping.actual_source_file_len();
+        self.sum_bytes += info.sum_bytes as u64;
+        self.unmapped_bytes += info.unmapped_bytes() as u64;
+
+        // Source map can list the same source more than once.
+        let mut seen_sources = HashSet::new();
+
+        for file_info in &info.info_by_file {
+            let path = info.get_full_file_name(file_info.file);
+            let (bytes, bundles) = self.sources.entry(path.clone()).or_default();
+
+            *bytes += file_info.bytes as u64;
+
+            if seen_sources.insert(path) {
+                *bundles += 1;
+            }
+        }
+    }
+
+    pub fn info(&self) -> CombinedInfo {
+        let mut sources = self
+            .sources
+            .iter()
+            .map(|(path, (bytes, bundles))| CombinedSource {
+                path: path.clone(),
+                bytes: *bytes,
+                bundles: *bundles,
+            })
+            .collect::<Vec<_>>();
+
+        sources.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+
+        CombinedInfo {
+            bundles: self.bundles,
+            file_bytes: self.file_bytes,
+            sum_bytes: self.sum_bytes,
+            unmapped_bytes: self.unmapped_bytes,
+            sources_root: common_directory(sources.iter().map(|source| source.path.as_str())),
+            sources,
+        }
+    }
+}
+
+pub fn combine_infos<'info>(infos: impl IntoIterator<Item = &'info SourceMappingInfo>) -> CombinedInfo {
+    let mut analysis = CombinedAnalysis::default();
+
+    for info in infos {
+        analysis.add(info);
+    }
+
+    analysis.info()
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use crate::{
+        combined::{combine_infos, CombinedSource},
+        test_utils::analyze_bundle,
+    };
+
+    fn source(path: &str, bytes: u64, bundles: usize) -> CombinedSource {
+        CombinedSource {
+            path: path.to_owned(),
+            bytes,
+            bundles,
+        }
+    }
+
+    #[test]
+    fn combines_sources_of_all_bundles() {
+        let first = analyze_bundle(
+            "a.js",
+            "var a=1;\nvar b=22;\nvar c=3;\n",
+            &[("src/app.js", "a"), ("src/util.js", "b"), ("src/app.js", "c")],
+        );
+        let second = analyze_bundle("b.js", "var d=4444;\n", &[("src/util.js", "d")]);
+
+        let combined = combine_infos([&first, &second]);
+
+        assert_eq!(combined.bundles, 2);
+        assert_eq!(combined.file_bytes, 40);
+        assert_eq!(combined.sum_bytes, 40);
+        assert_eq!(combined.sources_root, "dist/src");
+        assert_eq!(
+            combined.sources,
+            vec![source("dist/src/util.js", 22, 2), source("dist/src/app.js", 18, 1)]
+        );
+        assert_eq!(combined.relative_path(&combined.sources[0]), "util.js");
+    }
+}
@@ -0,0 +1,304 @@
+use std::{
+    io::{self, Write},
+    ops::Range,
+};
+
+use flate2::{
+    write::{DeflateEncoder, GzEncoder},
+    Compression,
+};
+
+// Settings commonly used for static assets, lower brotli quality keeps large bundles fast enough.
+const GZIP_LEVEL: u32 = 9;
+const BROTLI_QUALITY: u32 = 9;
+const BROTLI_MAX_WINDOW: u32 = 22;
+
+// How estimates are calculated, shared by all of them.
+pub const METHOD_NOTE: &str = "estimated by compressing generated code of each source on its own \
+     and scaling results to the compressed size of the whole file";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SizeMetric {
+    #[default]
+    Raw,
+    Gzip,
+    Brotli,
+}
+
+impl SizeMetric {
+    pub const ALL: [SizeMetric; 3] = [SizeMetric::Raw, SizeMetric::Gzip, SizeMetric::Brotli];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SizeMetric::Raw => "raw",
+            SizeMetric::Gzip => "gzip",
+            SizeMetric::Brotli => "brotli",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|metric| metric.name() == name)
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|metric| *metric == self).unwrap_or_default();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn is_compressed(self) -> bool {
+        self != SizeMetric::Raw
+    }
+}
+
+// Sources with little generated code compress much worse on their own than within the bundle,
+// where they can refer to repetitions in the rest of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Accuracy {
+    Low,
+    Medium,
+    High,
+}
+
+impl Accuracy {
+    fn from_len(len: usize) -> Self {
+        match len {
+            0..=1023 => Accuracy::Low,
+            1024..=16383 => Accuracy::Medium,
+            _ => Accuracy::High,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Accuracy::Low => "low accuracy",
+            Accuracy::Medium => "medium accuracy",
+            Accuracy::High => "high accuracy",
+        }
+    }
+
+    pub fn note(self) -> &'static str {
+        match self {
+            Accuracy::Low => "too little code to compress on its own, estimate is mostly a share of the whole file",
+            Accuracy::Medium => "repetitions shared with other sources are split proportionally",
+            Accuracy::High => "enough code to compress about as well on its own as within the bundle",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressedSize {
+    pub gzip: u64,
+    pub brotli: u64,
+}
+
+impl CompressedSize {
+    // Returns None for raw size, which is not known here.
+    pub fn get(self, metric: SizeMetric) -> Option<u64> {
+        match metric {
+            SizeMetric::Raw => None,
+            SizeMetric::Gzip => Some(self.gzip),
+            SizeMetric::Brotli => Some(self.brotli),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressedEstimate {
+    pub size: CompressedSize,
+    pub accuracy: Accuracy,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompressionInfo {
+    // Whole file without sourceMappingURL annotation, compressed at once.
+    pub total: CompressedSize,
+    // Bytes not attributed to any source.
+    pub unmapped: CompressedEstimate,
+}
+
+// Estimates compressed size of each group of ranges, e.g. all spans attributed to a single
+// source. Estimates of all groups add up to the compressed size of the whole file.
+pub fn estimate_compressed_sizes(
+    file_contents: &[u8],
+    ranges_by_group: &[Vec<Range<usize>>],
+    unmapped_ranges: &[Range<usize>],
+    excluded: Option<Range<usize>>,
+) -> (CompressionInfo, Vec<CompressedEstimate>) {
+    let excluded = excluded.unwrap_or(file_contents.len()..file_contents.len());
+    let total = compress_whole(file_contents, &[0..excluded.start, excluded.end..file_contents.len()]);
+
+    let groups = ranges_by_group
+        .iter()
+        .map(Vec::as_slice)
+        .chain([unmapped_ranges])
+        .collect::<Vec<_>>();
+    let standalone = groups
+        .iter()
+        .map(|ranges| compress_standalone(file_contents, ranges))
+        .collect::<Vec<_>>();
+
+    let standalone_sum = standalone
+        .iter()
+        .fold(CompressedSize::default(), |sum, size| CompressedSize {
+            gzip: sum.gzip + size.gzip,
+            brotli: sum.brotli + size.brotli,
+        });
+
+    let mut estimates = standalone
+        .iter()
+        .zip(groups)
+        .map(|(size, ranges)| CompressedEstimate {
+            size: CompressedSize {
+                gzip: scale(size.gzip, total.gzip, standalone_sum.gzip),
+                brotli: scale(size.brotli, total.brotli, standalone_sum.brotli),
+            },
+            accuracy: Accuracy::from_len(ranges.iter().map(|range| range.len()).sum()),
+        })
+        .collect::<Vec<_>>();
+
+    let unmapped = estimates.pop().unwrap();
+
+    (CompressionInfo { total, unmapped }, estimates)
+}
+
+fn scale(size: u64, total: u64, sum: u64) -> u64 {
+    match sum {
+        0 => 0,
+        _ => (size as u128 * total as u128 / sum as u128) as u64,
+    }
+}
+
+fn compress_whole(file_contents: &[u8], ranges: &[Range<usize>]) -> CompressedSize {
+    let len = ranges.iter().map(|range| range.len()).sum();
+
+    CompressedSize {
+        gzip: compressed_len(
+            GzEncoder::new(ByteCounter(0), Compression::new(GZIP_LEVEL)),
+            file_contents,
+            ranges,
+        ),
+        brotli: compress_brotli(file_contents, ranges, len),
+    }
+}
+
+// Headers and trailers of gzip streams are left out, as they are paid only once per file.
+fn compress_standalone(file_contents: &[u8], ranges: &[Range<usize>]) -> CompressedSize {
+    let len = ranges.iter().map(|range| range.len()).sum();
+
+    if len == 0 {
+        return CompressedSize::default();
+    }
+
+    CompressedSize {
+        gzip: compressed_len(
+            DeflateEncoder::new(ByteCounter(0), Compression::new(GZIP_LEVEL)),
+            file_contents,
+            ranges,
+        ),
+        brotli: compress_brotli(file_contents, ranges, len),
+    }
+}
+
+fn compress_brotli(file_contents: &[u8], ranges: &[Range<usize>], len: usize) -> u64 {
+    // Window is not bigger than the input, so that small inputs do not allocate huge buffers.
+    let window = (usize::BITS - len.leading_zeros()).clamp(10, BROTLI_MAX_WINDOW);
+
+    compressed_len(
+        BrotliEncoder(brotli::CompressorWriter::new(
+            ByteCounter(0),
+            4096,
+            BROTLI_QUALITY,
+            window,
+        )),
+        file_contents,
+        ranges,
+    )
+}
+
+fn compressed_len<E: Encoder>(mut encoder: E, file_contents: &[u8], ranges: &[Range<usize>]) -> u64 {
+    for range in ranges {
+        // Writing into a byte counter never fails.
+        encoder.write_all(&file_contents[range.clone()]).unwrap();
+    }
+
+    encoder.finish_counting()
+}
+
+trait Encoder: Write {
+    fn finish_counting(self) -> u64;
+}
+
+impl Encoder for GzEncoder<ByteCounter> {
+    fn finish_counting(self) -> u64 {
+        self.finish().unwrap().0
+    }
+}
+
+impl Encoder for DeflateEncoder<ByteCounter> {
+    fn finish_counting(self) -> u64 {
+        self.finish().unwrap().0
+    }
+}
+
+struct BrotliEncoder(brotli::CompressorWriter<ByteCounter>);
+
+impl Write for BrotliEncoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Encoder for BrotliEncoder {
+    fn finish_counting(self) -> u64 {
+        self.0.into_inner().0
+    }
+}
+
+// Writer that only counts bytes, compressed output itself is not needed.
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use crate::compression::{estimate_compressed_sizes, Accuracy};
+
+    #[test]
+    fn estimates_add_up_to_compressed_file_size() {
+        let contents = "function a(){return 1}\n".repeat(100) + &"x".repeat(5000) + "//# sourceMappingURL=a.js.map";
+        let len = contents.len();
+        let code_end = 2300;
+
+        let (info, estimates) = estimate_compressed_sizes(
+            contents.as_bytes(),
+            &[vec![0..1000, 1200..code_end], vec![1000..1100, 1100..1200]],
+            &[code_end..code_end + 2500, code_end + 2500..code_end + 5000],
+            Some(code_end + 5000..len),
+        );
+
+        let gzip_sum = estimates.iter().map(|estimate| estimate.size.gzip).sum::<u64>() + info.unmapped.size.gzip;
+        let brotli_sum = estimates.iter().map(|estimate| estimate.size.brotli).sum::<u64>() + info.unmapped.size.brotli;
+
+        // Rounding down loses at most a byte per estimate.
+        assert!(info.total.gzip - gzip_sum < 3, "{info:?} {estimates:?}");
+        assert!(info.total.brotli - brotli_sum < 3, "{info:?} {estimates:?}");
+        assert!(info.total.gzip < 300 && info.total.brotli < 300, "{info:?}");
+
+        let accuracies = estimates.iter().map(|estimate| estimate.accuracy).collect::<Vec<_>>();
+        assert_eq!(accuracies, [Accuracy::Medium, Accuracy::Low]);
+        assert_eq!(info.unmapped.accuracy, Accuracy::Medium);
+    }
+}
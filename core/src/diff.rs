@@ -0,0 +1,631 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs,
+    path::Path,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    analyze_path,
+    analyzer::{AnalyzeOptions, SourceMappingInfo},
+    discovery::{relative_path, DiscoveryOptions},
+    error::{Error, SnapshotErrorKind},
+    identity::{bundle_identity, match_bundles, IdentityOptions},
+    parser::normalize_path,
+};
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+// Bumped whenever saved snapshots stop being readable by the current version.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// Sizes of all bundles of a build, small enough to be saved and compared with a later build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    // Scanned path, bundle and source paths are relative to it.
+    pub root: String,
+    pub bundles: Vec<BundleSnapshot>,
+    // Bundles that could not be analyzed. They are left out of the diff together with their
+    // counterparts in the other build.
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleSnapshot {
+    pub file: String,
+    // Size of the generated file without sourceMappingURL annotation.
+    pub bytes: u64,
+    // Sorted by path.
+    pub sources: Vec<SourceSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceSnapshot {
+    pub path: String,
+    pub bytes: u64,
+}
+
+impl Snapshot {
+    pub fn new(root: &str) -> Self {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            root: root.to_owned(),
+            bundles: vec![],
+            failed: vec![],
+        }
+    }
+
+    pub fn add(&mut self, info: &SourceMappingInfo) {
+        let directory = self.source_directory();
+        let mut sources = BTreeMap::<String, u64>::new();
+
+        for file_info in &info.info_by_file {
+            let path = info.get_full_file_name(file_info.file);
+            // Paths resolved from URLs like webpack://app/./src/app.js are relative to the project
+            // and the same wherever the build is, other paths depend on the way it was scanned.
+            let path = match info.source_mapping.is_url_source(file_info.file) && !path.starts_with('/') {
+                true => path,
+                false => relative_source_path(&directory, &absolute_path(&path)),
+            };
+            *sources.entry(path).or_default() += file_info.bytes as u64;
+        }
+
+        self.bundles.push(BundleSnapshot {
+            file: relative_path(&self.root, &info.source_mapping.file).to_owned(),
+            bytes: info.source_mapping.actual_source_file_len(),
+            sources: sources
+                .into_iter()
+                .map(|(path, bytes)| SourceSnapshot { path, bytes })
+                .collect(),
+        });
+    }
+
+    pub fn add_failed(&mut self, file: &str) {
+        self.failed.push(relative_path(&self.root, file).to_owned());
+    }
+
+    // Path of the file the snapshot was read from is used only in errors.
+    pub fn from_json(json: &str, path: &str) -> Result<Self> {
+        let invalid = |kind| Error::InvalidSnapshot {
+            path: path.to_owned(),
+            kind,
+        };
+
+        let snapshot: Snapshot = serde_json::from_str(json).map_err(|err| invalid(SnapshotErrorKind::Json(err)))?;
+
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(invalid(SnapshotErrorKind::UnsupportedVersion(snapshot.version)));
+        }
+
+        Ok(snapshot)
+    }
+
+    // Serializing plain structs cannot fail.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // Directory sources are made relative to, the parent one when a single bundle was scanned.
+    fn source_directory(&self) -> String {
+        let root = Path::new(&self.root);
+
+        absolute_path(match root.is_file() {
+            true => root.parent().and_then(|parent| parent.to_str()).unwrap_or_default(),
+            false => &self.root,
+        })
+    }
+}
+
+pub fn snapshot_path(path: &str, options: &DiscoveryOptions, analyze_options: &AnalyzeOptions) -> Result<Snapshot> {
+    let mut snapshot = Snapshot::new(path);
+
+    analyze_path(path, options, analyze_options, |file, result| match result {
+        Ok(info) => snapshot.add(&info),
+        Err(_) => snapshot.add_failed(file),
+    })?;
+
+    Ok(snapshot)
+}
+
+// Saved snapshots are recognized by the .json extension, anything else is scanned for bundles.
+pub fn load_snapshot(path: &str, options: &DiscoveryOptions, analyze_options: &AnalyzeOptions) -> Result<Snapshot> {
+    let file = Path::new(path);
+
+    if file.is_file() && file.extension().is_some_and(|extension| extension == "json") {
+        let json = fs::read_to_string(file).map_err(|err| Error::io(path, err))?;
+        return Snapshot::from_json(&json, path);
+    }
+
+    snapshot_path(path, options, analyze_options)
+}
+
+// Relative paths are resolved against the working directory, so that scanning a build through
+// a relative or an absolute path gives the same results.
+fn absolute_path(path: &str) -> String {
+    let path = if path.is_empty() { "." } else { path };
+
+    match std::path::absolute(path).ok().as_deref().and_then(Path::to_str) {
+        Some(absolute) => normalize_path(absolute),
+        None => path.to_owned(),
+    }
+}
+
+// Source path relative to the scanned directory, so that builds checked out in different places
+// produce the same paths. Paths with a different base, like webpack URLs resolved relative to
+// the project, are kept as they are.
+fn relative_source_path(directory: &str, path: &str) -> String {
+    if directory.starts_with('/') != path.starts_with('/') {
+        return path.to_owned();
+    }
+
+    let directory = normalize_path(directory);
+    let directory = directory
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let path = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    let common = directory
+        .iter()
+        .zip(&path)
+        .take_while(|(directory, path)| directory == path)
+        .count();
+
+    vec![".."; directory.len() - common]
+        .into_iter()
+        .chain(path[common..].iter().copied())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Grown,
+    Shrunk,
+    Unchanged,
+}
+
+// Size of a bundle or a source in both builds, missing on the side it does not exist in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SizeChange {
+    pub kind: ChangeKind,
+    pub old: Option<u64>,
+    pub new: Option<u64>,
+    pub delta: i64,
+    // Percentage of the old size, missing for added bundles and sources.
+    pub relative: Option<f64>,
+}
+
+impl SizeChange {
+    pub fn new(old: Option<u64>, new: Option<u64>) -> Self {
+        let delta = new.unwrap_or_default() as i64 - old.unwrap_or_default() as i64;
+
+        let kind = match (old, new) {
+            (None, Some(_)) => ChangeKind::Added,
+            (Some(_), None) => ChangeKind::Removed,
+            _ if delta > 0 => ChangeKind::Grown,
+            _ if delta < 0 => ChangeKind::Shrunk,
+            _ => ChangeKind::Unchanged,
+        };
+
+        SizeChange {
+            kind,
+            old,
+            new,
+            delta,
+            relative: old.filter(|&old| old > 0).map(|old| delta as f64 / old as f64 * 100.0),
+        }
+    }
+
+    pub fn exceeds(&self, threshold: Threshold) -> bool {
+        match (threshold, self.relative) {
+            (Threshold::Bytes(bytes), _) => self.delta.unsigned_abs() > bytes,
+            (Threshold::Percent(percent), Some(relative)) => relative.abs() > percent,
+            (Threshold::Percent(_), None) => self.delta != 0,
+        }
+    }
+}
+
+// Changes not bigger than the threshold are left out of the report, totals still include them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    Bytes(u64),
+    Percent(f64),
+}
+
+impl FromStr for Threshold {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || Error::InvalidThreshold(value.to_owned());
+
+        match value.strip_suffix('%') {
+            Some(percent) => Ok(Threshold::Percent(percent.trim().parse().map_err(|_| invalid())?)),
+            None => Ok(Threshold::Bytes(value.trim().parse().map_err(|_| invalid())?)),
+        }
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Threshold::Bytes(bytes) => write!(f, "{bytes} B"),
+            Threshold::Percent(percent) => write!(f, "{percent}%"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourceDiff {
+    pub path: String,
+    pub change: SizeChange,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BundleDiff {
+    // Path with the content hash stripped, see identity module.
+    pub identity: String,
+    // Actual paths in both builds, missing on the side the bundle does not exist in.
+    pub old_file: Option<String>,
+    pub new_file: Option<String>,
+    // Share of sources in common, present only for bundles matched by their sources.
+    pub similarity: Option<f64>,
+    pub change: SizeChange,
+    // Only changed sources, biggest change first.
+    pub sources: Vec<SourceDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffSummary {
+    pub total: SizeChange,
+    pub added: usize,
+    pub removed: usize,
+    pub grown: usize,
+    pub shrunk: usize,
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffReport {
+    pub summary: DiffSummary,
+    // Bundles that changed or whose sources changed, biggest change first.
+    pub bundles: Vec<BundleDiff>,
+    pub old_failed: Vec<String>,
+    pub new_failed: Vec<String>,
+}
+
+impl DiffReport {
+    pub fn with_threshold(mut self, threshold: Threshold) -> Self {
+        for bundle in &mut self.bundles {
+            bundle.sources.retain(|source| source.change.exceeds(threshold));
+        }
+
+        self.bundles
+            .retain(|bundle| bundle.change.exceeds(threshold) || !bundle.sources.is_empty());
+
+        self
+    }
+
+    // Serializing plain structs cannot fail.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+// Bundles are matched by their identity, so that content hashes in file names do not matter.
+pub fn diff_snapshots(old: &Snapshot, new: &Snapshot, options: &IdentityOptions) -> DiffReport {
+    // Bundle that failed in one build would otherwise be reported as added or removed.
+    let failed = old
+        .failed
+        .iter()
+        .chain(&new.failed)
+        .map(|file| bundle_identity(file, &options.hash_patterns))
+        .collect::<HashSet<_>>();
+    let analyzed = |snapshot: &Snapshot| {
+        snapshot
+            .bundles
+            .iter()
+            .filter(|bundle| !failed.contains(&bundle_identity(&bundle.file, &options.hash_patterns)))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let (old_bundles, new_bundles) = (analyzed(old), analyzed(new));
+
+    let mut counts = HashMap::<ChangeKind, usize>::new();
+    let mut diffs = vec![];
+
+    for bundle_match in match_bundles(&old_bundles, &new_bundles, options) {
+        let old_bundle = bundle_match.old.map(|index| &old_bundles[index]);
+        let new_bundle = bundle_match.new.map(|index| &new_bundles[index]);
+
+        let change = SizeChange::new(
+            old_bundle.map(|bundle| bundle.bytes),
+            new_bundle.map(|bundle| bundle.bytes),
+        );
+        let sources = diff_sources(old_bundle, new_bundle);
+
+        *counts.entry(change.kind).or_default() += 1;
+
+        if change.kind != ChangeKind::Unchanged || !sources.is_empty() {
+            diffs.push(BundleDiff {
+                identity: bundle_match.identity,
+                old_file: old_bundle.map(|bundle| bundle.file.clone()),
+                new_file: new_bundle.map(|bundle| bundle.file.clone()),
+                similarity: bundle_match.similarity,
+                change,
+                sources,
+            });
+        }
+    }
+
+    diffs.sort_by_key(|diff| Reverse(diff.change.delta.unsigned_abs()));
+
+    let total_bytes = |bundles: &[BundleSnapshot]| bundles.iter().map(|bundle| bundle.bytes).sum();
+    let count = |kind| counts.get(&kind).copied().unwrap_or_default();
+
+    DiffReport {
+        summary: DiffSummary {
+            total: SizeChange::new(Some(total_bytes(&old_bundles)), Some(total_bytes(&new_bundles))),
+            added: count(ChangeKind::Added),
+            removed: count(ChangeKind::Removed),
+            grown: count(ChangeKind::Grown),
+            shrunk: count(ChangeKind::Shrunk),
+            unchanged: count(ChangeKind::Unchanged),
+        },
+        bundles: diffs,
+        old_failed: old.failed.clone(),
+        new_failed: new.failed.clone(),
+    }
+}
+
+fn diff_sources(old: Option<&BundleSnapshot>, new: Option<&BundleSnapshot>) -> Vec<SourceDiff> {
+    let mut sources = BTreeMap::<&str, (Option<u64>, Option<u64>)>::new();
+
+    for source in old.iter().flat_map(|bundle| &bundle.sources) {
+        sources.entry(&source.path).or_default().0 = Some(source.bytes);
+    }
+
+    for source in new.iter().flat_map(|bundle| &bundle.sources) {
+        sources.entry(&source.path).or_default().1 = Some(source.bytes);
+    }
+
+    let mut diffs = sources
+        .into_iter()
+        .map(|(path, (old, new))| SourceDiff {
+            path: path.to_owned(),
+            change: SizeChange::new(old, new),
+        })
+        .filter(|diff| diff.change.kind != ChangeKind::Unchanged)
+        .collect::<Vec<_>>();
+
+    diffs.sort_by_key(|diff| Reverse(diff.change.delta.unsigned_abs()));
+
+    diffs
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        analyzer::AnalyzeOptions,
+        diff::{
+            diff_snapshots, relative_source_path, snapshot_path, BundleSnapshot, ChangeKind, Snapshot, SourceSnapshot,
+            Threshold,
+        },
+        discovery::DiscoveryOptions,
+        error::{Error, ErrorKind, SnapshotErrorKind},
+        identity::IdentityOptions,
+        test_utils::TempDir,
+    };
+
+    fn bundle(file: &str, sources: &[(&str, u64)]) -> BundleSnapshot {
+        BundleSnapshot {
+            file: file.to_owned(),
+            bytes: sources.iter().map(|(_, bytes)| bytes).sum::<u64>() + 10,
+            sources: sources
+                .iter()
+                .map(|(path, bytes)| SourceSnapshot {
+                    path: path.to_string(),
+                    bytes: *bytes,
+                })
+                .collect(),
+        }
+    }
+
+    fn snapshot(bundles: Vec<BundleSnapshot>) -> Snapshot {
+        Snapshot {
+            bundles,
+            ..Snapshot::new("dist")
+        }
+    }
+
+    #[test]
+    fn diffs_bundles_and_sources() {
+        let old = snapshot(vec![
+            bundle(
+                "main.js",
+                &[("src/app.js", 100), ("src/old.js", 40), ("src/same.js", 50)],
+            ),
+            bundle("legacy.js", &[("src/legacy.js", 20)]),
+            bundle("vendor.1111aaaa.js", &[("node_modules/a/index.js", 200)]),
+        ]);
+        let new = snapshot(vec![
+            bundle(
+                "main.js",
+                &[("src/app.js", 150), ("src/new.js", 5), ("src/same.js", 50)],
+            ),
+            bundle("chunk.js", &[("src/chunk.js", 30)]),
+            bundle("vendor.2222bbbb.js", &[("node_modules/a/index.js", 200)]),
+        ]);
+
+        let report = diff_snapshots(&old, &new, &IdentityOptions::default());
+        let summary = &report.summary;
+
+        assert_eq!((summary.total.old, summary.total.new), (Some(440), Some(465)));
+        assert_eq!(
+            (
+                summary.added,
+                summary.removed,
+                summary.grown,
+                summary.shrunk,
+                summary.unchanged
+            ),
+            (1, 1, 1, 0, 1)
+        );
+
+        let changes = report
+            .bundles
+            .iter()
+            .map(|bundle| (bundle.identity.as_str(), bundle.change.kind, bundle.change.delta))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                ("chunk.js", ChangeKind::Added, 40),
+                ("legacy.js", ChangeKind::Removed, -30),
+                ("main.js", ChangeKind::Grown, 15),
+            ]
+        );
+
+        let main = &report.bundles[2];
+        let sources = main
+            .sources
+            .iter()
+            .map(|source| (source.path.as_str(), source.change.kind, source.change.delta))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            vec![
+                ("src/app.js", ChangeKind::Grown, 50),
+                ("src/old.js", ChangeKind::Removed, -40),
+                ("src/new.js", ChangeKind::Added, 5),
+            ]
+        );
+        assert_eq!(main.sources[0].change.relative, Some(50.0));
+        assert_eq!(main.sources[2].change.relative, None);
+
+        let report = report.with_threshold("35".parse().unwrap());
+        let files = report
+            .bundles
+            .iter()
+            .map(|bundle| bundle.identity.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(files, vec!["chunk.js", "main.js"]);
+        assert_eq!(report.bundles[1].sources.len(), 2);
+
+        let report = diff_snapshots(&old, &new, &IdentityOptions::default()).with_threshold("20%".parse().unwrap());
+        assert_eq!(report.bundles.len(), 3);
+        assert_eq!(report.bundles[2].sources.len(), 3);
+        assert_eq!("2.5 %".parse::<Threshold>().unwrap(), Threshold::Percent(2.5));
+        assert_eq!(
+            "ten".parse::<Threshold>().unwrap_err().kind(),
+            ErrorKind::InvalidThreshold
+        );
+    }
+
+    #[test]
+    fn leaves_out_bundles_failed_in_either_build() {
+        let old = Snapshot {
+            failed: vec!["admin.1111aaaa.js".to_owned()],
+            ..snapshot(vec![
+                bundle("main.js", &[("src/app.js", 100)]),
+                bundle("legacy.js", &[("src/legacy.js", 20)]),
+            ])
+        };
+        let new = Snapshot {
+            failed: vec!["legacy.js".to_owned()],
+            ..snapshot(vec![
+                bundle("main.js", &[("src/app.js", 110)]),
+                bundle("admin.2222bbbb.js", &[("src/admin.js", 50)]),
+            ])
+        };
+
+        let report = diff_snapshots(&old, &new, &IdentityOptions::default());
+        let summary = &report.summary;
+
+        assert_eq!((summary.total.old, summary.total.new), (Some(110), Some(120)));
+        assert_eq!((summary.added, summary.removed, summary.grown), (0, 0, 1));
+        assert_eq!(report.bundles.len(), 1);
+        assert_eq!(report.bundles[0].identity, "main.js");
+        assert_eq!(report.old_failed, ["admin.1111aaaa.js"]);
+        assert_eq!(report.new_failed, ["legacy.js"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn snapshots_the_same_build_through_relative_and_absolute_paths() {
+        let dir = TempDir::new("diff-roots");
+        let dist = dir.join("dist");
+        fs::create_dir_all(&dist).unwrap();
+        fs::write(dist.join("main.js"), "a=1;\nb=2;\n//# sourceMappingURL=main.js.map").unwrap();
+        fs::write(
+            dist.join("main.js.map"),
+            r#"{"version":3,"sources":["webpack://app/./src/app.js","../src/util.js"],"names":[],"mappings":"AAAA;ACAA"}"#,
+        )
+        .unwrap();
+
+        let absolute = dist.to_str().unwrap().to_owned();
+        // Goes up from the working directory to the file system root first.
+        let depth = std::env::current_dir().unwrap().components().count() - 1;
+        let relative = format!("{}{}", "../".repeat(depth), absolute.trim_start_matches('/'));
+
+        let snapshot =
+            |path: &str| snapshot_path(path, &DiscoveryOptions::default(), &AnalyzeOptions::default()).unwrap();
+        let (old, new) = (snapshot(&relative), snapshot(&absolute));
+
+        assert!(old.failed.is_empty());
+        assert_eq!(
+            old.bundles[0]
+                .sources
+                .iter()
+                .map(|source| source.path.as_str())
+                .collect::<Vec<_>>(),
+            ["../src/util.js", "src/app.js"]
+        );
+        assert_eq!(old.bundles, new.bundles);
+        assert!(diff_snapshots(&old, &new, &IdentityOptions::default())
+            .bundles
+            .is_empty());
+    }
+
+    #[test]
+    fn reads_snapshots_of_current_version_only() {
+        let snapshot = snapshot(vec![bundle("main.js", &[("src/app.js", 100)])]);
+        assert_eq!(Snapshot::from_json(&snapshot.to_json(), "new.json").unwrap(), snapshot);
+
+        let json = snapshot.to_json().replacen("\"version\": 1", "\"version\": 0", 1);
+        assert!(matches!(
+            Snapshot::from_json(&json, "old.json"),
+            Err(Error::InvalidSnapshot {
+                kind: SnapshotErrorKind::UnsupportedVersion(0),
+                ..
+            })
+        ));
+
+        let error = Snapshot::from_json("{", "broken.json").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidSnapshot);
+        assert!(
+            error.to_string().starts_with("Snapshot broken.json is not valid JSON"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn makes_source_paths_relative_to_scanned_directory() {
+        assert_eq!(relative_source_path("/a/dist", "/a/src/app.js"), "../src/app.js");
+        assert_eq!(relative_source_path("/a/dist/", "/a/dist/app.js"), "app.js");
+        assert_eq!(relative_source_path("./dist", "src/app.js"), "../src/app.js");
+        assert_eq!(relative_source_path("/a/dist", "src/app.js"), "src/app.js");
+    }
+}
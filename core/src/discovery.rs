@@ -0,0 +1,263 @@
+use std::path::Path;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+
+use super::error::Error;
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+// Kinds of generated files that are picked up when walking directories.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BundleKind {
+    #[default]
+    JavaScript,
+    Css,
+    WebAssembly,
+}
+
+impl BundleKind {
+    pub fn from_path(path: &Path) -> Option<BundleKind> {
+        match path.extension()?.to_str()? {
+            "js" | "mjs" | "cjs" => Some(BundleKind::JavaScript),
+            "css" => Some(BundleKind::Css),
+            "wasm" => Some(BundleKind::WebAssembly),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BundleKind::JavaScript => "js",
+            BundleKind::Css => "css",
+            BundleKind::WebAssembly => "wasm",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscoveryOptions {
+    // Globs matched against paths relative to the scan root, when not empty only matching
+    // bundles are kept.
+    pub include: Vec<String>,
+    // Globs matched against paths relative to the scan root, matching directories are not entered.
+    pub exclude: Vec<String>,
+    // Depth of 1 means only files placed directly in the scan root are checked.
+    pub max_depth: Option<usize>,
+    // Skips paths ignored by .gitignore files found within the scan root.
+    pub respect_gitignore: bool,
+    // Follows sourceMappingURL annotations pointing outside of the scan root, e.g. absolute paths
+    // and file URLs.
+    pub allow_external_maps: bool,
+}
+
+impl Default for DiscoveryOptions {
+    fn default() -> Self {
+        DiscoveryOptions {
+            include: vec![],
+            exclude: vec![],
+            max_depth: None,
+            respect_gitignore: true,
+            allow_external_maps: false,
+        }
+    }
+}
+
+impl DiscoveryOptions {
+    // Directory source maps of bundles found in the scanned path have to be located in.
+    pub fn map_root<'a>(&self, path: &'a str) -> Option<&'a str> {
+        (!self.allow_external_maps).then_some(path)
+    }
+}
+
+pub fn discover_files(path: &str, options: &DiscoveryOptions) -> Result<Vec<String>> {
+    let path_meta = std::fs::metadata(path).map_err(|err| Error::io(path, err))?;
+
+    if !path_meta.is_dir() {
+        return Ok(vec![path.to_owned()]);
+    }
+
+    let include = build_glob_set(&options.include)?;
+    let exclude = build_glob_set(&options.exclude)?;
+
+    let root = Path::new(path).to_owned();
+    let mut walker = WalkBuilder::new(path);
+    walker
+        .standard_filters(false)
+        .git_ignore(options.respect_gitignore)
+        // .gitignore files outside of the scan root usually exclude the very build output we
+        // are asked to check, so only the ones within it are taken into account.
+        .parents(false)
+        .require_git(false)
+        // Symbolic links are followed, walker detects loops and reports them as errors.
+        .follow_links(true)
+        .max_depth(options.max_depth)
+        .filter_entry(move |entry| match entry.path().strip_prefix(&root) {
+            Ok(relative_path) => relative_path.as_os_str().is_empty() || !exclude.is_match(relative_path),
+            Err(_) => true,
+        });
+
+    let mut files_to_check: Vec<String> = vec![];
+
+    // Entries that cannot be read, including symbolic link loops, are skipped.
+    for entry in walker.build().flatten() {
+        if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+            continue;
+        }
+
+        let file_path = entry.path();
+
+        if BundleKind::from_path(file_path).is_none() {
+            continue;
+        }
+
+        if !options.include.is_empty() {
+            let relative_path = file_path.strip_prefix(path).unwrap_or(file_path);
+            if !include.is_match(relative_path) {
+                continue;
+            }
+        }
+
+        // Paths that are not valid UTF-8 cannot be presented, so they are skipped.
+        if let Some(file_path) = file_path.to_str() {
+            files_to_check.push(file_path.to_owned());
+        }
+    }
+
+    files_to_check.sort();
+
+    Ok(files_to_check)
+}
+
+// Path of a discovered file relative to the scan root. When the scan root is the file itself,
+// its file name is returned instead.
+pub fn relative_path<'a>(root: &str, file: &'a str) -> &'a str {
+    match Path::new(file).strip_prefix(root).ok().and_then(|path| path.to_str()) {
+        Some(relative_path) if !relative_path.is_empty() => relative_path,
+        _ => Path::new(file)
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or(file),
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|source| invalid_glob(pattern, source))?);
+    }
+
+    builder
+        .build()
+        .map_err(|source| invalid_glob(&patterns.join(" "), source))
+}
+
+fn invalid_glob(pattern: &str, source: globset::Error) -> Error {
+    Error::InvalidGlob {
+        pattern: pattern.to_owned(),
+        source,
+    }
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use std::{fs, path::Path};
+
+    use crate::{
+        discovery::{discover_files, relative_path, DiscoveryOptions},
+        error::ErrorKind,
+        test_utils::TempDir,
+    };
+
+    fn create_tree(name: &str, files: &[&str]) -> TempDir {
+        let dir = TempDir::new(&format!("discovery-{name}"));
+
+        for file in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        dir
+    }
+
+    fn discover(dir: &Path, options: &DiscoveryOptions) -> Vec<String> {
+        let root = dir.to_str().unwrap();
+
+        discover_files(root, options)
+            .unwrap()
+            .iter()
+            .map(|file| relative_path(root, file).to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn discovers_nested_bundles() {
+        let dir = create_tree(
+            "nested",
+            &[
+                ".gitignore",
+                "main.js",
+                "main.js.map",
+                "assets/chunk.mjs",
+                "static/js/legacy.cjs",
+                "_next/static/chunks/page.js",
+                "_next/static/chunks/page.css",
+                "node_modules/lib/index.js",
+                "ignored/skipped.js",
+            ],
+        );
+        fs::write(dir.join(".gitignore"), "ignored/\n").unwrap();
+
+        assert_eq!(
+            discover(&dir, &DiscoveryOptions::default()),
+            [
+                "_next/static/chunks/page.css",
+                "_next/static/chunks/page.js",
+                "assets/chunk.mjs",
+                "main.js",
+                "node_modules/lib/index.js",
+                "static/js/legacy.cjs",
+            ]
+        );
+
+        let options = DiscoveryOptions {
+            include: vec!["**/*.js".to_owned()],
+            exclude: vec!["node_modules".to_owned(), "_next".to_owned()],
+            max_depth: None,
+            respect_gitignore: false,
+            allow_external_maps: false,
+        };
+        assert_eq!(discover(&dir, &options), ["ignored/skipped.js", "main.js"]);
+
+        let options = DiscoveryOptions {
+            max_depth: Some(2),
+            ..DiscoveryOptions::default()
+        };
+        assert_eq!(discover(&dir, &options), ["assets/chunk.mjs", "main.js"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn survives_symlink_loops() {
+        let dir = create_tree("symlinks", &["assets/main.js"]);
+        std::os::unix::fs::symlink(&*dir, dir.join("assets/loop")).unwrap();
+
+        assert_eq!(discover(&dir, &DiscoveryOptions::default()), ["assets/main.js"]);
+    }
+
+    #[test]
+    fn reports_invalid_globs() {
+        let options = DiscoveryOptions {
+            include: vec!["a[".to_owned()],
+            ..DiscoveryOptions::default()
+        };
+
+        let error = discover_files(std::env::temp_dir().to_str().unwrap(), &options).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidGlob, "{error}");
+
+        let error = discover_files("/nonexistent/dist", &DiscoveryOptions::default()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Io, "{error}");
+    }
+}
@@ -0,0 +1,472 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use super::{
+    analyzer::SourceMappingInfo,
+    packages::{find_package, Package},
+};
+
+// Single copy of a package or a module, identified by its path. Bytes are summed over all bundles
+// the copy was found in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceCopy {
+    pub path: String,
+    pub bytes: u64,
+    // Generated files, in the order they were analyzed.
+    pub bundles: Vec<String>,
+}
+
+impl SourceCopy {
+    fn new(path: String) -> Self {
+        Self {
+            path,
+            bytes: 0,
+            bundles: vec![],
+        }
+    }
+
+    fn add(&mut self, bundle: &str, bytes: u64) {
+        self.bytes += bytes;
+
+        if self.bundles.last().map(String::as_str) != Some(bundle) {
+            self.bundles.push(bundle.to_owned());
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageCopy {
+    pub package: Package,
+    pub copy: SourceCopy,
+}
+
+// Package installed in more than one folder, e.g. nested in another package or in several
+// versions with pnpm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicatePackage {
+    pub name: String,
+    // Largest copy first.
+    pub copies: Vec<PackageCopy>,
+    // Bytes of all copies but the largest one.
+    pub wasted_bytes: u64,
+}
+
+// Source with the same content under different paths, found by hashing "sourcesContent".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateModule {
+    // Largest copy first.
+    pub copies: Vec<SourceCopy>,
+    pub wasted_bytes: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct DuplicateReport {
+    // Most wasted bytes first.
+    pub packages: Vec<DuplicatePackage>,
+    pub modules: Vec<DuplicateModule>,
+}
+
+impl DuplicateReport {
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty() && self.modules.is_empty()
+    }
+
+    pub fn wasted_bytes(&self) -> u64 {
+        let package_bytes = self.packages.iter().map(|package| package.wasted_bytes).sum::<u64>();
+        let module_bytes = self.modules.iter().map(|module| module.wasted_bytes).sum::<u64>();
+
+        package_bytes + module_bytes
+    }
+}
+
+// Sources with the same content. The content is kept to tell apart sources whose hashes collide.
+#[derive(Debug)]
+struct ModuleCopies {
+    content: String,
+    // Source path -> copy and the name of the package the source belongs to.
+    copies: BTreeMap<String, (SourceCopy, Option<String>)>,
+}
+
+// Copies of packages and modules collected from any number of bundles.
+#[derive(Debug, Default)]
+pub struct DuplicateIndex {
+    // Package name -> package folder -> copy.
+    packages: HashMap<String, BTreeMap<String, PackageCopy>>,
+    // Content length and hash -> copies of each distinct content.
+    modules: HashMap<(usize, u64), Vec<ModuleCopies>>,
+}
+
+impl DuplicateIndex {
+    pub fn add(&mut self, info: &SourceMappingInfo) {
+        let bundle = info.source_mapping.file.as_str();
+
+        for file_info in &info.info_by_file {
+            let path = info.get_full_file_name(file_info.file);
+            let bytes = file_info.bytes as u64;
+            let package = find_package(&path);
+
+            if let Some(package) = &package {
+                self.packages
+                    .entry(package.name.clone())
+                    .or_default()
+                    .entry(package.path.clone())
+                    .or_insert_with(|| PackageCopy {
+                        package: package.clone(),
+                        copy: SourceCopy::new(package.path.clone()),
+                    })
+                    .copy
+                    .add(bundle, bytes);
+            }
+
+            let Some(content) = info
+                .source_mapping
+                .source_content(file_info.file)
+                .filter(|c| !c.is_empty())
+            else {
+                continue;
+            };
+
+            let mut hasher = DefaultHasher::new();
+            content.hash(&mut hasher);
+
+            self.module_copies((content.len(), hasher.finish()), &content)
+                .entry(path.clone())
+                .or_insert_with(|| (SourceCopy::new(path), package.map(|package| package.name)))
+                .0
+                .add(bundle, bytes);
+        }
+    }
+
+    fn module_copies(
+        &mut self,
+        key: (usize, u64),
+        content: &str,
+    ) -> &mut BTreeMap<String, (SourceCopy, Option<String>)> {
+        let modules = self.modules.entry(key).or_default();
+
+        let index = match modules.iter().position(|module| module.content == content) {
+            Some(index) => index,
+            None => {
+                modules.push(ModuleCopies {
+                    content: content.to_owned(),
+                    copies: BTreeMap::new(),
+                });
+                modules.len() - 1
+            }
+        };
+
+        &mut modules[index].copies
+    }
+
+    pub fn report(&self) -> DuplicateReport {
+        let mut packages = self
+            .packages
+            .iter()
+            .filter(|(_, copies)| copies.len() > 1)
+            .map(|(name, copies)| {
+                let mut copies = copies.values().cloned().collect::<Vec<_>>();
+                copies.sort_by_key(|copy| Reverse(copy.copy.bytes));
+
+                DuplicatePackage {
+                    name: name.clone(),
+                    wasted_bytes: wasted_bytes(copies.iter().map(|copy| copy.copy.bytes)),
+                    copies,
+                }
+            })
+            .collect::<Vec<_>>();
+        packages.sort_by(|a, b| b.wasted_bytes.cmp(&a.wasted_bytes).then_with(|| a.name.cmp(&b.name)));
+
+        let mut modules = self
+            .modules
+            .values()
+            .flatten()
+            .map(|module| &module.copies)
+            .filter(|copies| copies.len() > 1)
+            // Copies of a duplicated package are already reported with the package.
+            .filter(|copies| {
+                let first_package = &copies.values().next().unwrap().1;
+                let is_duplicated_package = first_package
+                    .as_ref()
+                    .is_some_and(|name| packages.iter().any(|package| package.name == *name));
+
+                !is_duplicated_package || copies.values().any(|(_, package)| package != first_package)
+            })
+            .map(|copies| {
+                let mut copies = copies.values().map(|(copy, _)| copy.clone()).collect::<Vec<_>>();
+                copies.sort_by_key(|copy| Reverse(copy.bytes));
+
+                DuplicateModule {
+                    wasted_bytes: wasted_bytes(copies.iter().map(|copy| copy.bytes)),
+                    copies,
+                }
+            })
+            .collect::<Vec<_>>();
+        modules.sort_by(|a, b| {
+            b.wasted_bytes
+                .cmp(&a.wasted_bytes)
+                .then_with(|| a.copies[0].path.cmp(&b.copies[0].path))
+        });
+
+        DuplicateReport { packages, modules }
+    }
+}
+
+// Bundle a source was compiled into, with bytes attributed to the source there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceOccurrence {
+    pub bundle: String,
+    pub bytes: u64,
+}
+
+// Source compiled into more than one bundle, usually a module that belongs into a shared chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedSource {
+    pub path: String,
+    // Largest occurrence first.
+    pub occurrences: Vec<SourceOccurrence>,
+    // Bytes of all occurrences but the largest one.
+    pub duplicated_bytes: u64,
+}
+
+// Every bundle containing each source, keyed by resolved source path.
+#[derive(Debug, Default)]
+pub struct SourceIndex {
+    sources: HashMap<String, Vec<SourceOccurrence>>,
+}
+
+impl SourceIndex {
+    pub fn add(&mut self, info: &SourceMappingInfo) {
+        let bundle = info.source_mapping.file.as_str();
+
+        for file_info in &info.info_by_file {
+            // Sources listed in the source map without any mapping are not part of the bundle.
+            if file_info.bytes == 0 {
+                continue;
+            }
+
+            let occurrences = self.sources.entry(info.get_full_file_name(file_info.file)).or_default();
+
+            // Source map can list the same source more than once.
+            match occurrences.last_mut() {
+                Some(occurrence) if occurrence.bundle == bundle => occurrence.bytes += file_info.bytes as u64,
+                _ => occurrences.push(SourceOccurrence {
+                    bundle: bundle.to_owned(),
+                    bytes: file_info.bytes as u64,
+                }),
+            }
+        }
+    }
+
+    // Bundles containing the source, in the order they were added.
+    pub fn bundles(&self, path: &str) -> &[SourceOccurrence] {
+        self.sources.get(path).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    // Sources found in two or more bundles, most duplicated bytes first.
+    pub fn shared_sources(&self) -> Vec<SharedSource> {
+        let mut shared_sources = self
+            .sources
+            .iter()
+            .filter(|(_, occurrences)| occurrences.len() > 1)
+            .map(|(path, occurrences)| {
+                let mut occurrences = occurrences.clone();
+                occurrences.sort_by_key(|occurrence| Reverse(occurrence.bytes));
+
+                SharedSource {
+                    path: path.clone(),
+                    duplicated_bytes: wasted_bytes(occurrences.iter().map(|occurrence| occurrence.bytes)),
+                    occurrences,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        shared_sources.sort_by(|a, b| {
+            b.duplicated_bytes
+                .cmp(&a.duplicated_bytes)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+
+        shared_sources
+    }
+}
+
+pub fn index_sources<'info>(infos: impl IntoIterator<Item = &'info SourceMappingInfo>) -> SourceIndex {
+    let mut index = SourceIndex::default();
+
+    for info in infos {
+        index.add(info);
+    }
+
+    index
+}
+
+fn wasted_bytes(sizes: impl Iterator<Item = u64>) -> u64 {
+    let (sum, max) = sizes.fold((0, 0), |(sum, max), bytes| (sum + bytes, max.max(bytes)));
+    sum - max
+}
+
+pub fn find_duplicates<'info>(infos: impl IntoIterator<Item = &'info SourceMappingInfo>) -> DuplicateReport {
+    let mut index = DuplicateIndex::default();
+
+    for info in infos {
+        index.add(info);
+    }
+
+    index.report()
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use crate::{
+        duplicates::{find_duplicates, index_sources, DuplicateIndex, SourceCopy, SourceOccurrence},
+        test_utils::analyze_bundle,
+    };
+
+    #[test]
+    fn finds_duplicated_packages_and_modules() {
+        let info = analyze_bundle(
+            "main.js",
+            "var a=1;\nvar b=22;\nvar c=333;\nvar d=4444;\n",
+            &[
+                ("node_modules/lodash/map.js", "map"),
+                ("node_modules/some-lib/node_modules/lodash/map.js", "map"),
+                ("src/utils/format.js", "format"),
+                ("src/legacy/format.js", "format"),
+            ],
+        );
+        let report = find_duplicates([&info]);
+
+        assert_eq!(report.packages.len(), 1);
+        let lodash = &report.packages[0];
+        assert_eq!(lodash.name, "lodash");
+        assert_eq!(
+            lodash
+                .copies
+                .iter()
+                .map(|copy| (copy.copy.path.as_str(), copy.copy.bytes))
+                .collect::<Vec<_>>(),
+            [
+                ("dist/node_modules/some-lib/node_modules/lodash", 10),
+                ("dist/node_modules/lodash", 9)
+            ]
+        );
+        assert_eq!(lodash.wasted_bytes, 9);
+        assert_eq!(lodash.copies[0].copy.bundles, ["dist/main.js"]);
+
+        // Identical lodash modules are reported only as a duplicated package.
+        assert_eq!(report.modules.len(), 1);
+        assert_eq!(
+            report.modules[0]
+                .copies
+                .iter()
+                .map(|copy| copy.path.as_str())
+                .collect::<Vec<_>>(),
+            ["dist/src/legacy/format.js", "dist/src/utils/format.js"]
+        );
+        assert_eq!(report.modules[0].wasted_bytes, 11);
+        assert_eq!(report.wasted_bytes(), 20);
+    }
+
+    #[test]
+    fn reports_modules_duplicated_within_a_single_package_copy() {
+        let info = analyze_bundle(
+            "main.js",
+            "var a=1;\nvar b=22;\n",
+            &[
+                ("node_modules/lodash/map.js", "map"),
+                ("node_modules/lodash/fp/map.js", "map"),
+            ],
+        );
+        let report = find_duplicates([&info]);
+
+        assert!(report.packages.is_empty());
+        assert_eq!(report.modules.len(), 1);
+        assert_eq!(
+            report.modules[0]
+                .copies
+                .iter()
+                .map(|copy| copy.path.as_str())
+                .collect::<Vec<_>>(),
+            ["dist/node_modules/lodash/fp/map.js", "dist/node_modules/lodash/map.js"]
+        );
+    }
+
+    #[test]
+    fn indexes_sources_shared_between_bundles() {
+        let sources = [("src/a.js", "a"), ("src/b.js", "b")];
+        let first = analyze_bundle("first.js", "var a=1;\nvar b=22;\n", &sources);
+        let second = analyze_bundle("second.js", "var a=1;\n", &sources[..1]);
+        let third = analyze_bundle("third.js", "var aa=1;\n", &sources[..1]);
+
+        let index = index_sources([&first, &second, &third]);
+
+        assert_eq!(
+            index.bundles("dist/src/b.js"),
+            [SourceOccurrence {
+                bundle: "dist/first.js".to_owned(),
+                bytes: 10
+            }]
+        );
+
+        let shared_sources = index.shared_sources();
+        assert_eq!(shared_sources.len(), 1);
+        assert_eq!(shared_sources[0].path, "dist/src/a.js");
+        assert_eq!(
+            shared_sources[0]
+                .occurrences
+                .iter()
+                .map(|occurrence| (occurrence.bundle.as_str(), occurrence.bytes))
+                .collect::<Vec<_>>(),
+            [("dist/third.js", 10), ("dist/first.js", 9), ("dist/second.js", 9)]
+        );
+        assert_eq!(shared_sources[0].duplicated_bytes, 18);
+    }
+
+    #[test]
+    fn ignores_sources_without_bytes() {
+        let sources = [("src/a.js", "a"), ("src/b.js", "b")];
+        let first = analyze_bundle("first.js", "var a=1;\nvar b=22;\n", &sources);
+        let mut second = analyze_bundle("second.js", "var a=1;\nvar b=22;\n", &sources);
+        second.info_by_file[1].bytes = 0;
+
+        let index = index_sources([&first, &second]);
+
+        assert_eq!(index.bundles("dist/src/b.js").len(), 1);
+        assert_eq!(
+            index
+                .shared_sources()
+                .iter()
+                .map(|source| source.path.as_str())
+                .collect::<Vec<_>>(),
+            ["dist/src/a.js"]
+        );
+    }
+
+    #[test]
+    fn tells_apart_modules_with_colliding_hashes() {
+        let mut index = DuplicateIndex::default();
+
+        for (path, content) in [("a.js", "a"), ("b.js", "b"), ("c.js", "a")] {
+            index
+                .module_copies((1, 0), content)
+                .entry(path.to_owned())
+                .or_insert_with(|| (SourceCopy::new(path.to_owned()), None))
+                .0
+                .add("main.js", 1);
+        }
+
+        let report = index.report();
+        assert_eq!(report.modules.len(), 1);
+        assert_eq!(
+            report.modules[0]
+                .copies
+                .iter()
+                .map(|copy| copy.path.as_str())
+                .collect::<Vec<_>>(),
+            ["a.js", "c.js"]
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use std::{fmt, io};
+
+use super::{analyzer::AnalyzeError, diff::SNAPSHOT_VERSION, vlq::VlqError, wasm::WasmError};
+
+// Everything that can go wrong when parsing or analyzing a single bundle, and when reading options
+// and snapshots of a whole scan. Positions are kept in typed fields, so that failures can be
+// grouped and explained without parsing messages.
+#[derive(Debug)]
+pub enum Error {
+    // Generated file or source map could not be read.
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    // Annotation references a source map file that does not exist.
+    MissingMapFile {
+        path: String,
+        map_file: String,
+    },
+    // Source map was given directly, but neither its "file" field nor its name leads to a file.
+    MissingGeneratedFile {
+        map_file: String,
+    },
+    UnsupportedAnnotation {
+        path: String,
+        kind: AnnotationErrorKind,
+    },
+    InvalidBase64 {
+        path: String,
+        source: base64::DecodeError,
+    },
+    InvalidJson {
+        map_file: String,
+        source: serde_json::Error,
+    },
+    InvalidVlq(VlqError),
+    // Indexed source map whose sections cannot be merged, position is the offset of the section.
+    InvalidSection {
+        line: u32,
+        column: u32,
+        kind: SectionErrorKind,
+    },
+    InconsistentMapping(AnalyzeError),
+    InvalidWasm(WasmError),
+    // Include or exclude pattern given for discovery.
+    InvalidGlob {
+        pattern: String,
+        source: globset::Error,
+    },
+    InvalidHashPattern {
+        pattern: String,
+        kind: HashPatternErrorKind,
+    },
+    // Holds the value given as threshold of reported changes.
+    InvalidThreshold(String),
+    InvalidSnapshot {
+        path: String,
+        kind: SnapshotErrorKind,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnnotationErrorKind {
+    EmptyFile,
+    // Neither sourceMappingURL annotation nor a source map next to the bundle was found, holds
+    // the last line of the file.
+    NotFound(String),
+    // WebAssembly module without sourceMappingURL section or a source map next to it.
+    MissingWasmSection,
+    NonLocalUrl(String),
+    // Annotation points outside of the directory source maps are allowed to be read from.
+    OutsideRoot(String),
+    MissingData,
+    UnsupportedMediaType(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SectionErrorKind {
+    NotOrdered { previous_line: u32, previous_column: u32 },
+    SourceOutOfRange { source: u32, sources_len: usize },
+    NameOutOfRange { name: u32, names_len: usize },
+    // Mapping at the given position does not fit into 32 bits once shifted by the section offset.
+    Overflow { line: u32, column: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HashPatternErrorKind {
+    UnclosedPlaceholder,
+    UnknownPlaceholder(String),
+    // Pattern has to contain exactly one hash placeholder, holds the number found.
+    HashCount(usize),
+}
+
+#[derive(Debug)]
+pub enum SnapshotErrorKind {
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+}
+
+// Coarse classification of errors, used for grouping failing bundles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    Io,
+    MissingMapFile,
+    MissingGeneratedFile,
+    UnsupportedAnnotation,
+    InvalidBase64,
+    InvalidJson,
+    InvalidVlq,
+    InvalidSection,
+    InconsistentMapping,
+    InvalidWasm,
+    InvalidGlob,
+    InvalidHashPattern,
+    InvalidThreshold,
+    InvalidSnapshot,
+}
+
+impl ErrorKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorKind::Io => "I/O error",
+            ErrorKind::MissingMapFile => "missing map file",
+            ErrorKind::MissingGeneratedFile => "missing generated file",
+            ErrorKind::UnsupportedAnnotation => "unsupported annotation",
+            ErrorKind::InvalidBase64 => "invalid base64",
+            ErrorKind::InvalidJson => "invalid JSON",
+            ErrorKind::InvalidVlq => "invalid VLQ",
+            ErrorKind::InvalidSection => "invalid section",
+            ErrorKind::InconsistentMapping => "inconsistent mapping",
+            ErrorKind::InvalidWasm => "invalid WebAssembly",
+            ErrorKind::InvalidGlob => "invalid glob",
+            ErrorKind::InvalidHashPattern => "invalid hash pattern",
+            ErrorKind::InvalidThreshold => "invalid threshold",
+            ErrorKind::InvalidSnapshot => "invalid snapshot",
+        }
+    }
+
+    // Short suggestion of what usually causes the error and how to fix it.
+    pub fn hint(self) -> &'static str {
+        match self {
+            ErrorKind::Io => "check that the file still exists and is readable",
+            ErrorKind::MissingMapFile => "source map was not emitted or was removed after the build",
+            ErrorKind::MissingGeneratedFile => "set \"file\" field or name the source map after the generated file",
+            ErrorKind::UnsupportedAnnotation => {
+                "enable source maps in the bundler or keep a .map file next to the bundle"
+            }
+            ErrorKind::InvalidBase64 => "inline source map is truncated or was not encoded properly",
+            ErrorKind::InvalidJson => "source map is truncated or is not a source map at all",
+            ErrorKind::InvalidVlq => "\"mappings\" field is corrupted, regenerate the source map",
+            ErrorKind::InvalidSection => "sections of the indexed source map overlap or reference missing entries",
+            ErrorKind::InconsistentMapping => "source map is stale or belongs to another file, run without --strict",
+            ErrorKind::InvalidWasm => "WebAssembly module is truncated or malformed",
+            ErrorKind::InvalidGlob => "check the --include and --exclude patterns",
+            ErrorKind::InvalidHashPattern => "use [name], [ext] and exactly one [hash] or [hash:N] placeholder",
+            ErrorKind::InvalidThreshold => "give number of bytes or percentage, e.g. 500 or 2.5%",
+            ErrorKind::InvalidSnapshot => "snapshot was saved by another version or is not a snapshot, save it again",
+        }
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io { .. } => ErrorKind::Io,
+            Error::MissingMapFile { .. } => ErrorKind::MissingMapFile,
+            Error::MissingGeneratedFile { .. } => ErrorKind::MissingGeneratedFile,
+            Error::UnsupportedAnnotation { .. } => ErrorKind::UnsupportedAnnotation,
+            Error::InvalidBase64 { .. } => ErrorKind::InvalidBase64,
+            Error::InvalidJson { .. } => ErrorKind::InvalidJson,
+            Error::InvalidVlq(_) => ErrorKind::InvalidVlq,
+            Error::InvalidSection { .. } => ErrorKind::InvalidSection,
+            Error::InconsistentMapping(_) => ErrorKind::InconsistentMapping,
+            Error::InvalidWasm(_) => ErrorKind::InvalidWasm,
+            Error::InvalidGlob { .. } => ErrorKind::InvalidGlob,
+            Error::InvalidHashPattern { .. } => ErrorKind::InvalidHashPattern,
+            Error::InvalidThreshold(_) => ErrorKind::InvalidThreshold,
+            Error::InvalidSnapshot { .. } => ErrorKind::InvalidSnapshot,
+        }
+    }
+
+    pub(crate) fn io(path: impl fmt::Display, source: io::Error) -> Self {
+        Error::Io {
+            path: path.to_string(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "Could not read {path}: {source}"),
+            Error::MissingMapFile { path, map_file } => {
+                write!(f, "File {path} references sourcemap {map_file}, which does not exist")
+            }
+            Error::MissingGeneratedFile { map_file } => {
+                write!(f, "Could not find generated file for sourcemap {map_file}")
+            }
+            Error::UnsupportedAnnotation { path, kind } => match kind {
+                AnnotationErrorKind::EmptyFile => write!(f, "File {path} is empty"),
+                AnnotationErrorKind::NotFound(last_line) => write!(f, "Unsupported format: {last_line}"),
+                AnnotationErrorKind::MissingWasmSection => {
+                    write!(f, "WebAssembly module {path} contains no sourceMappingURL section")
+                }
+                AnnotationErrorKind::NonLocalUrl(url) => {
+                    write!(f, "File {path} references sourcemap {url}, which is not a local file")
+                }
+                AnnotationErrorKind::OutsideRoot(url) => write!(
+                    f,
+                    "File {path} references sourcemap {url}, which is outside of the scanned directory, use \
+                     --allow-external-maps to follow it"
+                ),
+                AnnotationErrorKind::MissingData => {
+                    write!(
+                        f,
+                        "File {path} contains invalid inline sourcemap: data URL does not contain any data"
+                    )
+                }
+                AnnotationErrorKind::UnsupportedMediaType(media_type) => write!(
+                    f,
+                    "File {path} contains invalid inline sourcemap: unsupported data URL media type {media_type}"
+                ),
+            },
+            Error::InvalidBase64 { path, source } => {
+                write!(f, "File {path} contains invalid inline sourcemap: {source}")
+            }
+            Error::InvalidJson { map_file, source } => write!(f, "Sourcemap {map_file} is not valid JSON: {source}"),
+            Error::InvalidVlq(err) => err.fmt(f),
+            Error::InvalidSection { line, column, kind } => match kind {
+                SectionErrorKind::NotOrdered {
+                    previous_line,
+                    previous_column,
+                } => write!(
+                    f,
+                    "Sections are not ordered: section at {line}:{column} follows section at \
+                     {previous_line}:{previous_column}"
+                ),
+                SectionErrorKind::SourceOutOfRange { source, sources_len } => write!(
+                    f,
+                    "Section at {line}:{column} references source {source}, but it contains only {sources_len} sources"
+                ),
+                SectionErrorKind::NameOutOfRange { name, names_len } => write!(
+                    f,
+                    "Section at {line}:{column} references name {name}, but it contains only {names_len} names"
+                ),
+                SectionErrorKind::Overflow {
+                    line: mapping_line,
+                    column: mapping_column,
+                } => write!(
+                    f,
+                    "Position {mapping_line}:{mapping_column} shifted by section offset {line}:{column} does not fit \
+                     into 32 bits"
+                ),
+            },
+            Error::InconsistentMapping(err) => err.fmt(f),
+            Error::InvalidWasm(err) => err.fmt(f),
+            Error::InvalidGlob { pattern, source } => write!(f, "Invalid glob pattern {pattern:?}: {source}"),
+            Error::InvalidHashPattern { pattern, kind } => match kind {
+                HashPatternErrorKind::UnclosedPlaceholder => {
+                    write!(f, "Unclosed placeholder in hash pattern {pattern:?}")
+                }
+                HashPatternErrorKind::UnknownPlaceholder(placeholder) => {
+                    write!(f, "Unknown placeholder [{placeholder}] in hash pattern {pattern:?}")
+                }
+                HashPatternErrorKind::HashCount(count) => write!(
+                    f,
+                    "Hash pattern {pattern:?} has to contain exactly one [hash] or [hash:N] placeholder, found {count}"
+                ),
+            },
+            Error::InvalidThreshold(value) => write!(
+                f,
+                "Expected number of bytes or percentage, e.g. 500 or 2.5%, got {value:?}"
+            ),
+            Error::InvalidSnapshot { path, kind } => match kind {
+                SnapshotErrorKind::Json(source) => write!(f, "Snapshot {path} is not valid JSON: {source}"),
+                SnapshotErrorKind::UnsupportedVersion(version) => write!(
+                    f,
+                    "Snapshot {path} has version {version}, which is not supported, expected {SNAPSHOT_VERSION}"
+                ),
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::InvalidBase64 { source, .. } => Some(source),
+            Error::InvalidJson { source, .. } => Some(source),
+            Error::InvalidGlob { source, .. } => Some(source),
+            Error::InvalidSnapshot {
+                kind: SnapshotErrorKind::Json(source),
+                ..
+            } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<VlqError> for Error {
+    fn from(err: VlqError) -> Self {
+        Error::InvalidVlq(err)
+    }
+}
+
+impl From<AnalyzeError> for Error {
+    fn from(err: AnalyzeError) -> Self {
+        Error::InconsistentMapping(err)
+    }
+}
+
+impl From<WasmError> for Error {
+    fn from(err: WasmError) -> Self {
+        Error::InvalidWasm(err)
+    }
+}
@@ -0,0 +1,458 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use super::{
+    diff::BundleSnapshot,
+    error::{Error, HashPatternErrorKind},
+};
+
+// Hashes as named by webpack and parcel (hex), including chunks of Create React App, and by Vite
+// and Rollup (8 characters of base64url).
+pub const DEFAULT_HASH_PATTERNS: [&str; 4] = [
+    "[name].[hash].chunk.[ext]",
+    "[name].[hash].[ext]",
+    "[name]-[hash].[ext]",
+    "[name]-[hash:8].[ext]",
+];
+
+// Bundles with less sources in common are not considered to be the same chunk.
+pub const DEFAULT_MIN_SIMILARITY: f64 = 0.5;
+
+// Minimal length of a hexadecimal hash, shorter ones are too easily confused with names.
+const MIN_HEX_HASH_LEN: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    // Any non-empty text.
+    Name,
+    // Non-empty text without dots.
+    Ext,
+    // Hexadecimal hash of at least MIN_HEX_HASH_LEN characters, or base64url hash of given length.
+    // The latter has to contain a digit or both upper and lower case letters, so that words like
+    // "settings" in app-settings.js are not taken for one.
+    Hash(Option<usize>),
+}
+
+impl Token {
+    fn accepts(&self, text: &str) -> bool {
+        match self {
+            Token::Literal(literal) => text == literal,
+            Token::Name => !text.is_empty(),
+            Token::Ext => !text.is_empty() && !text.contains('.'),
+            Token::Hash(None) => text.len() >= MIN_HEX_HASH_LEN && text.chars().all(|char| char.is_ascii_hexdigit()),
+            Token::Hash(Some(len)) => {
+                text.len() == *len
+                    && text
+                        .chars()
+                        .all(|char| char.is_ascii_alphanumeric() || "-_".contains(char))
+                    && (text.chars().any(|char| char.is_ascii_digit())
+                        || text.chars().any(|char| char.is_ascii_uppercase())
+                            && text.chars().any(|char| char.is_ascii_lowercase()))
+            }
+        }
+    }
+}
+
+// File name pattern with placeholders in the style of bundler configs, e.g. [name].[hash].[ext].
+// Matched against the whole file name, directories are never hashed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashPattern {
+    pattern: String,
+    tokens: Vec<Token>,
+}
+
+impl FromStr for HashPattern {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Error> {
+        let invalid = |kind| Error::InvalidHashPattern {
+            pattern: pattern.to_owned(),
+            kind,
+        };
+
+        let mut tokens = vec![];
+        let mut rest = pattern;
+
+        while !rest.is_empty() {
+            let Some(start) = rest.find('[') else {
+                tokens.push(Token::Literal(rest.to_owned()));
+                break;
+            };
+
+            if start > 0 {
+                tokens.push(Token::Literal(rest[..start].to_owned()));
+            }
+
+            let end = rest[start..]
+                .find(']')
+                .ok_or_else(|| invalid(HashPatternErrorKind::UnclosedPlaceholder))?
+                + start;
+
+            tokens.push(match &rest[start + 1..end] {
+                "name" => Token::Name,
+                "ext" => Token::Ext,
+                "hash" => Token::Hash(None),
+                placeholder => match placeholder.strip_prefix("hash:").and_then(|len| len.parse().ok()) {
+                    Some(len) if len > 0 => Token::Hash(Some(len)),
+                    _ => {
+                        return Err(invalid(HashPatternErrorKind::UnknownPlaceholder(
+                            placeholder.to_owned(),
+                        )))
+                    }
+                },
+            });
+
+            rest = &rest[end + 1..];
+        }
+
+        let hash_count = tokens.iter().filter(|token| matches!(token, Token::Hash(_))).count();
+        if hash_count != 1 {
+            return Err(invalid(HashPatternErrorKind::HashCount(hash_count)));
+        }
+
+        Ok(HashPattern {
+            pattern: pattern.to_owned(),
+            tokens,
+        })
+    }
+}
+
+impl fmt::Display for HashPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+impl HashPattern {
+    // File name without the hash and the separator in front of it, None when the pattern does not match.
+    pub fn strip(&self, file_name: &str) -> Option<String> {
+        let mut parts = vec![];
+
+        if !match_tokens(&self.tokens, file_name, &mut parts) {
+            return None;
+        }
+
+        let hash = self.tokens.iter().position(|token| matches!(token, Token::Hash(_)))?;
+        let separator = match (hash.checked_sub(1), hash + 1) {
+            (Some(before), _) if matches!(self.tokens[before], Token::Literal(_)) => Some(before),
+            (_, after) if matches!(self.tokens.get(after), Some(Token::Literal(_))) => Some(after),
+            _ => None,
+        };
+
+        Some(
+            parts
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| *index != hash && Some(*index) != separator)
+                .map(|(_, part)| part)
+                .collect(),
+        )
+    }
+}
+
+// Placeholders match as little as possible, so that the hash is found in the last segments.
+fn match_tokens<'text>(tokens: &[Token], text: &'text str, parts: &mut Vec<&'text str>) -> bool {
+    let Some((token, rest_tokens)) = tokens.split_first() else {
+        return text.is_empty();
+    };
+
+    let ends: Vec<usize> = match token {
+        Token::Literal(literal) if text.starts_with(literal.as_str()) => vec![literal.len()],
+        Token::Literal(_) => vec![],
+        _ => text
+            .char_indices()
+            .skip(1)
+            .map(|(index, _)| index)
+            .chain([text.len()])
+            .collect(),
+    };
+
+    for end in ends {
+        if !token.accepts(&text[..end]) {
+            continue;
+        }
+
+        parts.push(&text[..end]);
+
+        if match_tokens(rest_tokens, &text[end..], parts) {
+            return true;
+        }
+
+        parts.pop();
+    }
+
+    false
+}
+
+#[derive(Debug, Clone)]
+pub struct IdentityOptions {
+    // Tried in order, the first matching pattern wins.
+    pub hash_patterns: Vec<HashPattern>,
+    // Jaccard index of source paths, from 0 to 1, needed to match bundles by their sources.
+    pub min_similarity: f64,
+}
+
+impl Default for IdentityOptions {
+    fn default() -> Self {
+        IdentityOptions {
+            hash_patterns: DEFAULT_HASH_PATTERNS
+                .iter()
+                .map(|pattern| pattern.parse().unwrap())
+                .collect(),
+            min_similarity: DEFAULT_MIN_SIMILARITY,
+        }
+    }
+}
+
+// Path of the bundle with the content hash stripped from its file name, the same in every build
+// as long as the chunk keeps its name.
+pub fn bundle_identity(file: &str, patterns: &[HashPattern]) -> String {
+    let (directory, file_name) = match file.rfind('/') {
+        Some(pos) => file.split_at(pos + 1),
+        None => ("", file),
+    };
+
+    patterns
+        .iter()
+        .find_map(|pattern| pattern.strip(file_name))
+        .map(|file_name| format!("{directory}{file_name}"))
+        .unwrap_or_else(|| file.to_owned())
+}
+
+// Share of sources the bundles have in common out of all their sources.
+pub fn source_similarity(a: &BundleSnapshot, b: &BundleSnapshot) -> f64 {
+    let a = a
+        .sources
+        .iter()
+        .map(|source| source.path.as_str())
+        .collect::<HashSet<_>>();
+    let b = b
+        .sources
+        .iter()
+        .map(|source| source.path.as_str())
+        .collect::<HashSet<_>>();
+
+    match a.union(&b).count() {
+        0 => 0.0,
+        all => a.intersection(&b).count() as f64 / all as f64,
+    }
+}
+
+// Bundle of the old build and its counterpart in the new one, one of them is missing for
+// removed and added bundles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleMatch {
+    pub old: Option<usize>,
+    pub new: Option<usize>,
+    pub identity: String,
+    // Present only for bundles matched by their sources.
+    pub similarity: Option<f64>,
+}
+
+// Bundles are matched by identity first. Bundles left without a counterpart, or sharing their
+// identity with others, are matched by sources, the most similar pairs first.
+pub fn match_bundles(old: &[BundleSnapshot], new: &[BundleSnapshot], options: &IdentityOptions) -> Vec<BundleMatch> {
+    let identity = |bundle: &BundleSnapshot| bundle_identity(&bundle.file, &options.hash_patterns);
+
+    let mut groups = HashMap::<String, (Vec<usize>, Vec<usize>)>::new();
+    for (index, bundle) in old.iter().enumerate() {
+        groups.entry(identity(bundle)).or_default().0.push(index);
+    }
+    for (index, bundle) in new.iter().enumerate() {
+        groups.entry(identity(bundle)).or_default().1.push(index);
+    }
+
+    let mut matches = vec![];
+    let mut old_left = vec![];
+    let mut new_left = vec![];
+
+    for (identity, (old_indices, new_indices)) in groups {
+        match (old_indices.as_slice(), new_indices.as_slice()) {
+            ([old], [new]) => matches.push(BundleMatch {
+                old: Some(*old),
+                new: Some(*new),
+                identity,
+                similarity: None,
+            }),
+            _ => {
+                old_left.extend(old_indices);
+                new_left.extend(new_indices);
+            }
+        }
+    }
+
+    let mut candidates = old_left
+        .iter()
+        .flat_map(|&old_index| new_left.iter().map(move |&new_index| (old_index, new_index)))
+        .map(|(old_index, new_index)| {
+            (
+                old_index,
+                new_index,
+                source_similarity(&old[old_index], &new[new_index]),
+            )
+        })
+        .filter(|(_, _, similarity)| *similarity > 0.0 && *similarity >= options.min_similarity)
+        .collect::<Vec<_>>();
+
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| (a.0, a.1).cmp(&(b.0, b.1))));
+
+    let mut old_matched = HashSet::new();
+    let mut new_matched = HashSet::new();
+
+    for (old_index, new_index, similarity) in candidates {
+        if old_matched.contains(&old_index) || new_matched.contains(&new_index) {
+            continue;
+        }
+
+        old_matched.insert(old_index);
+        new_matched.insert(new_index);
+
+        matches.push(BundleMatch {
+            old: Some(old_index),
+            new: Some(new_index),
+            identity: identity(&new[new_index]),
+            similarity: Some(similarity),
+        });
+    }
+
+    for old_index in old_left.into_iter().filter(|index| !old_matched.contains(index)) {
+        matches.push(BundleMatch {
+            old: Some(old_index),
+            new: None,
+            identity: identity(&old[old_index]),
+            similarity: None,
+        });
+    }
+
+    for new_index in new_left.into_iter().filter(|index| !new_matched.contains(index)) {
+        matches.push(BundleMatch {
+            old: None,
+            new: Some(new_index),
+            identity: identity(&new[new_index]),
+            similarity: None,
+        });
+    }
+
+    matches.sort_by(|a, b| {
+        a.identity
+            .cmp(&b.identity)
+            .then_with(|| (a.old, a.new).cmp(&(b.old, b.new)))
+    });
+
+    matches
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use crate::{
+        diff::{BundleSnapshot, SourceSnapshot},
+        error::{Error, HashPatternErrorKind},
+        identity::{bundle_identity, match_bundles, HashPattern, IdentityOptions},
+    };
+
+    fn bundle(file: &str, sources: &[&str]) -> BundleSnapshot {
+        BundleSnapshot {
+            file: file.to_owned(),
+            bytes: 100,
+            sources: sources
+                .iter()
+                .map(|path| SourceSnapshot {
+                    path: path.to_string(),
+                    bytes: 10,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn strips_hashes_from_bundle_names() {
+        let patterns = IdentityOptions::default().hash_patterns;
+        let identity = |file| bundle_identity(file, &patterns);
+
+        assert_eq!(identity("BasicFilter.09ac81e1.js"), "BasicFilter.js");
+        assert_eq!(
+            identity("static/js/main.3f2a1b9c4d5e6f70.chunk.js"),
+            "static/js/main.chunk.js"
+        );
+        assert_eq!(
+            identity("static/css/787.1a2b3c4d.chunk.css"),
+            "static/css/787.chunk.css"
+        );
+        assert_eq!(identity("assets/index-BkvS1x_w.js"), "assets/index.js");
+        assert_eq!(identity("assets/vendor-react-5e0c2b1a.js"), "assets/vendor-react.js");
+        assert_eq!(identity("assets/index-BkvSaxQw.js"), "assets/index.js");
+        assert_eq!(identity("styles.css"), "styles.css");
+        assert_eq!(identity("app.min.js"), "app.min.js");
+        assert_eq!(identity("app-settings.js"), "app-settings.js");
+        assert_eq!(identity("main-polyfill.js"), "main-polyfill.js");
+        assert_eq!(identity("admin-checkout.js"), "admin-checkout.js");
+        assert_eq!(identity("admin-products.js"), "admin-products.js");
+        assert_eq!(identity("assets/ABOUTPAGE.js"), "assets/ABOUTPAGE.js");
+
+        let pattern = "[name].[hash].chunk.[ext]".parse::<HashPattern>().unwrap();
+        assert_eq!(
+            bundle_identity("static/js/main.3f2a1b9c.chunk.js", &[pattern]),
+            "static/js/main.chunk.js"
+        );
+
+        let pattern = "[hash:4]_[name].[ext]".parse::<HashPattern>().unwrap();
+        assert_eq!(pattern.strip("a1b2_app.js"), Some("app.js".to_owned()));
+
+        let error = |pattern: &str| match pattern.parse::<HashPattern>() {
+            Err(Error::InvalidHashPattern { kind, .. }) => Some(kind),
+            _ => None,
+        };
+        assert_eq!(error("[name].[ext]"), Some(HashPatternErrorKind::HashCount(0)));
+        assert_eq!(error("[name].[hash"), Some(HashPatternErrorKind::UnclosedPlaceholder));
+        assert_eq!(
+            error("[name].[id].js"),
+            Some(HashPatternErrorKind::UnknownPlaceholder("id".to_owned()))
+        );
+    }
+
+    #[test]
+    fn matches_bundles_by_identity_and_sources() {
+        let old = [
+            bundle("main.1111aaaa.js", &["src/main.js"]),
+            bundle("chunk-a.2222bbbb.js", &["src/a.js", "src/b.js", "src/c.js"]),
+            bundle("123.3333cccc.js", &["src/x.js", "src/y.js"]),
+            bundle("legacy.4444dddd.js", &["src/legacy.js"]),
+        ];
+        let new = [
+            bundle("main.5555eeee.js", &["src/main.js", "src/new.js"]),
+            bundle("chunk-b.6666ffff.js", &["src/a.js", "src/b.js", "src/d.js"]),
+            bundle("456.7777aaaa.js", &["src/x.js", "src/y.js", "src/z.js"]),
+        ];
+
+        let matches = match_bundles(&old, &new, &IdentityOptions::default())
+            .into_iter()
+            .map(|m| (m.old, m.new, m.identity, m.similarity))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            matches,
+            vec![
+                (Some(2), Some(2), "456.js".to_owned(), Some(2.0 / 3.0)),
+                (Some(1), Some(1), "chunk-b.js".to_owned(), Some(0.5)),
+                (Some(3), None, "legacy.js".to_owned(), None),
+                (Some(0), Some(0), "main.js".to_owned(), None),
+            ]
+        );
+
+        let options = IdentityOptions {
+            min_similarity: 0.6,
+            ..IdentityOptions::default()
+        };
+        let unmatched = match_bundles(&old, &new, &options)
+            .into_iter()
+            .filter(|m| m.old.is_none() || m.new.is_none())
+            .map(|m| m.identity)
+            .collect::<Vec<_>>();
+
+        assert_eq!(unmatched, vec!["chunk-a.js", "chunk-b.js", "legacy.js"]);
+    }
+}
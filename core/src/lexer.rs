@@ -0,0 +1,334 @@
+use crate::discovery::BundleKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace,
+    Comment,
+    // Comment holding sourceMappingURL annotation.
+    SourceMapComment,
+    // String literals, including static parts of template literals.
+    String,
+    Identifier,
+    // Punctuation, numbers and regular expressions.
+    Code,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+// Keywords after which slash starts a regular expression rather than division.
+const KEYWORDS_BEFORE_EXPRESSION: [&[u8]; 13] = [
+    b"return",
+    b"typeof",
+    b"case",
+    b"do",
+    b"else",
+    b"in",
+    b"instanceof",
+    b"new",
+    b"void",
+    b"delete",
+    b"throw",
+    b"yield",
+    b"await",
+];
+
+// Lightweight lexer splitting generated file into tokens, good enough to tell comments and strings
+// apart from code. It never fails, malformed input just results in less accurate tokens.
+// CSS is handled by the same lexer with line comments, template literals and regular expressions
+// disabled.
+pub struct Lexer<'a> {
+    contents: &'a [u8],
+    position: usize,
+    is_css: bool,
+    // Whether slash at the current position would start a regular expression.
+    expects_expression: bool,
+    brace_depth: usize,
+    // Brace depths at which template literal expressions were opened, to resume the literal
+    // after the closing brace.
+    template_depths: Vec<usize>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(contents: &'a [u8], kind: BundleKind) -> Self {
+        Lexer {
+            contents,
+            position: 0,
+            is_css: kind == BundleKind::Css,
+            expects_expression: true,
+            brace_depth: 0,
+            template_depths: vec![],
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.contents.get(self.position + offset).copied()
+    }
+
+    fn whitespace_len(&self) -> usize {
+        match &self.contents[self.position..] {
+            [b' ' | b'\t' | b'\n' | b'\r' | 0x0B | 0x0C, ..] => 1,
+            // NO-BREAK SPACE, LINE SEPARATOR, PARAGRAPH SEPARATOR and BYTE ORDER MARK
+            [0xC2, 0xA0, ..] => 2,
+            [0xE2, 0x80, 0xA8 | 0xA9, ..] | [0xEF, 0xBB, 0xBF, ..] => 3,
+            _ => 0,
+        }
+    }
+
+    fn is_line_terminator(&self) -> bool {
+        matches!(
+            &self.contents[self.position..],
+            [b'\n' | b'\r', ..] | [0xE2, 0x80, 0xA8 | 0xA9, ..]
+        )
+    }
+
+    fn skip_line(&mut self) {
+        while self.position < self.contents.len() && !self.is_line_terminator() {
+            self.position += 1;
+        }
+    }
+
+    fn comment_kind(&self, start: usize) -> TokenKind {
+        let body = &self.contents[start + 2..self.position];
+        let body = body.strip_prefix(b"#").or_else(|| body.strip_prefix(b"@"));
+
+        match body {
+            Some(body) if body.trim_ascii_start().starts_with(b"sourceMappingURL=") => TokenKind::SourceMapComment,
+            _ => TokenKind::Comment,
+        }
+    }
+
+    fn skip_string(&mut self, quote: u8) {
+        self.position += 1;
+
+        while let Some(byte) = self.peek(0) {
+            match byte {
+                b'\\' => self.position += 2,
+                _ if byte == quote => {
+                    self.position += 1;
+                    return;
+                }
+                // Unterminated string, only template literals span multiple lines.
+                _ if quote != b'`' && self.is_line_terminator() => return,
+                b'$' if quote == b'`' && self.peek(1) == Some(b'{') => {
+                    self.position += 2;
+                    self.template_depths.push(self.brace_depth);
+                    self.brace_depth += 1;
+                    return;
+                }
+                _ => self.position += 1,
+            }
+        }
+
+        self.position = self.position.min(self.contents.len());
+    }
+
+    fn skip_regex(&mut self) {
+        self.position += 1;
+        let mut in_class = false;
+
+        while let Some(byte) = self.peek(0) {
+            if self.is_line_terminator() {
+                return;
+            }
+
+            self.position += 1;
+
+            match byte {
+                b'\\' => self.position += 1,
+                b'[' => in_class = true,
+                b']' => in_class = false,
+                b'/' if !in_class => break,
+                _ => {}
+            }
+        }
+
+        // Flags
+        while self.peek(0).is_some_and(is_identifier_byte) {
+            self.position += 1;
+        }
+
+        self.position = self.position.min(self.contents.len());
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let start = self.position;
+        let byte = self.peek(0)?;
+
+        let whitespace_len = self.whitespace_len();
+        let kind = if whitespace_len > 0 {
+            self.position += whitespace_len;
+            while self.position < self.contents.len() {
+                match self.whitespace_len() {
+                    0 => break,
+                    len => self.position += len,
+                }
+            }
+
+            return Some(Token {
+                kind: TokenKind::Whitespace,
+                start,
+                end: self.position,
+            });
+        } else if byte == b'/' && self.peek(1) == Some(b'*') {
+            self.position = match self.contents[start + 2..].windows(2).position(|window| window == b"*/") {
+                Some(position) => start + 2 + position + 2,
+                None => self.contents.len(),
+            };
+
+            return Some(Token {
+                kind: self.comment_kind(start),
+                start,
+                end: self.position,
+            });
+        } else if byte == b'/' && self.peek(1) == Some(b'/') && !self.is_css {
+            self.skip_line();
+
+            return Some(Token {
+                kind: self.comment_kind(start),
+                start,
+                end: self.position,
+            });
+        } else if byte == b'"' || byte == b'\'' || (byte == b'`' && !self.is_css) {
+            self.skip_string(byte);
+            self.expects_expression = false;
+            TokenKind::String
+        } else if byte == b'}' && self.template_depths.last() == Some(&(self.brace_depth.saturating_sub(1))) {
+            // End of template literal expression, rest of the literal follows.
+            self.template_depths.pop();
+            self.brace_depth = self.brace_depth.saturating_sub(1);
+            self.skip_string(b'`');
+            self.expects_expression = false;
+            TokenKind::String
+        } else if byte == b'/' && self.expects_expression && !self.is_css {
+            self.skip_regex();
+            self.expects_expression = false;
+            TokenKind::Code
+        } else if is_identifier_byte(byte) {
+            while self.peek(0).is_some_and(is_identifier_byte) && self.whitespace_len() == 0 {
+                self.position += 1;
+            }
+
+            let identifier = &self.contents[start..self.position];
+            self.expects_expression = KEYWORDS_BEFORE_EXPRESSION.contains(&identifier);
+
+            // Numbers are lexed as identifiers, but are not reported as such.
+            if byte.is_ascii_digit() {
+                TokenKind::Code
+            } else {
+                TokenKind::Identifier
+            }
+        } else {
+            self.position += 1;
+
+            match byte {
+                b'{' => self.brace_depth += 1,
+                b'}' => self.brace_depth = self.brace_depth.saturating_sub(1),
+                _ => {}
+            }
+
+            self.expects_expression = !matches!(byte, b')' | b']' | b'}');
+            TokenKind::Code
+        };
+
+        Some(Token {
+            kind,
+            start,
+            end: self.position,
+        })
+    }
+}
+
+// Non-ASCII bytes are treated as parts of identifiers, unless they form whitespace.
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || byte >= 0x80
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use crate::{
+        discovery::BundleKind,
+        lexer::{Lexer, TokenKind},
+    };
+
+    fn tokens(contents: &str, kind: BundleKind) -> Vec<(TokenKind, &str)> {
+        Lexer::new(contents.as_bytes(), kind)
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, &contents[token.start..token.end]))
+            .collect()
+    }
+
+    #[test]
+    fn splits_javascript_into_tokens() {
+        use TokenKind::*;
+
+        let contents =
+            "/*! MIT */\nvar a = 'x\\'y' / 2, b = /\"[/]/g; // c\nf(`t${ {a:`u`} }v`);\n//# sourceMappingURL=a.js.map";
+
+        assert_eq!(
+            tokens(contents, BundleKind::JavaScript),
+            [
+                (Comment, "/*! MIT */"),
+                (Identifier, "var"),
+                (Identifier, "a"),
+                (Code, "="),
+                (String, "'x\\'y'"),
+                (Code, "/"),
+                (Code, "2"),
+                (Code, ","),
+                (Identifier, "b"),
+                (Code, "="),
+                (Code, "/\"[/]/g"),
+                (Code, ";"),
+                (Comment, "// c"),
+                (Identifier, "f"),
+                (Code, "("),
+                (String, "`t${"),
+                (Code, "{"),
+                (Identifier, "a"),
+                (Code, ":"),
+                (String, "`u`"),
+                (Code, "}"),
+                (String, "}v`"),
+                (Code, ")"),
+                (Code, ";"),
+                (SourceMapComment, "//# sourceMappingURL=a.js.map"),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_css_into_tokens() {
+        use TokenKind::*;
+
+        let contents = "a{background:url(//x.png)}/*# sourceMappingURL=a.css.map */";
+
+        assert_eq!(
+            tokens(contents, BundleKind::Css),
+            [
+                (Identifier, "a"),
+                (Code, "{"),
+                (Identifier, "background"),
+                (Code, ":"),
+                (Identifier, "url"),
+                (Code, "("),
+                (Code, "/"),
+                (Code, "/"),
+                (Identifier, "x"),
+                (Code, "."),
+                (Identifier, "png"),
+                (Code, ")"),
+                (Code, "}"),
+                (SourceMapComment, "/*# sourceMappingURL=a.css.map */"),
+            ]
+        );
+    }
+}
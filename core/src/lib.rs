@@ -1,48 +1,52 @@
 use self::{
-    analyzer::{calculate_size_by_file, SourceMappingInfo},
-    parser::parse_file_by_path,
+    analyzer::{calculate_size_by_file, AnalyzeOptions, SourceMappingInfo},
+    discovery::{discover_files, DiscoveryOptions},
+    parser::{parse_file_by_path, ParseContext},
 };
-use anyhow::{Error, Result};
+
+pub use self::error::{Error, ErrorKind};
+
+type Result<T, E = Error> = std::result::Result<T, E>;
 
 pub mod analyzer;
+pub mod combined;
+pub mod compression;
+pub mod diff;
+pub mod discovery;
+pub mod duplicates;
+pub mod error;
+pub mod identity;
+mod lexer;
+mod lines;
+pub mod packages;
 pub mod parser;
+#[cfg(any(test, rust_analyzer))]
+mod test_utils;
+pub mod validator;
 mod vlq;
+mod wasm;
+
+pub fn analyze_path(
+    path: &str,
+    options: &DiscoveryOptions,
+    analyze_options: &AnalyzeOptions,
+    mut on_file_result: impl FnMut(&str, Result<SourceMappingInfo, Error>),
+) -> Result<()> {
+    let files_to_check = discover_files(path, options)?;
 
-pub fn analyze_path(path: &str, mut on_file_result: impl FnMut(&str, Result<SourceMappingInfo, Error>)) -> Result<()> {
-    let files_to_check = discover_files(path)?;
+    let context = ParseContext::new(options.map_root(path));
 
     for file in files_to_check.iter() {
-        on_file_result(file, handle_file(file));
+        on_file_result(file, handle_file(file, &context, analyze_options));
     }
 
     Ok(())
 }
 
-pub fn discover_files(path: &str) -> Result<Vec<String>> {
-    let path_meta = std::fs::metadata(path)?;
-
-    let mut files_to_check: Vec<String> = vec![];
-
-    if path_meta.is_dir() {
-        for entry in (std::fs::read_dir(path)?).flatten() {
-            let path = entry.path();
-            if let "js" = path.extension().unwrap().to_str().unwrap() {
-                files_to_check.push(path.to_str().unwrap().to_owned())
-            }
-        }
-    } else {
-        files_to_check.push(path.to_owned());
-    }
-
-    files_to_check.sort();
-
-    Ok(files_to_check)
-}
-
-pub fn handle_file(file: &str) -> Result<SourceMappingInfo> {
-    let (file_contents, mapping) = parse_file_by_path(file)?;
+pub fn handle_file(file: &str, context: &ParseContext, options: &AnalyzeOptions) -> Result<SourceMappingInfo, Error> {
+    let (file_contents, mapping) = parse_file_by_path(file, context)?;
 
-    let info = calculate_size_by_file(&file_contents, mapping)?;
+    let info = calculate_size_by_file(&file_contents, mapping, options)?;
 
     Ok(info)
 }
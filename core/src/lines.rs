@@ -0,0 +1,174 @@
+// Byte range of a single line of generated file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeneratedLine {
+    pub start: usize,
+    // End of line contents, excluding line terminator.
+    pub end: usize,
+    // End of line including line terminator, equal to start of the next line.
+    pub terminator_end: usize,
+}
+
+// Splits file into lines the same way JS engines count generated lines: on LF, CRLF, CR,
+// LINE SEPARATOR and PARAGRAPH SEPARATOR. Works on bytes, so files that are not valid UTF-8
+// can be analyzed as well.
+pub fn split_lines(contents: &[u8]) -> Vec<GeneratedLine> {
+    let mut lines = vec![];
+    let mut start = 0;
+    let mut position = 0;
+
+    while position < contents.len() {
+        let terminator_len = match contents[position] {
+            b'\n' => 1,
+            b'\r' if contents.get(position + 1) == Some(&b'\n') => 2,
+            b'\r' => 1,
+            // UTF-8 encoded U+2028 and U+2029
+            0xE2 if matches!(contents.get(position + 1..position + 3), Some([0x80, 0xA8 | 0xA9])) => 3,
+            _ => {
+                position += 1;
+                continue;
+            }
+        };
+
+        lines.push(GeneratedLine {
+            start,
+            end: position,
+            terminator_end: position + terminator_len,
+        });
+
+        position += terminator_len;
+        start = position;
+    }
+
+    lines.push(GeneratedLine {
+        start,
+        end: contents.len(),
+        terminator_end: contents.len(),
+    });
+
+    lines
+}
+
+// Converts source map columns, which are counted in UTF-16 code units, into byte offsets within
+// a line. Columns are expected to grow within a line, so the cursor only moves forward and whole
+// line is walked at most once.
+pub struct ColumnCursor<'a> {
+    line: &'a [u8],
+    byte: usize,
+    column: u32,
+    last_query: Option<(u32, usize)>,
+}
+
+impl<'a> ColumnCursor<'a> {
+    pub fn new(line: &'a [u8]) -> Self {
+        Self {
+            line,
+            byte: 0,
+            column: 0,
+            last_query: None,
+        }
+    }
+
+    // Returns None if the column is past the end of line.
+    pub fn byte_offset(&mut self, column: u32) -> Option<usize> {
+        match self.last_query {
+            Some((last_column, byte)) if last_column == column => return Some(byte),
+            _ => {}
+        }
+
+        if column < self.column {
+            self.byte = 0;
+            self.column = 0;
+        }
+
+        while self.column < column {
+            if self.byte >= self.line.len() {
+                return None;
+            }
+
+            let (len, units) = utf8_char_len(&self.line[self.byte..]);
+            self.byte += len;
+            self.column += units;
+        }
+
+        self.last_query = Some((column, self.byte));
+
+        Some(self.byte)
+    }
+
+    // Inverse of byte_offset, offsets in the middle of a character return column after it.
+    pub fn column(&mut self, byte_offset: usize) -> u32 {
+        if byte_offset < self.byte {
+            self.byte = 0;
+            self.column = 0;
+        }
+
+        while self.byte < byte_offset.min(self.line.len()) {
+            let (len, units) = utf8_char_len(&self.line[self.byte..]);
+            self.byte += len;
+            self.column += units;
+        }
+
+        self.column
+    }
+}
+
+// Returns length of the first character in bytes and in UTF-16 code units. Bytes that are not
+// part of a valid UTF-8 sequence count as single characters, like in lossy conversion.
+fn utf8_char_len(bytes: &[u8]) -> (usize, u32) {
+    let len = match bytes[0] {
+        0x00..=0x7F => return (1, 1),
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => return (1, 1),
+    };
+
+    match bytes.get(1..len) {
+        Some(continuation) if continuation.iter().all(|byte| byte & 0b1100_0000 == 0b1000_0000) => {
+            // Characters outside of Basic Multilingual Plane are encoded as surrogate pairs.
+            (len, if len == 4 { 2 } else { 1 })
+        }
+        _ => (1, 1),
+    }
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use crate::lines::{split_lines, ColumnCursor};
+
+    #[test]
+    fn splits_on_all_line_terminators() {
+        let contents = "a\nbb\r\nc\rd\u{2028}e\u{2029}".as_bytes();
+
+        let lines = split_lines(contents)
+            .iter()
+            .map(|line| (line.start, line.end, line.terminator_end))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            [(0, 1, 2), (2, 4, 6), (6, 7, 8), (8, 9, 12), (12, 13, 16), (16, 16, 16)]
+        );
+    }
+
+    #[test]
+    fn converts_utf16_columns_to_byte_offsets() {
+        // "é" takes 2 bytes, "中" takes 3 bytes and "😀" takes 4 bytes and 2 UTF-16 code units.
+        let line = "aé中😀b".as_bytes();
+        let mut cursor = ColumnCursor::new(line);
+
+        let offsets = [0, 1, 2, 3, 5, 6].map(|column| cursor.byte_offset(column));
+        assert_eq!(offsets, [0, 1, 3, 6, 10, 11].map(Some));
+
+        assert_eq!(cursor.byte_offset(7), None);
+        assert_eq!(cursor.byte_offset(2), Some(3));
+
+        let mut cursor = ColumnCursor::new(b"a\xFF\xFEb");
+        assert_eq!(cursor.byte_offset(3), Some(3));
+
+        let mut cursor = ColumnCursor::new(line);
+        let columns = [0, 1, 3, 6, 10, 11].map(|byte_offset| cursor.column(byte_offset));
+        assert_eq!(columns, [0, 1, 2, 3, 5, 6]);
+        assert_eq!(cursor.column(3), 2);
+    }
+}
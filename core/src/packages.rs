@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+
+use super::analyzer::SourceMappingInfo;
+
+const NODE_MODULES: &str = "node_modules";
+const PNPM_STORE: &str = ".pnpm";
+
+// npm package a source belongs to, found by the last node_modules folder in its path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Package {
+    // Includes scope, e.g. @mui/material.
+    pub name: String,
+    // Known only for pnpm layout, where it is part of the store folder name.
+    pub version: Option<String>,
+    // Package whose own node_modules folder contains this copy, for nested installs.
+    pub parent: Option<String>,
+    // Path of the package folder, including all folders before it.
+    pub path: String,
+}
+
+impl Package {
+    pub fn label(&self) -> String {
+        let mut label = match &self.version {
+            Some(version) => format!("{}@{}", self.name, version),
+            None => self.name.clone(),
+        };
+
+        if let Some(parent) = &self.parent {
+            label += &format!(" (in {parent})");
+        }
+
+        label
+    }
+
+    // Path of the source within the package folder.
+    pub fn inner_path<'path>(&self, path: &'path str) -> &'path str {
+        path.get(self.path.len() + 1..).unwrap_or_default()
+    }
+}
+
+// Returns None for sources outside of node_modules, which are considered to be your own code.
+pub fn find_package(path: &str) -> Option<Package> {
+    let segments = path.split('/').collect::<Vec<_>>();
+    // Start and end of the name segments of the last package found.
+    let mut package: Option<(usize, usize)> = None;
+    let mut parent: Option<(usize, usize)> = None;
+
+    let mut index = 0;
+    while index < segments.len() {
+        if segments[index] != NODE_MODULES {
+            index += 1;
+            continue;
+        }
+
+        let name_len = match segments.get(index + 1) {
+            Some(segment) if segment.starts_with('@') => 2,
+            // .pnpm store, .bin, .cache and similar folders are not packages.
+            Some(segment) if !segment.starts_with('.') && !segment.is_empty() => 1,
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+
+        // At least one path segment has to follow the package name.
+        let name_end = index + 1 + name_len;
+        if name_end >= segments.len() {
+            break;
+        }
+
+        parent = package;
+        package = Some((index + 1, name_end));
+        index = name_end;
+    }
+
+    let (name_start, name_end) = package?;
+    let name = segments[name_start..name_end].join("/");
+
+    // pnpm layout: .pnpm/<name with + instead of />@<version>[_peers or (peers)]/node_modules/<name>
+    let version = match name_start.checked_sub(3) {
+        Some(store_index) if segments[store_index] == PNPM_STORE => pnpm_version(segments[store_index + 1], &name),
+        _ => None,
+    };
+
+    Some(Package {
+        version,
+        parent: parent.map(|(start, end)| segments[start..end].join("/")),
+        path: segments[..name_end].join("/"),
+        name,
+    })
+}
+
+fn pnpm_version(store_folder: &str, name: &str) -> Option<String> {
+    let version = store_folder.strip_prefix(&name.replace('/', "+"))?.strip_prefix('@')?;
+    let version = version.split(['_', '(']).next().unwrap_or(version);
+
+    (!version.is_empty()).then(|| version.to_owned())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageSize {
+    pub name: String,
+    pub bytes: u64,
+    // Distinct source paths and bundles the package was found in.
+    pub files: usize,
+    pub bundles: usize,
+}
+
+// Bytes attributed to sources, split between your own code and npm packages. Versions and
+// copies of the same package are summed up under its name.
+#[derive(Debug, Default)]
+pub struct PackageReport {
+    pub own_bytes: u64,
+    pub third_party_bytes: u64,
+    packages: HashMap<String, (PackageSize, HashSet<String>)>,
+}
+
+impl PackageReport {
+    pub fn add(&mut self, info: &SourceMappingInfo) {
+        let mut seen_packages = HashSet::new();
+
+        for file_info in &info.info_by_file {
+            // Paths relative to sources root would hide node_modules for bundles made of a single package.
+            let path = info.get_full_file_name(file_info.file);
+            let bytes = file_info.bytes as u64;
+
+            let Some(package) = find_package(&path) else {
+                self.own_bytes += bytes;
+                continue;
+            };
+
+            self.third_party_bytes += bytes;
+
+            let (size, paths) = self.packages.entry(package.name.clone()).or_insert_with(|| {
+                (
+                    PackageSize {
+                        name: package.name.clone(),
+                        bytes: 0,
+                        files: 0,
+                        bundles: 0,
+                    },
+                    HashSet::new(),
+                )
+            });
+
+            size.bytes += bytes;
+
+            if paths.insert(path) {
+                size.files += 1;
+            }
+
+            if seen_packages.insert(package.name) {
+                size.bundles += 1;
+            }
+        }
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.own_bytes + self.third_party_bytes
+    }
+
+    // Largest packages first.
+    pub fn packages(&self) -> Vec<&PackageSize> {
+        let mut packages = self.packages.values().map(|(size, _)| size).collect::<Vec<_>>();
+        packages.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+        packages
+    }
+}
+
+pub fn aggregate_by_package<'info>(infos: impl IntoIterator<Item = &'info SourceMappingInfo>) -> PackageReport {
+    let mut report = PackageReport::default();
+
+    for info in infos {
+        report.add(info);
+    }
+
+    report
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use crate::{
+        packages::{aggregate_by_package, find_package, Package},
+        test_utils::analyze_bundle,
+    };
+
+    fn package(name: &str, version: Option<&str>, parent: Option<&str>, path: &str) -> Option<Package> {
+        Some(Package {
+            name: name.to_owned(),
+            version: version.map(str::to_owned),
+            parent: parent.map(str::to_owned),
+            path: path.to_owned(),
+        })
+    }
+
+    #[test]
+    fn finds_packages_in_paths() {
+        assert_eq!(find_package("src/main.js"), None);
+        assert_eq!(find_package("node_modules/.bin/tool.js"), None);
+        assert_eq!(find_package("node_modules/lodash"), None);
+        assert_eq!(
+            find_package("node_modules/lodash/lodash.js"),
+            package("lodash", None, None, "node_modules/lodash")
+        );
+        assert_eq!(
+            find_package("../../node_modules/@mui/material/esm/Button/Button.js"),
+            package("@mui/material", None, None, "../../node_modules/@mui/material")
+        );
+        assert_eq!(
+            find_package("node_modules/some-lib/node_modules/lodash/map.js"),
+            package(
+                "lodash",
+                None,
+                Some("some-lib"),
+                "node_modules/some-lib/node_modules/lodash"
+            )
+        );
+        assert_eq!(
+            find_package("node_modules/.pnpm/react-dom@18.2.0/node_modules/react-dom/index.js"),
+            package(
+                "react-dom",
+                Some("18.2.0"),
+                None,
+                "node_modules/.pnpm/react-dom@18.2.0/node_modules/react-dom"
+            )
+        );
+        assert_eq!(
+            find_package("node_modules/.pnpm/@mui+material@5.15.0_react@18.2.0/node_modules/@mui/material/index.js"),
+            package(
+                "@mui/material",
+                Some("5.15.0"),
+                None,
+                "node_modules/.pnpm/@mui+material@5.15.0_react@18.2.0/node_modules/@mui/material"
+            )
+        );
+        assert_eq!(
+            find_package("node_modules/.pnpm/a@1.0.0(b@2.0.0)/node_modules/a/index.js").map(|p| p.label()),
+            Some("a@1.0.0".to_owned())
+        );
+
+        let nested = find_package("node_modules/some-lib/node_modules/lodash/map.js").unwrap();
+        assert_eq!(nested.label(), "lodash (in some-lib)");
+        assert_eq!(
+            nested.inner_path("node_modules/some-lib/node_modules/lodash/map.js"),
+            "map.js"
+        );
+    }
+
+    #[test]
+    fn attributes_single_package_bundles_to_the_package() {
+        // Sources root of such bundle is the package folder, so node_modules is only part of
+        // full source paths.
+        let info = analyze_bundle(
+            "lodash.js",
+            "var a=1;\nvar b=22;\n",
+            &[
+                ("node_modules/lodash/map.js", "map"),
+                ("node_modules/lodash/filter.js", "filter"),
+            ],
+        );
+        let report = aggregate_by_package([&info]);
+
+        assert_eq!(report.own_bytes, 0);
+        assert_eq!(report.third_party_bytes, report.total_bytes());
+        let packages = report.packages();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "lodash");
+        assert_eq!(packages[0].files, 2);
+        assert_eq!(packages[0].bundles, 1);
+    }
+}
@@ -1,74 +1,565 @@
-use base64::{engine::general_purpose, Engine as _};
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine as _,
+};
+use percent_encoding::percent_decode_str;
 use serde::Deserialize;
-use std::{fs, path::Path};
+use serde_json::value::RawValue;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
 
-use anyhow::{anyhow, Context, Result};
+use super::{
+    discovery::BundleKind,
+    error::{AnnotationErrorKind, Error, SectionErrorKind},
+    vlq::{MappingsDecoder, VlqErrorKind},
+    wasm,
+};
 
-use super::vlq::vlq_decode;
+type Result<T, E = Error> = std::result::Result<T, E>;
 
-pub fn parse_file_by_path(path: &str) -> Result<(String, SourceMapping)> {
-    let file_meta = std::fs::metadata(path)?;
-    let contents = fs::read_to_string(path)?;
+// Source maps are read whole, anything larger is not a real source map.
+const MAX_MAP_FILE_LEN: u64 = 512 * 1024 * 1024;
 
-    let last_line = contents
+// State shared by all files parsed during a single scan.
+#[derive(Debug, Default)]
+pub struct ParseContext {
+    // Files referenced by annotations or "file" fields are read only from within this directory,
+    // e.g. the scanned path. Any file can be read when there is no root.
+    pub map_root: Option<String>,
+    // Only source maps inlined as data URLs are used.
+    inline_only: bool,
+    // Source maps of bundles without annotation, by directory and then by the generated file they
+    // declare. Each directory is indexed once, when the first such bundle is found in it.
+    hidden_maps: Mutex<HashMap<String, HashMap<String, String>>>,
+}
+
+impl ParseContext {
+    pub fn new(map_root: Option<&str>) -> Self {
+        ParseContext {
+            map_root: map_root.map(|root| root.to_owned()),
+            ..ParseContext::default()
+        }
+    }
+
+    // Nothing besides the generated file is read, e.g. when fuzzing.
+    pub fn inline_only() -> Self {
+        ParseContext {
+            inline_only: true,
+            ..ParseContext::default()
+        }
+    }
+
+    fn find_hidden_map(&self, path: &str) -> Result<Option<String>> {
+        let directory = map_directory(path);
+        let mut hidden_maps = self.hidden_maps.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if !hidden_maps.contains_key(directory) {
+            hidden_maps.insert(directory.to_owned(), index_hidden_maps(directory)?);
+        }
+
+        Ok(hidden_maps[directory].get(&normalize_path(path)).cloned())
+    }
+}
+
+// Generated file is returned as bytes, as it does not have to be valid UTF-8.
+pub fn parse_file_by_path(path: &str, context: &ParseContext) -> Result<(Vec<u8>, SourceMapping)> {
+    if path.ends_with(".map") {
+        return parse_source_map_by_path(path, context);
+    }
+
+    parse_file_contents(path, read_file(path)?, context)
+}
+
+// Same as parse_file_by_path for generated files already read into memory.
+pub fn parse_file_contents(path: &str, contents: Vec<u8>, context: &ParseContext) -> Result<(Vec<u8>, SourceMapping)> {
+    if wasm::is_wasm_module(&contents) {
+        return parse_wasm_module(path, contents, context);
+    }
+
+    let contents_str = String::from_utf8_lossy(&contents);
+
+    let last_line = contents_str
         .lines()
-        .next_back()
-        .ok_or_else(|| anyhow!("File {path} is empty."))?;
+        .rfind(|line| !line.trim().is_empty())
+        .ok_or_else(|| unsupported_annotation(path, AnnotationErrorKind::EmptyFile))?;
+
+    let annotation = find_source_mapping_annotation(&contents_str);
+
+    let (raw_source_mapping, map_file) = match &annotation {
+        Some(annotation) => parse_raw_source_mapping(path, annotation.url, context)?,
+        // Bundles built with "hidden" source maps contain no annotation at all.
+        None => find_hidden_source_mapping(path, context)?.ok_or_else(|| {
+            unsupported_annotation(
+                path,
+                AnnotationErrorKind::NotFound(last_line.chars().take(100).collect::<String>()),
+            )
+        })?,
+    };
 
-    let mut raw_source_mapping = parse_raw_source_mapping(path, last_line)?;
-    raw_source_mapping.file = String::from(path); // TODO
+    let annotation_len = annotation.map_or(0, |annotation| annotation.line.len() as u64);
 
-    let mut source_mapping = SourceMapping::from_raw(raw_source_mapping)?;
-    source_mapping.source_file_len = file_meta.len();
-    source_mapping.source_map_len = last_line.len() as u64;
+    finish_parsing(path, contents, raw_source_mapping, map_file, annotation_len)
+}
+
+// WebAssembly modules reference their source maps with a custom section instead of a comment.
+fn parse_wasm_module(path: &str, contents: Vec<u8>, context: &ParseContext) -> Result<(Vec<u8>, SourceMapping)> {
+    let sections = wasm::parse_sections(&contents)?;
+
+    let (raw_source_mapping, map_file, annotation_len) = match wasm::find_source_mapping_url(&contents, &sections)? {
+        Some((url, section)) => {
+            let (raw_source_mapping, map_file) = parse_raw_source_mapping(path, url, context)?;
+            (raw_source_mapping, map_file, section.len() as u64)
+        }
+        None => {
+            let (raw_source_mapping, map_file) = find_hidden_source_mapping(path, context)?
+                .ok_or_else(|| unsupported_annotation(path, AnnotationErrorKind::MissingWasmSection))?;
+            (raw_source_mapping, map_file, 0)
+        }
+    };
+
+    finish_parsing(path, contents, raw_source_mapping, map_file, annotation_len)
+}
+
+// Parses source map given directly, generated file is located using "file" field or the name
+// of the source map.
+fn parse_source_map_by_path(map_path: &str, context: &ParseContext) -> Result<(Vec<u8>, SourceMapping)> {
+    let raw_source_mapping = parse_raw_json(&read_map_file(map_path)?, map_path)?;
+
+    let candidates = [
+        (!raw_source_mapping.file.is_empty())
+            .then(|| normalize_path(&format!("{}/{}", map_directory(map_path), raw_source_mapping.file))),
+        map_path.strip_suffix(".map").map(|path| path.to_owned()),
+    ];
+
+    let path = candidates
+        .into_iter()
+        .flatten()
+        .find(|path| Path::new(path).is_file() && is_within_root(Path::new(path), context))
+        .ok_or_else(|| Error::MissingGeneratedFile {
+            map_file: map_path.to_owned(),
+        })?;
+
+    let contents = read_file(&path)?;
+
+    if wasm::is_wasm_module(&contents) {
+        let sections = wasm::parse_sections(&contents)?;
+        let annotation_len =
+            wasm::find_source_mapping_url(&contents, &sections)?.map_or(0, |(_, section)| section.len() as u64);
+
+        return finish_parsing(&path, contents, raw_source_mapping, map_path.to_owned(), annotation_len);
+    }
+
+    let annotation_len = find_source_mapping_annotation(&String::from_utf8_lossy(&contents))
+        .map_or(0, |annotation| annotation.line.len() as u64);
+
+    finish_parsing(&path, contents, raw_source_mapping, map_path.to_owned(), annotation_len)
+}
+
+fn finish_parsing(
+    path: &str,
+    contents: Vec<u8>,
+    mut raw_source_mapping: RawSourceMapping,
+    map_file: String,
+    annotation_len: u64,
+) -> Result<(Vec<u8>, SourceMapping)> {
+    let declared_file = std::mem::replace(&mut raw_source_mapping.file, String::from(path)); // TODO
+
+    let mut source_mapping = SourceMapping::from_raw(raw_source_mapping, map_file)?;
+    source_mapping.declared_file = declared_file;
+    source_mapping.source_file_len = contents.len() as u64;
+    source_mapping.source_map_len = annotation_len;
+
+    // Modules are recognized by their contents, regardless of the extension.
+    if wasm::is_wasm_module(&contents) {
+        source_mapping.kind = BundleKind::WebAssembly;
+    }
 
     Ok((contents, source_mapping))
 }
 
+// Looks for a source map of a bundle without sourceMappingURL annotation: first <bundle>.map,
+// then any source map in the same directory whose "file" field points to the bundle.
+fn find_hidden_source_mapping(path: &str, context: &ParseContext) -> Result<Option<(RawSourceMapping, String)>> {
+    if context.inline_only {
+        return Ok(None);
+    }
+
+    let sibling_map_path = format!("{path}.map");
+    let map_path = match Path::new(&sibling_map_path).is_file() {
+        true => sibling_map_path,
+        false => match context.find_hidden_map(path)? {
+            Some(map_path) => map_path,
+            None => return Ok(None),
+        },
+    };
+
+    let raw_source_mapping = parse_raw_json(&read_map_file(&map_path)?, &map_path)?;
+
+    Ok(Some((raw_source_mapping, map_path)))
+}
+
+// Maps paths of generated files to source maps in the directory that declare them in "file" field.
+fn index_hidden_maps(directory: &str) -> Result<HashMap<String, String>> {
+    #[derive(Deserialize)]
+    struct RawSourceMappingFile {
+        #[serde(default)]
+        file: String,
+    }
+
+    let read_dir_path = if directory.is_empty() { "." } else { directory };
+    let entries = fs::read_dir(read_dir_path).map_err(|err| Error::io(read_dir_path, err))?;
+
+    let mut index = HashMap::new();
+
+    for entry in entries.flatten() {
+        let map_path = entry.path();
+        let Some(map_path) = map_path.to_str().filter(|map_path| map_path.ends_with(".map")) else {
+            continue;
+        };
+
+        // Source maps named after an existing file belong to that file.
+        if Path::new(map_path.trim_end_matches(".map")).is_file() {
+            continue;
+        }
+
+        let Ok(json_str) = read_map_file(map_path) else {
+            continue;
+        };
+
+        let Ok(RawSourceMappingFile { file }) = serde_json::from_str(&json_str) else {
+            continue;
+        };
+
+        if !file.is_empty() {
+            let file_path = match directory {
+                "" => file,
+                _ => format!("{directory}/{file}"),
+            };
+
+            index
+                .entry(normalize_path(&file_path))
+                .or_insert_with(|| map_path.to_owned());
+        }
+    }
+
+    Ok(index)
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|err| Error::io(path, err))
+}
+
+// Special files like /dev/zero and files too large to be source maps are rejected before reading.
+fn read_map_file(path: &str) -> Result<String> {
+    let meta = fs::metadata(path).map_err(|err| Error::io(path, err))?;
+
+    if !meta.is_file() {
+        return Err(Error::io(
+            path,
+            io::Error::new(io::ErrorKind::InvalidInput, "not a regular file"),
+        ));
+    }
+
+    let too_large = || {
+        Error::io(
+            path,
+            io::Error::new(
+                io::ErrorKind::FileTooLarge,
+                format!("source map is larger than {} MiB", MAX_MAP_FILE_LEN / 1024 / 1024),
+            ),
+        )
+    };
+
+    if meta.len() > MAX_MAP_FILE_LEN {
+        return Err(too_large());
+    }
+
+    // File might still grow after its size was checked.
+    let mut json_str = String::with_capacity(meta.len() as usize);
+    File::open(path)
+        .and_then(|file| file.take(MAX_MAP_FILE_LEN + 1).read_to_string(&mut json_str))
+        .map_err(|err| Error::io(path, err))?;
+
+    if json_str.len() as u64 > MAX_MAP_FILE_LEN {
+        return Err(too_large());
+    }
+
+    Ok(json_str)
+}
+
+// Paths are compared after resolving symbolic links, so links cannot be used to escape the root.
+// A file given as the root stands for its directory.
+fn is_within_root(path: &Path, context: &ParseContext) -> bool {
+    let Some(map_root) = &context.map_root else {
+        return true;
+    };
+
+    let root = Path::new(map_root);
+    let root = match root.is_file() {
+        true => root.parent().unwrap_or(Path::new("")),
+        false => root,
+    };
+    let root = if root.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        root
+    };
+
+    match (fs::canonicalize(root), fs::canonicalize(path)) {
+        (Ok(root), Ok(path)) => path.starts_with(root),
+        _ => false,
+    }
+}
+
+fn parse_raw_json(json_str: &str, map_file: &str) -> Result<RawSourceMapping> {
+    serde_json::from_str(json_str).map_err(|source| Error::InvalidJson {
+        map_file: map_file.to_owned(),
+        source,
+    })
+}
+
+fn unsupported_annotation(path: &str, kind: AnnotationErrorKind) -> Error {
+    Error::UnsupportedAnnotation {
+        path: path.to_owned(),
+        kind,
+    }
+}
+
+struct SourceMappingAnnotation<'a> {
+    url: &'a str,
+    // Whole line containing the annotation, needed to exclude it from the file size.
+    line: &'a str,
+}
+
+// Looks for the last sourceMappingURL annotation, going from the end of file and skipping
+// whitespace and comments (e.g. license banners), but stopping at the first line of actual code.
+// Supports JS (//# and legacy //@) and CSS (/*# ... */) comments.
+fn find_source_mapping_annotation<'a>(contents: &'a str) -> Option<SourceMappingAnnotation<'a>> {
+    const PREFIXES: [&str; 4] = ["//#", "//@", "/*#", "/*@"];
+
+    // Lines are visited backwards, so block comments are entered at their end.
+    let mut in_block_comment = false;
+
+    for line in contents.lines().rev() {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if in_block_comment {
+            match trimmed.find("/*") {
+                // Code before the start of the comment ends the search.
+                Some(0) => in_block_comment = false,
+                Some(_) => return None,
+                None => {}
+            }
+            continue;
+        }
+
+        let parse_annotation = |comment: &'a str| {
+            PREFIXES.iter().find_map(|prefix| {
+                let rest = comment.strip_prefix(prefix)?;
+                let url = rest.trim_start().strip_prefix("sourceMappingURL=")?;
+
+                let url = if prefix.starts_with("/*") {
+                    url.strip_suffix("*/")?
+                } else {
+                    url
+                };
+
+                // URL cannot contain whitespace, otherwise it's not an annotation.
+                let url = url.trim();
+                (!url.is_empty() && !url.contains(char::is_whitespace)).then_some(url)
+            })
+        };
+
+        if let Some(url) = parse_annotation(trimmed) {
+            return Some(SourceMappingAnnotation { url, line });
+        }
+
+        // Block comments might follow code on the same line, which is common for minified CSS.
+        if trimmed.ends_with("*/") {
+            if let Some(comment) = trimmed.rfind("/*").map(|position| &trimmed[position..]) {
+                if let Some(url) = parse_annotation(comment) {
+                    return Some(SourceMappingAnnotation { url, line: comment });
+                }
+            }
+        }
+
+        if trimmed.starts_with("//") || trimmed.starts_with("/*") {
+            continue;
+        }
+
+        // Only the end of a multi-line comment may be skipped, not code followed by a comment.
+        if trimmed.ends_with("*/") && !trimmed.contains("/*") {
+            in_block_comment = true;
+            continue;
+        }
+
+        return None;
+    }
+
+    None
+}
+
 #[allow(dead_code)]
 #[derive(Default, Deserialize, Debug)]
+#[serde(default, rename_all = "camelCase")]
 struct RawSourceMapping {
     file: String,
     source_root: Option<String>,
     sources: Vec<String>,
+    sources_content: Vec<Option<Box<RawValue>>>,
     names: Vec<String>,
     mappings: String,
+    // Present only in indexed source maps, which contain no mappings on their own.
+    sections: Vec<RawSourceMappingSection>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawSourceMappingSection {
+    offset: RawSectionOffset,
+    map: RawSourceMapping,
 }
 
-fn parse_raw_source_mapping(path: &str, line: &str) -> Result<RawSourceMapping> {
-    let line_stripped = line.trim_start_matches("//# sourceMappingURL=");
+#[derive(Deserialize, Debug, Clone, Copy)]
+struct RawSectionOffset {
+    line: u32,
+    column: u32,
+}
 
-    if line_stripped.len() == line.len() {
-        return Err(anyhow!(
-            "Unsupported format: {}",
-            line.chars().take(100).collect::<String>(),
-        ));
+impl RawSectionOffset {
+    // Applies a position relative to this offset, only the first line is shifted by the column.
+    fn shift(self, position: RawSectionOffset) -> Result<RawSectionOffset> {
+        let overflow = || {
+            self.error(SectionErrorKind::Overflow {
+                line: position.line,
+                column: position.column,
+            })
+        };
+
+        Ok(RawSectionOffset {
+            line: self.line.checked_add(position.line).ok_or_else(overflow)?,
+            column: if position.line == 0 {
+                self.column.checked_add(position.column).ok_or_else(overflow)?
+            } else {
+                position.column
+            },
+        })
     }
 
-    let json_str = if line_stripped.starts_with("data:application/json;") {
-        // base64 encoded source map
-        let lookup = "base64,";
-        let index = line
-            .find(lookup)
-            .ok_or_else(|| anyhow!("File {path} does not contain base64 sourcemap."))?;
-        let (_, base64_value) = line.split_at(index + lookup.len());
+    fn error(self, kind: SectionErrorKind) -> Error {
+        Error::InvalidSection {
+            line: self.line,
+            column: self.column,
+            kind,
+        }
+    }
+}
 
-        let base64_decoded = general_purpose::STANDARD
-            .decode(base64_value)
-            .with_context(|| anyhow!("File {path} contains invalid base64 sourcemap."))?;
+// Returns the source map along with the path of the file it was read from, which for inline
+// source maps is the generated file itself.
+fn parse_raw_source_mapping(path: &str, url: &str, context: &ParseContext) -> Result<(RawSourceMapping, String)> {
+    let (json_str, map_file) = if let Some(data_url) = url.strip_prefix("data:") {
+        let json_str = decode_data_url(data_url).map_err(|err| match err {
+            DataUrlError::Annotation(kind) => unsupported_annotation(path, kind),
+            DataUrlError::Base64(source) => Error::InvalidBase64 {
+                path: path.to_owned(),
+                source,
+            },
+        })?;
 
-        String::from_utf8_lossy(&base64_decoded).into_owned()
+        (json_str, path.to_owned())
     } else {
-        let path = Path::new(path);
-        let parent = path.parent().unwrap();
-        let map_path = parent.join(line_stripped);
+        // Query string and fragment have no meaning for files on disk.
+        let url = url.split(['?', '#']).next().unwrap_or(url);
+        let url = percent_decode_str(url).decode_utf8_lossy();
+
+        let map_path = match split_url_scheme(&url) {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("file") => {
+                Path::new("/").join(rest.split_once('/').map(|(_, path)| path).unwrap_or_default())
+            }
+            Some(_) => {
+                return Err(unsupported_annotation(
+                    path,
+                    AnnotationErrorKind::NonLocalUrl(url.into_owned()),
+                ))
+            }
+            None => Path::new(path).parent().unwrap_or(Path::new("")).join(url.as_ref()),
+        };
+        let map_file = map_path.to_string_lossy().into_owned();
+
+        if context.inline_only || !map_path.exists() {
+            return Err(Error::MissingMapFile {
+                path: path.to_owned(),
+                map_file,
+            });
+        }
+
+        if !is_within_root(&map_path, context) {
+            return Err(unsupported_annotation(
+                path,
+                AnnotationErrorKind::OutsideRoot(url.into_owned()),
+            ));
+        }
 
-        fs::read_to_string(map_path)?
+        (read_map_file(&map_file)?, map_file)
     };
 
-    let raw_source_mapping: RawSourceMapping = serde_json::from_str(&json_str)?;
+    let raw_source_mapping = parse_raw_json(&json_str, &map_file)?;
+
+    Ok((raw_source_mapping, map_file))
+}
+
+const BASE64_ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
 
-    Ok(raw_source_mapping)
+#[derive(Debug)]
+enum DataUrlError {
+    Annotation(AnnotationErrorKind),
+    Base64(base64::DecodeError),
+}
+
+// Decodes data URL as described in RFC 2397, without the "data:" prefix:
+// [<mediatype>][;<parameter>=<value>]*[;base64],<data>
+fn decode_data_url(data_url: &str) -> Result<String, DataUrlError> {
+    let (header, data) = data_url
+        .split_once(',')
+        .ok_or(DataUrlError::Annotation(AnnotationErrorKind::MissingData))?;
+
+    let mut header_parts = header.split(';');
+    let media_type = header_parts.next().unwrap_or_default().trim();
+    let is_base64 = header_parts.any(|part| part.trim().eq_ignore_ascii_case("base64"));
+
+    if !matches!(
+        media_type.to_ascii_lowercase().as_str(),
+        "" | "application/json" | "text/json" | "application/octet-stream"
+    ) {
+        return Err(DataUrlError::Annotation(AnnotationErrorKind::UnsupportedMediaType(
+            media_type.to_owned(),
+        )));
+    }
+
+    let data = percent_decode_str(data).collect::<Vec<u8>>();
+
+    let data = if is_base64 {
+        BASE64_ENGINE.decode(data).map_err(DataUrlError::Base64)?
+    } else {
+        data
+    };
+
+    Ok(String::from_utf8_lossy(&data).into_owned())
 }
 
 #[derive(Debug)]
@@ -78,6 +569,11 @@ pub struct Mapping {
     pub src_file: u32,
     pub src_line: u32,
     pub src_column: u32,
+    // Index into "names" of the original identifier, if any.
+    pub name: Option<u32>,
+    // Segments with only generated column mark the end of a mapped span, source fields of such
+    // mappings are carried over from the previous mapping.
+    pub has_source: bool,
 }
 
 impl Mapping {
@@ -88,6 +584,8 @@ impl Mapping {
             src_file: 0,
             src_line: 0,
             src_column: 0,
+            name: None,
+            has_source: false,
         }
     }
 }
@@ -103,49 +601,53 @@ pub static EMPTY_MAPPING: Mapping = Mapping::const_default();
 #[derive(Debug)]
 pub struct SourceMapping {
     pub file: String,
+    // "file" field as written in the source map, empty when missing.
+    pub declared_file: String,
     pub source_root: Option<String>,
     pub sources: Vec<String>,
+    // Original contents of each source, aligned with "sources". Entries are missing when the map
+    // was generated without "sourcesContent" or omits contents for particular sources. Contents are
+    // kept as JSON string literals and unescaped only when requested.
+    pub sources_content: Vec<Option<Box<RawValue>>>,
     pub names: Vec<String>,
     pub mappings: Vec<Mapping>,
+    // Path of the source map file, equal to "file" for inline source maps. Relative sources
+    // are resolved against its directory.
+    pub map_file: String,
+    // Resolved source paths, relative to "sources_root" whenever possible. Sources that could
+    // not be resolved are kept as they were written in the source map.
+    pub source_paths: Vec<String>,
+    resolved_sources_root: String,
     // Field not present in source JSON, but read early to split presentation logic from
     // parsing and analyzing logic
     pub source_file_len: u64,
     // Field not present in source JSON, but needed for presenting meaningful results
     pub source_map_len: u64,
     pub file_name: String,
+    // Kind of the generated file, guessed from its extension.
+    pub kind: BundleKind,
 }
 
 impl SourceMapping {
     pub fn actual_source_file_len(&self) -> u64 {
-        self.source_file_len - self.source_map_len
+        self.source_file_len.saturating_sub(self.source_map_len)
     }
 
-    fn from_raw(raw_mapping: RawSourceMapping) -> Result<Self> {
-        let mut mappings: Vec<Mapping> = vec![];
-
-        for (gen_line, generated_line_mapping) in raw_mapping.mappings.split(';').enumerate() {
-            if generated_line_mapping.is_empty() {
-                continue;
-            }
-
-            let mut line_prev_column = 0i32;
-
-            for term_mapping in generated_line_mapping.split(',') {
-                let raw_mapping = vlq_decode(term_mapping)?;
-                let prev_mapping = mappings.last().unwrap_or(&EMPTY_MAPPING);
-
-                let mapping = Mapping {
-                    gen_line: gen_line as u32,
-                    gen_column: (raw_mapping[0] + line_prev_column) as u32,
-                    src_file: (raw_mapping[1] + prev_mapping.src_file as i32) as u32,
-                    src_line: (raw_mapping[2] + prev_mapping.src_line as i32) as u32,
-                    src_column: (raw_mapping[3] + prev_mapping.src_column as i32) as u32,
-                };
+    pub fn from_json(json_str: &str, map_file: String) -> Result<Self> {
+        Self::from_raw(parse_raw_json(json_str, &map_file)?, map_file)
+    }
 
-                line_prev_column = mapping.gen_column as i32;
+    fn from_raw(raw_mapping: RawSourceMapping, map_file: String) -> Result<Self> {
+        let mut flat_mapping = FlatSourceMapping::default();
 
-                mappings.push(mapping);
-            }
+        if raw_mapping.sections.is_empty() {
+            flat_mapping.mappings = decode_mappings(&raw_mapping.mappings)?;
+            flat_mapping.sources = raw_mapping.sources;
+            flat_mapping.sources_content = raw_mapping.sources_content;
+            flat_mapping.sources_content.resize(flat_mapping.sources.len(), None);
+            flat_mapping.names = raw_mapping.names;
+        } else {
+            flat_mapping.flatten_sections(raw_mapping.sections)?;
         }
 
         let file_name = match raw_mapping.file.rfind('/') {
@@ -153,74 +655,817 @@ impl SourceMapping {
             None => &raw_mapping.file,
         }
         .to_string();
+        let kind = BundleKind::from_path(Path::new(&raw_mapping.file)).unwrap_or_default();
 
-        Ok(SourceMapping {
+        let mut source_mapping = SourceMapping {
+            declared_file: raw_mapping.file.clone(),
             file: raw_mapping.file,
             source_root: raw_mapping.source_root,
-            sources: raw_mapping.sources,
-            names: raw_mapping.names,
-            mappings,
+            sources: flat_mapping.sources,
+            sources_content: flat_mapping.sources_content,
+            names: flat_mapping.names,
+            mappings: flat_mapping.mappings,
+            map_file,
+            source_paths: vec![],
+            resolved_sources_root: String::new(),
             source_file_len: 0,
             source_map_len: 0,
             file_name,
-        })
+            kind,
+        };
+        source_mapping.resolve_source_paths();
+
+        Ok(source_mapping)
+    }
+
+    pub fn resolve_source(&self, source: u32) -> Result<String, SourcePathError> {
+        let source = self
+            .sources
+            .get(source as usize)
+            .ok_or(SourcePathError::MissingSource(source))?;
+
+        resolve_source_path(self.source_root.as_deref(), source, map_directory(&self.map_file))
+    }
+
+    fn resolve_source_paths(&mut self) {
+        let resolved_sources = (0..self.sources.len() as u32)
+            .map(|source| self.resolve_source(source).ok())
+            .collect::<Vec<_>>();
+
+        self.resolved_sources_root = match &self.source_root {
+            Some(source_root) if !source_root.is_empty() => {
+                resolve_source_path(None, source_root, map_directory(&self.map_file)).unwrap_or_default()
+            }
+            _ => common_directory(resolved_sources.iter().flatten().map(|path| path.as_str())),
+        };
+
+        self.source_paths = resolved_sources
+            .into_iter()
+            .zip(&self.sources)
+            .map(|(resolved, source)| match resolved {
+                Some(resolved) => relative_to(&resolved, &self.resolved_sources_root).to_owned(),
+                None => source.clone(),
+            })
+            .collect();
+    }
+
+    // Resolved path of the source, not shortened by sources root, so that the same source has the
+    // same path in all bundles built from a project.
+    pub fn full_source_path(&self, source: u32) -> String {
+        self.resolve_source(source)
+            .unwrap_or_else(|_| self.sources.get(source as usize).cloned().unwrap_or_default())
+    }
+
+    // Sources given as URLs, e.g. webpack://app/./src/index.js. Unless they point to an absolute
+    // path, they are not relative to the source map but to the project root.
+    pub fn is_url_source(&self, source: u32) -> bool {
+        self.sources
+            .get(source as usize)
+            .is_some_and(|source| split_url_scheme(&prepend_source_root(self.source_root.as_deref(), source)).is_some())
     }
 
     pub fn is_empty(&self) -> bool {
         self.sources.is_empty() && self.mappings.is_empty()
     }
 
+    // Contents without escape sequences are borrowed directly from the JSON literal.
+    pub fn source_content(&self, source: u32) -> Option<Cow<'_, str>> {
+        let literal = self.sources_content.get(source as usize)?.as_deref()?.get();
+
+        match literal.strip_prefix('"').and_then(|literal| literal.strip_suffix('"')) {
+            Some(content) if !content.contains('\\') => Some(Cow::Borrowed(content)),
+            _ => serde_json::from_str::<String>(literal).ok().map(Cow::Owned),
+        }
+    }
+
+    pub fn source_line(&self, mapping: &Mapping) -> Option<String> {
+        self.source_content(mapping.src_file)?
+            .lines()
+            .nth(mapping.src_line as usize)
+            .map(|line| line.to_owned())
+    }
+
     pub fn sources_root(&self) -> &str {
-        match &self.source_root {
-            Some(path) if !path.is_empty() => return path,
-            _ => {}
+        if self.resolved_sources_root.is_empty() {
+            "."
+        } else {
+            &self.resolved_sources_root
+        }
+    }
+}
+
+fn decode_mappings(raw_mappings: &str) -> Result<Vec<Mapping>> {
+    // Segments take at least 2 characters including separator, usually a few more.
+    let mut mappings: Vec<Mapping> = Vec::with_capacity(raw_mappings.len() / 6);
+    let mut state = [0i64; 5];
+    let mut prev_line = 0u32;
+
+    let mut decoder = MappingsDecoder::new(raw_mappings);
+
+    while let Some(segment) = decoder.next() {
+        let segment = segment?;
+
+        if segment.line != prev_line {
+            // Generated column is the only field relative to the current line.
+            state[0] = 0;
+            prev_line = segment.line;
+        }
+
+        for (value, delta) in state.iter_mut().zip(&segment.values[..segment.len as usize]) {
+            *value = value.saturating_add(*delta as i64);
+        }
+
+        let [gen_column, src_file, src_line, src_column, name] = state
+            .map(|value| u32::try_from(value).map_err(|_| decoder.segment_error(VlqErrorKind::NegativeValue(value))));
+
+        mappings.push(Mapping {
+            gen_line: segment.line,
+            gen_column: gen_column?,
+            src_file: src_file?,
+            src_line: src_line?,
+            src_column: src_column?,
+            // Unlike other fields, name index is relative to the last mapping that had a name.
+            name: if segment.len == 5 { Some(name?) } else { None },
+            has_source: segment.len >= 4,
+        });
+    }
+
+    Ok(mappings)
+}
+
+// Result of merging all sections of an indexed source map into a single list of mappings
+// with shared "sources" and "names" tables.
+#[derive(Default)]
+struct FlatSourceMapping {
+    sources: Vec<String>,
+    sources_content: Vec<Option<Box<RawValue>>>,
+    names: Vec<String>,
+    mappings: Vec<Mapping>,
+    source_indices: HashMap<String, u32>,
+    name_indices: HashMap<String, u32>,
+}
+
+impl FlatSourceMapping {
+    fn flatten_sections(&mut self, sections: Vec<RawSourceMappingSection>) -> Result<()> {
+        self.flatten_sections_with_offset(sections, RawSectionOffset { line: 0, column: 0 })
+    }
+
+    fn flatten_sections_with_offset(
+        &mut self,
+        sections: Vec<RawSourceMappingSection>,
+        parent_offset: RawSectionOffset,
+    ) -> Result<()> {
+        let mut prev_offset: Option<RawSectionOffset> = None;
+
+        for section in sections {
+            if let Some(prev_offset) = prev_offset {
+                if (section.offset.line, section.offset.column) < (prev_offset.line, prev_offset.column) {
+                    return Err(section.offset.error(SectionErrorKind::NotOrdered {
+                        previous_line: prev_offset.line,
+                        previous_column: prev_offset.column,
+                    }));
+                }
+            }
+            prev_offset = Some(section.offset);
+
+            // Offsets of nested sections are relative to the section that contains them.
+            let offset = parent_offset.shift(section.offset)?;
+
+            if section.map.sections.is_empty() {
+                self.append_section_map(section.map, offset)?;
+            } else {
+                self.flatten_sections_with_offset(section.map.sections, offset)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn append_section_map(&mut self, map: RawSourceMapping, offset: RawSectionOffset) -> Result<()> {
+        let mut sources_content = map.sources_content.into_iter();
+        let source_indices = map
+            .sources
+            .into_iter()
+            .map(|source| {
+                let index = Self::intern(&mut self.sources, &mut self.source_indices, source);
+                let content = sources_content.next().flatten();
+
+                // The same source might be listed by several sections, first known content wins.
+                match self.sources_content.get_mut(index as usize) {
+                    Some(existing @ None) => *existing = content,
+                    Some(Some(_)) => {}
+                    None => self.sources_content.push(content),
+                }
+
+                index
+            })
+            .collect::<Vec<_>>();
+
+        let name_indices = map
+            .names
+            .into_iter()
+            .map(|name| Self::intern(&mut self.names, &mut self.name_indices, name))
+            .collect::<Vec<_>>();
+
+        for mut mapping in decode_mappings(&map.mappings)? {
+            if !mapping.has_source {
+                self.push_shifted(mapping, offset)?;
+                continue;
+            }
+
+            mapping.src_file = *source_indices.get(mapping.src_file as usize).ok_or_else(|| {
+                offset.error(SectionErrorKind::SourceOutOfRange {
+                    source: mapping.src_file,
+                    sources_len: source_indices.len(),
+                })
+            })?;
+
+            mapping.name = match mapping.name {
+                Some(name) => Some(*name_indices.get(name as usize).ok_or_else(|| {
+                    offset.error(SectionErrorKind::NameOutOfRange {
+                        name,
+                        names_len: name_indices.len(),
+                    })
+                })?),
+                None => None,
+            };
+
+            self.push_shifted(mapping, offset)?;
+        }
+
+        Ok(())
+    }
+
+    fn push_shifted(&mut self, mut mapping: Mapping, offset: RawSectionOffset) -> Result<()> {
+        let shifted = offset.shift(RawSectionOffset {
+            line: mapping.gen_line,
+            column: mapping.gen_column,
+        })?;
+        mapping.gen_line = shifted.line;
+        mapping.gen_column = shifted.column;
+
+        self.mappings.push(mapping);
+
+        Ok(())
+    }
+
+    fn intern(values: &mut Vec<String>, indices: &mut HashMap<String, u32>, value: String) -> u32 {
+        *indices.entry(value).or_insert_with_key(|value| {
+            values.push(value.clone());
+            (values.len() - 1) as u32
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourcePathError {
+    MissingSource(u32),
+    EmptySource,
+    UnsupportedScheme(String),
+    NonLocalFileUrl(String),
+}
+
+impl fmt::Display for SourcePathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourcePathError::MissingSource(source) => write!(f, "Source {source} does not exist"),
+            SourcePathError::EmptySource => write!(f, "Source path is empty"),
+            SourcePathError::UnsupportedScheme(url) => write!(f, "Unsupported URL scheme in source {url}"),
+            SourcePathError::NonLocalFileUrl(url) => write!(f, "File URL {url} does not point to a local file"),
         }
+    }
+}
 
-        resolve_relative_path(self.sources.first().unwrap(), &self.file)
+impl std::error::Error for SourcePathError {}
+
+// Resolves source as described by the source map specification: "sourceRoot" is prepended to
+// relative sources and the result is resolved against the directory of the source map. URLs are
+// then turned into a canonical path, e.g. webpack://app/./src/index.js becomes src/index.js and
+// file:///app/src/index.js becomes /app/src/index.js.
+pub fn resolve_source_path(
+    source_root: Option<&str>,
+    source: &str,
+    map_directory: &str,
+) -> Result<String, SourcePathError> {
+    let source = prepend_source_root(source_root, source);
+
+    if source.is_empty() {
+        return Err(SourcePathError::EmptySource);
+    }
+
+    if let Some((scheme, rest)) = split_url_scheme(&source) {
+        return match scheme.to_ascii_lowercase().as_str() {
+            // Webpack URLs contain a namespace (usually the project name) followed by a path
+            // relative to the project root.
+            "webpack" => {
+                let path = rest.split_once('/').map(|(_, path)| path).unwrap_or_default();
+                Ok(normalize_path(path.trim_start_matches('/')))
+            }
+            "file" => {
+                let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+                if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+                    return Err(SourcePathError::NonLocalFileUrl(source));
+                }
+
+                Ok(normalize_path(&format!("/{path}")))
+            }
+            _ => Err(SourcePathError::UnsupportedScheme(source)),
+        };
+    }
+
+    if source.starts_with('/') || map_directory.is_empty() {
+        Ok(normalize_path(&source))
+    } else {
+        Ok(normalize_path(&format!("{map_directory}/{source}")))
+    }
+}
+
+// Absolute paths and URLs are used as they are.
+fn prepend_source_root(source_root: Option<&str>, source: &str) -> String {
+    let is_absolute = source.starts_with('/') || split_url_scheme(source).is_some();
+
+    match source_root {
+        Some(source_root) if !source_root.is_empty() && !is_absolute => {
+            let separator = if source_root.ends_with('/') { "" } else { "/" };
+            format!("{source_root}{separator}{source}")
+        }
+        _ => source.to_owned(),
     }
 }
 
-fn resolve_relative_path<'a>(relative_path: &'a str, relative_to: &'a str) -> &'a str {
-    const PREFIX_LENGTH: usize = "../".len();
-    // Finds the position of a first character after ../
-    let relative_jumps = relative_path.chars().position(|c| c != '.' && c != '/').unwrap() / PREFIX_LENGTH;
+// Returns scheme and the part after "://", if given value is an URL.
+fn split_url_scheme(value: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = value.split_once("://")?;
+
+    let mut chars = scheme.chars();
+    let is_scheme = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+
+    is_scheme.then_some((scheme, rest))
+}
+
+// Lexically removes "." and ".." segments. Leading ".." segments are kept for relative paths,
+// as there is nothing to go back from, and dropped for absolute paths.
+pub(crate) fn normalize_path(path: &str) -> String {
+    let is_absolute = path.starts_with('/');
+    let mut segments: Vec<&str> = vec![];
 
-    let mut skipped_parts = 0;
-    for (index, c) in relative_to.chars().rev().enumerate() {
-        if c == '/' {
-            skipped_parts += 1;
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => match segments.last() {
+                Some(&last) if last != ".." => {
+                    segments.pop();
+                }
+                _ if !is_absolute => segments.push(".."),
+                _ => {}
+            },
+            _ => segments.push(segment),
         }
+    }
+
+    let path = segments.join("/");
+
+    if is_absolute {
+        format!("/{path}")
+    } else {
+        path
+    }
+}
+
+fn map_directory(map_file: &str) -> &str {
+    match map_file.rfind('/') {
+        Some(pos) => &map_file[..pos],
+        None => "",
+    }
+}
+
+// Finds the deepest directory containing all given paths.
+pub(crate) fn common_directory<'a>(mut paths: impl Iterator<Item = &'a str>) -> String {
+    let Some(first) = paths.next() else {
+        return String::new();
+    };
 
-        if skipped_parts == relative_jumps + 1 {
-            return relative_to.get(0..(relative_to.len() - index - 1)).unwrap();
+    let mut common = map_directory(first);
+
+    for path in paths {
+        while !common.is_empty() && relative_to(path, common).len() == path.len() {
+            common = map_directory(common);
         }
     }
 
-    unreachable!();
+    common.to_owned()
+}
+
+pub(crate) fn relative_to<'a>(path: &'a str, directory: &str) -> &'a str {
+    if directory.is_empty() {
+        return path;
+    }
+
+    match path.strip_prefix(directory) {
+        Some(rest) if rest.starts_with('/') => &rest[1..],
+        _ => path,
+    }
 }
 
 #[cfg(any(test, rust_analyzer))]
 mod test {
-    use crate::parser::resolve_relative_path;
+    use std::{borrow::Cow, fs};
+
+    use crate::{
+        analyzer::{calculate_size_by_file, AnalyzeOptions},
+        discovery::BundleKind,
+        error::{AnnotationErrorKind, Error, ErrorKind},
+        test_utils::TempDir,
+        wasm::tests::example_module,
+    };
+
+    use crate::parser::{
+        decode_data_url, find_source_mapping_annotation, parse_file_by_path, parse_file_contents, resolve_source_path,
+        ParseContext, RawSourceMapping, SourceMapping, SourcePathError,
+    };
 
     #[test]
     fn works_for_example_paths() {
-        let test_paths = [
+        let raw_mapping: RawSourceMapping = serde_json::from_str(
+            r#"{
+                "version": 3,
+                "sources": [
+                    "../../../node_modules/quasar/src/composables/private/use-tick.js",
+                    "../../../src/components/paginations/BasicFilter.vue"
+                ],
+                "names": [],
+                "mappings": ""
+            }"#,
+        )
+        .unwrap();
+
+        let mapping = SourceMapping::from_raw(
+            raw_mapping,
+            "./test_files/quasar-admin/dist/spa/assets/BasicFilter.09ac81e1.js.map".to_owned(),
+        )
+        .unwrap();
+
+        assert_eq!(mapping.sources_root(), "test_files/quasar-admin");
+        assert_eq!(
+            mapping.source_paths,
+            [
+                "node_modules/quasar/src/composables/private/use-tick.js",
+                "src/components/paginations/BasicFilter.vue"
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_source_urls() {
+        let test_sources = [
+            (None, "../src/index.js", "dist/assets", Ok("dist/src/index.js")),
+            (None, "./../../../src/index.js", "dist/assets", Ok("../src/index.js")),
+            (None, "../../../../index.js", "/app/dist", Ok("/index.js")),
+            (Some("/app/"), "src/index.js", "dist", Ok("/app/src/index.js")),
+            (Some("../lib"), "index.js", "dist", Ok("lib/index.js")),
+            (Some("src"), "webpack:///./a.js", "dist", Ok("a.js")),
+            (Some("root"), "/abs/a.js", "dist", Ok("/abs/a.js")),
+            (None, "webpack://app/./src/index.js", "dist", Ok("src/index.js")),
+            (None, "webpack:///./src/index.js", "dist", Ok("src/index.js")),
+            (None, "file:///app/src/index.js", "dist", Ok("/app/src/index.js")),
+            (None, "file://localhost/app/index.js", "dist", Ok("/app/index.js")),
+            (
+                None,
+                "file://server/app/index.js",
+                "dist",
+                Err(SourcePathError::NonLocalFileUrl(
+                    "file://server/app/index.js".to_owned(),
+                )),
+            ),
+            (
+                None,
+                "https://example.com/index.js",
+                "dist",
+                Err(SourcePathError::UnsupportedScheme(
+                    "https://example.com/index.js".to_owned(),
+                )),
+            ),
+            (None, "", "dist", Err(SourcePathError::EmptySource)),
+        ];
+
+        for (source_root, source, map_directory, expected) in test_sources {
+            assert_eq!(
+                resolve_source_path(source_root, source, map_directory),
+                expected.map(|path| path.to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn flattens_indexed_source_map() {
+        let raw_mapping: RawSourceMapping = serde_json::from_str(
+            r#"{
+                "version": 3,
+                "file": "bundle.js",
+                "sections": [
+                    {
+                        "offset": { "line": 0, "column": 0 },
+                        "map": { "version": 3, "sources": ["a.js"], "names": ["foo"], "mappings": "AAAAA,IAAI;AACA" }
+                    },
+                    {
+                        "offset": { "line": 1, "column": 10 },
+                        "map": { "version": 3, "sources": ["b.js", "a.js"], "names": ["bar", "foo"], "mappings": "AAAA,ECAAC;AACAD" }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let mapping = SourceMapping::from_raw(raw_mapping, "bundle.js.map".to_owned()).unwrap();
+
+        assert_eq!(mapping.sources, ["a.js", "b.js"]);
+        assert_eq!(mapping.names, ["foo", "bar"]);
+
+        let positions = mapping
+            .mappings
+            .iter()
+            .map(|mapping| (mapping.gen_line, mapping.gen_column, mapping.src_file))
+            .collect::<Vec<_>>();
+
+        let names = mapping.mappings.iter().map(|mapping| mapping.name).collect::<Vec<_>>();
+
+        assert_eq!(names, [Some(0), None, None, None, Some(0), Some(1)]);
+
+        assert_eq!(
+            positions,
+            [(0, 0, 0), (0, 4, 0), (1, 0, 0), (1, 10, 1), (1, 12, 0), (2, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn reads_sources_content() {
+        let raw_mapping: RawSourceMapping = serde_json::from_str(
+            r#"{
+                "version": 3,
+                "sources": ["a.js", "b.js", "c.js", "d.js"],
+                "sourcesContent": ["const a = 1;\nexport default a;\n", null, null, "d();"],
+                "names": [],
+                "mappings": "AAAA;AACA,ECAA"
+            }"#,
+        )
+        .unwrap();
+
+        let mapping = SourceMapping::from_raw(raw_mapping, "bundle.js.map".to_owned()).unwrap();
+
+        assert_eq!(
+            mapping.source_content(0).as_deref(),
+            Some("const a = 1;\nexport default a;\n")
+        );
+        assert_eq!(mapping.source_content(1), None);
+        assert_eq!(mapping.source_content(2), None);
+        assert!(matches!(mapping.source_content(3), Some(Cow::Borrowed("d();"))));
+        assert_eq!(
+            mapping.source_line(&mapping.mappings[1]).as_deref(),
+            Some("export default a;")
+        );
+        assert_eq!(mapping.source_line(&mapping.mappings[2]), None);
+    }
+
+    #[test]
+    fn finds_source_mapping_annotation() {
+        let test_contents = [
+            ("a();\n//# sourceMappingURL=a.js.map", Some("a.js.map")),
+            ("a();\n//# sourceMappingURL=a.js.map\n\n\n", Some("a.js.map")),
+            ("a();\n//@ sourceMappingURL=a.js.map", Some("a.js.map")),
+            ("a{}\n/*# sourceMappingURL=a.css.map */\n", Some("a.css.map")),
+            ("a{color:red}/*# sourceMappingURL=a.css.map */", Some("a.css.map")),
+            ("a{color:red}/* sourceMappingURL=a.css.map */", None),
+            (
+                "a();\n//# sourceMappingURL=a.js.map\n/*! License\n * MIT\n */\n",
+                Some("a.js.map"),
+            ),
             (
-                "../../../node_modules/quasar/src/composables/private/use-tick.js",
-                "./test_files/quasar-admin/dist/spa/assets/use-timeout.cdce10a0.js",
+                "//# sourceMappingURL=old.js.map\na();\n//# sourceMappingURL=new.js.map",
+                Some("new.js.map"),
             ),
+            ("//# sourceMappingURL=a.js.map\na();\n", None),
+            ("//# sourceMappingURL=a.js.map\nfoo(); /* x */\n", None),
+            ("//# sourceMappingURL=a.js.map\nfoo(); /* x\n y */\n", None),
             (
-                "../../../src/components/paginations/BasicFilter.vue",
-                "./test_files/quasar-admin/dist/spa/assets/BasicFilter.09ac81e1.js",
+                "a();\n//# sourceMappingURL=a.js.map\n/*\n License\n*/\n",
+                Some("a.js.map"),
             ),
+            ("a();\n// sourceMappingURL=a.js.map", None),
+            ("a();", None),
         ];
 
-        for (relative_path, relative_to) in test_paths {
+        for (contents, expected) in test_contents {
             assert_eq!(
-                resolve_relative_path(relative_path, relative_to),
-                "./test_files/quasar-admin"
+                find_source_mapping_annotation(contents).map(|annotation| annotation.url),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_data_urls() {
+        let test_urls = [
+            "application/json;base64,eyJ2ZXJzaW9uIjozfQ==",
+            "application/json;charset=utf-8;base64,eyJ2ZXJzaW9uIjozfQ",
+            "application/json;charset=utf-8,%7B%22version%22%3A3%7D",
+            "application/json,{\"version\":3}",
+        ];
+
+        for url in test_urls {
+            assert_eq!(decode_data_url(url).unwrap(), r#"{"version":3}"#);
+        }
+
+        assert!(decode_data_url("text/html;base64,PGgxPg==").is_err());
+        assert!(decode_data_url("application/json;base64").is_err());
+    }
+
+    #[test]
+    fn pairs_hidden_source_maps() {
+        let dir = TempDir::new("hidden-maps");
+
+        let map = r#"{"version":3,"file":"renamed.js","sources":["a.js"],"names":[],"mappings":"AAAA"}"#;
+        fs::write(dir.join("sibling.js"), "a();\n").unwrap();
+        fs::write(dir.join("sibling.js.map"), map).unwrap();
+        fs::write(dir.join("renamed.js"), "a();\n").unwrap();
+        fs::write(dir.join("renamed.abc123.map"), map).unwrap();
+
+        let path = |file: &str| dir.join(file).to_str().unwrap().to_owned();
+        let context = ParseContext::default();
+
+        for (input, expected_file, expected_map) in [
+            ("sibling.js", "sibling.js", "sibling.js.map"),
+            ("renamed.js", "renamed.js", "renamed.abc123.map"),
+            ("renamed.abc123.map", "renamed.js", "renamed.abc123.map"),
+        ] {
+            let (_, mapping) = parse_file_by_path(&path(input), &context).unwrap();
+            assert_eq!(mapping.file_name, expected_file);
+            assert_eq!(mapping.map_file, path(expected_map));
+            assert_eq!(mapping.source_map_len, 0);
+        }
+
+        // Source maps in the directory are indexed only once.
+        assert_eq!(context.hidden_maps.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn parses_css_bundles() {
+        let dir = TempDir::new("css");
+
+        let map = r#"{"version":3,"sources":["../src/main.scss","../src/theme.less"],"names":[],"mappings":"AAAA,YCAA;AAAA"}"#;
+        fs::write(
+            dir.join("main.css"),
+            "a{color:red}b{margin:0}\nc{}\n/*# sourceMappingURL=main.css.map */",
+        )
+        .unwrap();
+        fs::write(dir.join("main.css.map"), map).unwrap();
+
+        let (contents, mapping) =
+            parse_file_by_path(dir.join("main.css").to_str().unwrap(), &ParseContext::default()).unwrap();
+        assert_eq!(mapping.kind, BundleKind::Css);
+        assert_eq!(mapping.source_paths, ["main.scss", "theme.less"]);
+
+        let info = calculate_size_by_file(&contents, mapping, &AnalyzeOptions::default()).unwrap();
+        let bytes = info.info_by_file.iter().map(|info| info.bytes).collect::<Vec<_>>();
+        assert_eq!(bytes, [12, 12 + 4]);
+    }
+
+    #[test]
+    fn parses_wasm_modules() {
+        let dir = TempDir::new("wasm");
+
+        let map = r#"{"version":3,"sources":["src/lib.rs"],"names":[],"mappings":"GAAA"}"#;
+        fs::write(dir.join("module.wasm"), example_module("module.wasm.map")).unwrap();
+        fs::write(dir.join("module.wasm.map"), map).unwrap();
+
+        for input in ["module.wasm", "module.wasm.map"] {
+            let (_, mapping) = parse_file_by_path(dir.join(input).to_str().unwrap(), &ParseContext::default()).unwrap();
+            assert_eq!(mapping.kind, BundleKind::WebAssembly);
+            assert_eq!(mapping.source_map_len, 35);
+        }
+    }
+
+    #[test]
+    fn classifies_parsing_errors() {
+        let dir = TempDir::new("errors");
+
+        let test_files = [
+            (
+                "missing.js",
+                "a();\n//# sourceMappingURL=missing.js.map",
+                ErrorKind::MissingMapFile,
+            ),
+            ("plain.js", "a();\n", ErrorKind::UnsupportedAnnotation),
+            (
+                "remote.js",
+                "a();\n//# sourceMappingURL=https://a.com/a.js.map",
+                ErrorKind::UnsupportedAnnotation,
+            ),
+            (
+                "base64.js",
+                "a();\n//# sourceMappingURL=data:application/json;base64,e!==",
+                ErrorKind::InvalidBase64,
+            ),
+            (
+                "json.js",
+                "a();\n//# sourceMappingURL=data:application/json,{",
+                ErrorKind::InvalidJson,
+            ),
+            (
+                "vlq.js",
+                "a();\n//# sourceMappingURL=data:application/json,{\"mappings\":\"A!\"}",
+                ErrorKind::InvalidVlq,
+            ),
+            (
+                "negative.js",
+                "a();\n//# sourceMappingURL=data:application/json,{\"mappings\":\"D\"}",
+                ErrorKind::InvalidVlq,
+            ),
+            ("orphan.js.map", "{}", ErrorKind::MissingGeneratedFile),
+        ];
+
+        for (file, contents, _) in test_files {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+
+        for (file, _, expected) in test_files {
+            let error = parse_file_by_path(dir.join(file).to_str().unwrap(), &ParseContext::default()).unwrap_err();
+            assert_eq!(error.kind(), expected, "{file}: {error}");
+        }
+
+        let error = parse_file_by_path(dir.join("absent.js").to_str().unwrap(), &ParseContext::default()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Io);
+    }
+
+    #[test]
+    fn parses_inline_source_maps_without_reading_files() {
+        let context = ParseContext::inline_only();
+
+        let contents = r#"a();
+//# sourceMappingURL=data:application/json,{"sources":["a.js"],"mappings":"AAAA"}"#;
+        let (_, mapping) = parse_file_contents("dist/main.js", contents.as_bytes().to_vec(), &context).unwrap();
+        assert_eq!(mapping.sources, ["a.js"]);
+        assert_eq!(mapping.source_file_len, contents.len() as u64);
+
+        let contents = b"a();\n//# sourceMappingURL=passwd".to_vec();
+        let error = parse_file_contents("/etc/main.js", contents, &context).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::MissingMapFile, "{error}");
+
+        let error = parse_file_contents("main.js", b"a();\n".to_vec(), &context).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnsupportedAnnotation, "{error}");
+    }
+
+    #[test]
+    fn reads_map_files_only_within_root() {
+        let dir = TempDir::new("map-root");
+        fs::create_dir_all(dir.join("dist/maps.map")).unwrap();
+
+        let map = r#"{"version":3,"sources":["a.js"],"names":[],"mappings":"AAAA"}"#;
+        fs::write(dir.join("outside.js.map"), map).unwrap();
+
+        let test_files = [
+            ("parent.js", "../outside.js.map".to_owned()),
+            ("absolute.js", dir.join("outside.js.map").to_str().unwrap().to_owned()),
+            ("url.js", "file:///dev/zero".to_owned()),
+        ];
+
+        let root = dir.join("dist");
+        let root = root.to_str().unwrap();
+
+        for (file, url) in &test_files {
+            let path = dir.join("dist").join(file);
+            fs::write(&path, format!("a();\n//# sourceMappingURL={url}")).unwrap();
+
+            let error = parse_file_by_path(path.to_str().unwrap(), &ParseContext::new(Some(root))).unwrap_err();
+            assert!(
+                matches!(
+                    &error,
+                    Error::UnsupportedAnnotation {
+                        kind: AnnotationErrorKind::OutsideRoot(_),
+                        ..
+                    }
+                ),
+                "{file}: {error}"
+            );
+        }
+
+        // Once allowed, files outside of the root are read, but only regular ones.
+        for (file, _) in &test_files[..2] {
+            assert!(
+                parse_file_by_path(dir.join("dist").join(file).to_str().unwrap(), &ParseContext::default()).is_ok()
             );
         }
+
+        let error =
+            parse_file_by_path(dir.join("dist/url.js").to_str().unwrap(), &ParseContext::default()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Io, "{error}");
+
+        fs::write(dir.join("dist/directory.js"), "a();\n//# sourceMappingURL=maps.map").unwrap();
+        let error = parse_file_by_path(
+            dir.join("dist/directory.js").to_str().unwrap(),
+            &ParseContext::new(Some(root)),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Io, "{error}");
     }
 }
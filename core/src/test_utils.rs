@@ -0,0 +1,76 @@
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{
+    analyzer::{calculate_size_by_file, AnalyzeOptions, SourceMappingInfo},
+    parser::SourceMapping,
+};
+
+// Directory for files of a single test, removed once it goes out of scope, also when the test fails.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        // Tests run in parallel, so each directory gets a unique suffix.
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let suffix = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let path = std::env::temp_dir().join(format!("svis-{name}-{}-{suffix}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        TempDir(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// Bundle placed in dist directory, each line of contents is mapped to the next source, given as
+// path and content.
+pub fn analyze_bundle(bundle: &str, contents: &str, sources: &[(&str, &str)]) -> SourceMappingInfo {
+    let mut source_mapping = SourceMapping::from_json(
+        &format!(
+            r#"{{
+                "version": 3,
+                "sources": [{}],
+                "sourcesContent": [{}],
+                "names": [],
+                "mappings": "AAAA{}"
+            }}"#,
+            sources
+                .iter()
+                .map(|(path, _)| format!("{path:?}"))
+                .collect::<Vec<_>>()
+                .join(","),
+            sources
+                .iter()
+                .map(|(_, content)| format!("{content:?}"))
+                .collect::<Vec<_>>()
+                .join(","),
+            ";ACAA".repeat(sources.len() - 1),
+        ),
+        format!("dist/{bundle}.map"),
+    )
+    .unwrap();
+    source_mapping.file = format!("dist/{bundle}");
+    source_mapping.source_file_len = contents.len() as u64;
+
+    calculate_size_by_file(contents.as_bytes(), source_mapping, &AnalyzeOptions::default()).unwrap()
+}
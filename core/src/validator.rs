@@ -0,0 +1,517 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use super::{
+    discovery::{discover_files, BundleKind, DiscoveryOptions},
+    error::Error,
+    lines::{split_lines, ColumnCursor, GeneratedLine},
+    parser::{parse_file_by_path, ParseContext, SourceMapping},
+    wasm::{parse_sections, CODE_SECTION_ID},
+};
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+// Problems found in a source map. Lines and columns are 0-based in the fields, but presented
+// as 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueKind {
+    LineOutOfRange {
+        line: u32,
+        column: u32,
+        lines_len: usize,
+    },
+    ColumnOutOfRange {
+        line: u32,
+        column: u32,
+        line_len: u32,
+    },
+    SourceOutOfRange {
+        line: u32,
+        column: u32,
+        source: u32,
+        sources_len: usize,
+    },
+    NameOutOfRange {
+        line: u32,
+        column: u32,
+        name: u32,
+        names_len: usize,
+    },
+    ColumnsNotOrdered {
+        line: u32,
+        column: u32,
+        previous_column: u32,
+    },
+    // Source map contains no "sourcesContent" at all.
+    NoSourcesContent,
+    MissingSourceContent {
+        source: u32,
+    },
+    // "file" field names a different file than the generated file the map was found for.
+    FileMismatch {
+        declared_file: String,
+    },
+    DuplicateSource {
+        source: u32,
+        first_source: u32,
+    },
+    UnusedSource {
+        source: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub kind: IssueKind,
+    // Source path as written in the source map, for issues concerning a single source.
+    pub source: Option<String>,
+}
+
+impl ValidationIssue {
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            IssueKind::LineOutOfRange { .. }
+            | IssueKind::ColumnOutOfRange { .. }
+            | IssueKind::SourceOutOfRange { .. }
+            | IssueKind::NameOutOfRange { .. }
+            | IssueKind::ColumnsNotOrdered { .. } => Severity::Error,
+            IssueKind::NoSourcesContent
+            | IssueKind::MissingSourceContent { .. }
+            | IssueKind::FileMismatch { .. }
+            | IssueKind::DuplicateSource { .. }
+            | IssueKind::UnusedSource { .. } => Severity::Warning,
+        }
+    }
+
+    // Stable identifier of the check, meant for filtering output.
+    pub fn code(&self) -> &'static str {
+        match self.kind {
+            IssueKind::LineOutOfRange { .. } => "line-out-of-range",
+            IssueKind::ColumnOutOfRange { .. } => "column-out-of-range",
+            IssueKind::SourceOutOfRange { .. } => "source-out-of-range",
+            IssueKind::NameOutOfRange { .. } => "name-out-of-range",
+            IssueKind::ColumnsNotOrdered { .. } => "columns-not-ordered",
+            IssueKind::NoSourcesContent => "no-sources-content",
+            IssueKind::MissingSourceContent { .. } => "missing-source-content",
+            IssueKind::FileMismatch { .. } => "file-mismatch",
+            IssueKind::DuplicateSource { .. } => "duplicate-source",
+            IssueKind::UnusedSource { .. } => "unused-source",
+        }
+    }
+
+    // Position in the generated file of the mapping the issue was found at, 0-based.
+    pub fn position(&self) -> Option<(u32, u32)> {
+        match self.kind {
+            IssueKind::LineOutOfRange { line, column, .. }
+            | IssueKind::ColumnOutOfRange { line, column, .. }
+            | IssueKind::SourceOutOfRange { line, column, .. }
+            | IssueKind::NameOutOfRange { line, column, .. }
+            | IssueKind::ColumnsNotOrdered { line, column, .. } => Some((line, column)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = self.source.as_deref().unwrap_or_default();
+
+        match &self.kind {
+            IssueKind::LineOutOfRange { line, lines_len, .. } => write!(
+                f,
+                "Mapping points to line {}, but generated file has only {lines_len} lines",
+                line + 1
+            ),
+            IssueKind::ColumnOutOfRange { column, line_len, .. } => write!(
+                f,
+                "Mapping points to column {}, but the line is only {line_len} columns long",
+                column + 1
+            ),
+            IssueKind::SourceOutOfRange {
+                source, sources_len, ..
+            } => write!(
+                f,
+                "Mapping references source {source}, but there are only {sources_len} sources"
+            ),
+            IssueKind::NameOutOfRange { name, names_len, .. } => {
+                write!(
+                    f,
+                    "Mapping references name {name}, but there are only {names_len} names"
+                )
+            }
+            IssueKind::ColumnsNotOrdered { previous_column, .. } => write!(
+                f,
+                "Mapping precedes the previous mapping at column {} in the same line",
+                previous_column + 1
+            ),
+            IssueKind::NoSourcesContent => write!(f, "Source map contains no \"sourcesContent\""),
+            IssueKind::MissingSourceContent { .. } => write!(f, "Source {source:?} has no entry in \"sourcesContent\""),
+            IssueKind::FileMismatch { declared_file } => write!(
+                f,
+                "\"file\" field is {declared_file:?}, which does not match the generated file"
+            ),
+            IssueKind::DuplicateSource { first_source, .. } => write!(
+                f,
+                "Source {source:?} resolves to the same path as source {first_source}"
+            ),
+            IssueKind::UnusedSource { .. } => write!(f, "Source {source:?} is not referenced by any mapping"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ValidationReport {
+    // Generated file and source map, as they were found.
+    pub file: String,
+    pub map_file: String,
+    // Sorted by position, issues concerning the whole map come last.
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues.iter().filter(|issue| issue.severity() == severity).count()
+    }
+}
+
+pub fn validate_path(
+    path: &str,
+    options: &DiscoveryOptions,
+    mut on_file_result: impl FnMut(&str, Result<ValidationReport, Error>),
+) -> Result<()> {
+    let context = ParseContext::new(options.map_root(path));
+
+    for file in discover_files(path, options)?.iter() {
+        on_file_result(file, validate_file(file, &context));
+    }
+
+    Ok(())
+}
+
+// Fails only when the source map cannot be found or parsed at all.
+pub fn validate_file(file: &str, context: &ParseContext) -> Result<ValidationReport, Error> {
+    let (file_contents, source_mapping) = parse_file_by_path(file, context)?;
+
+    Ok(ValidationReport {
+        issues: validate_source_mapping(&file_contents, &source_mapping),
+        file: source_mapping.file.clone(),
+        map_file: source_mapping.map_file.clone(),
+    })
+}
+
+pub fn validate_source_mapping(file_contents: &[u8], source_mapping: &SourceMapping) -> Vec<ValidationIssue> {
+    let mut issues = validate_mappings(file_contents, source_mapping);
+    issues.extend(validate_sources(source_mapping));
+
+    if !source_mapping.declared_file.is_empty()
+        && file_name(&source_mapping.declared_file) != file_name(&source_mapping.file)
+    {
+        issues.push(ValidationIssue {
+            kind: IssueKind::FileMismatch {
+                declared_file: source_mapping.declared_file.clone(),
+            },
+            source: None,
+        });
+    }
+
+    // Sorting is stable, so issues at the same position and those without one keep their order.
+    issues.sort_by_key(|issue| match issue.position() {
+        Some((line, column)) => (false, line, column),
+        None => (true, 0, 0),
+    });
+
+    issues
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .unwrap_or(path)
+}
+
+// Length of generated lines in source map columns. WebAssembly modules have a single line,
+// with columns being byte offsets into the code section.
+enum GeneratedLines<'a> {
+    Text(&'a [u8], Vec<GeneratedLine>),
+    WebAssembly(usize),
+}
+
+impl GeneratedLines<'_> {
+    fn len(&self) -> usize {
+        match self {
+            GeneratedLines::Text(_, lines) => lines.len(),
+            GeneratedLines::WebAssembly(_) => 1,
+        }
+    }
+
+    fn line_len(&self, line: u32) -> u32 {
+        match self {
+            GeneratedLines::Text(contents, lines) => {
+                let line = lines[line as usize];
+                ColumnCursor::new(&contents[line.start..line.end]).column(line.end - line.start)
+            }
+            GeneratedLines::WebAssembly(code_len) => *code_len as u32,
+        }
+    }
+}
+
+fn validate_mappings(file_contents: &[u8], source_mapping: &SourceMapping) -> Vec<ValidationIssue> {
+    let lines = match source_mapping.kind {
+        BundleKind::WebAssembly => {
+            let code_len = parse_sections(file_contents)
+                .ok()
+                .and_then(|sections| {
+                    sections
+                        .into_iter()
+                        .find(|section| section.id == CODE_SECTION_ID)
+                        .map(|section| section.end - section.payload_start)
+                })
+                .unwrap_or_default();
+            GeneratedLines::WebAssembly(code_len)
+        }
+        _ => GeneratedLines::Text(file_contents, split_lines(file_contents)),
+    };
+
+    let mut issues = vec![];
+    let mut issue = |kind: IssueKind| issues.push(ValidationIssue { kind, source: None });
+
+    // Length of the current line, computed once per line with mappings.
+    let mut current_line: Option<(u32, u32)> = None;
+    let mut previous_column: Option<u32> = None;
+
+    for mapping in &source_mapping.mappings {
+        let (line, column) = (mapping.gen_line, mapping.gen_column);
+
+        if current_line.map(|(current_line, _)| current_line) != Some(line) {
+            previous_column = None;
+
+            if (line as usize) < lines.len() {
+                current_line = Some((line, lines.line_len(line)));
+            } else {
+                current_line = None;
+            }
+        }
+
+        match current_line {
+            None => issue(IssueKind::LineOutOfRange {
+                line,
+                column,
+                lines_len: lines.len(),
+            }),
+            Some((_, line_len)) if column > line_len => issue(IssueKind::ColumnOutOfRange { line, column, line_len }),
+            _ => {}
+        }
+
+        if let Some(previous_column) = previous_column.filter(|previous_column| column < *previous_column) {
+            issue(IssueKind::ColumnsNotOrdered {
+                line,
+                column,
+                previous_column,
+            });
+        }
+        previous_column = Some(column);
+
+        if !mapping.has_source {
+            continue;
+        }
+
+        if mapping.src_file as usize >= source_mapping.sources.len() {
+            issue(IssueKind::SourceOutOfRange {
+                line,
+                column,
+                source: mapping.src_file,
+                sources_len: source_mapping.sources.len(),
+            });
+        }
+
+        if let Some(name) = mapping.name.filter(|name| *name as usize >= source_mapping.names.len()) {
+            issue(IssueKind::NameOutOfRange {
+                line,
+                column,
+                name,
+                names_len: source_mapping.names.len(),
+            });
+        }
+    }
+
+    issues
+}
+
+fn validate_sources(source_mapping: &SourceMapping) -> Vec<ValidationIssue> {
+    let mut issues = vec![];
+    let mut issue = |kind: IssueKind, source: u32| {
+        issues.push(ValidationIssue {
+            kind,
+            source: Some(source_mapping.sources[source as usize].clone()),
+        })
+    };
+
+    let mut used_sources = vec![false; source_mapping.sources.len()];
+    for mapping in source_mapping.mappings.iter().filter(|mapping| mapping.has_source) {
+        if let Some(used) = used_sources.get_mut(mapping.src_file as usize) {
+            *used = true;
+        }
+    }
+
+    let has_sources_content = source_mapping.sources_content.iter().any(Option::is_some);
+    let mut first_source_by_path: HashMap<&str, u32> = HashMap::new();
+
+    for source in 0..source_mapping.sources.len() as u32 {
+        let path = source_mapping.source_paths[source as usize].as_str();
+
+        match first_source_by_path.get(path) {
+            Some(first_source) => issue(
+                IssueKind::DuplicateSource {
+                    source,
+                    first_source: *first_source,
+                },
+                source,
+            ),
+            None => {
+                first_source_by_path.insert(path, source);
+            }
+        }
+
+        if !used_sources[source as usize] {
+            issue(IssueKind::UnusedSource { source }, source);
+        }
+
+        if has_sources_content && source_mapping.sources_content[source as usize].is_none() {
+            issue(IssueKind::MissingSourceContent { source }, source);
+        }
+    }
+
+    if !has_sources_content && !source_mapping.sources.is_empty() {
+        issues.push(ValidationIssue {
+            kind: IssueKind::NoSourcesContent,
+            source: None,
+        });
+    }
+
+    issues
+}
+
+#[cfg(any(test, rust_analyzer))]
+mod tests {
+    use crate::{
+        parser::SourceMapping,
+        validator::{validate_source_mapping, IssueKind, Severity},
+    };
+
+    #[test]
+    fn reports_broken_source_maps() {
+        let contents = "a=1;\nb=2;";
+
+        // Mappings: column past the end of line 1, out of range name, columns going backwards and
+        // out of range source in line 2 and a line past the end of file. Issues are sorted by
+        // position, so the one about columns going backwards comes first in line 2.
+        let mut source_mapping = SourceMapping::from_json(
+            r#"{
+                "version": 3,
+                "file": "other.js",
+                "sources": ["a.js", "./a.js", "b.js"],
+                "sourcesContent": ["a=1", null, "b=2"],
+                "names": [],
+                "mappings": "AAAA,MAAA;EAAAA,DAAA,CGAA;ADAA"
+            }"#,
+            "bundle.js.map".to_owned(),
+        )
+        .unwrap();
+        source_mapping.file = "bundle.js".to_owned();
+
+        let issues = validate_source_mapping(contents.as_bytes(), &source_mapping);
+
+        // Columns are presented as 1-based, like positions the issues are reported at.
+        assert_eq!(
+            issues[0].to_string(),
+            "Mapping points to column 7, but the line is only 4 columns long"
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            "Mapping precedes the previous mapping at column 3 in the same line"
+        );
+
+        let issues = issues
+            .into_iter()
+            .map(|issue| (issue.severity(), issue.kind))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            issues,
+            [
+                (
+                    Severity::Error,
+                    IssueKind::ColumnOutOfRange {
+                        line: 0,
+                        column: 6,
+                        line_len: 4
+                    }
+                ),
+                (
+                    Severity::Error,
+                    IssueKind::ColumnsNotOrdered {
+                        line: 1,
+                        column: 1,
+                        previous_column: 2
+                    }
+                ),
+                (
+                    Severity::Error,
+                    IssueKind::NameOutOfRange {
+                        line: 1,
+                        column: 2,
+                        name: 0,
+                        names_len: 0
+                    }
+                ),
+                (
+                    Severity::Error,
+                    IssueKind::SourceOutOfRange {
+                        line: 1,
+                        column: 2,
+                        source: 3,
+                        sources_len: 3
+                    }
+                ),
+                (
+                    Severity::Error,
+                    IssueKind::LineOutOfRange {
+                        line: 2,
+                        column: 0,
+                        lines_len: 2
+                    }
+                ),
+                (
+                    Severity::Warning,
+                    IssueKind::DuplicateSource {
+                        source: 1,
+                        first_source: 0
+                    }
+                ),
+                (Severity::Warning, IssueKind::UnusedSource { source: 1 }),
+                (Severity::Warning, IssueKind::MissingSourceContent { source: 1 }),
+                (
+                    Severity::Warning,
+                    IssueKind::FileMismatch {
+                        declared_file: "other.js".to_owned()
+                    }
+                ),
+            ]
+        );
+    }
+}
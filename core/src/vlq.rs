@@ -1,81 +1,231 @@
-use anyhow::{anyhow, Result};
+use std::fmt;
 
-const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
 
-// This function works only for sourcemap VLQ values.
-pub fn vlq_decode(base64_str: &str) -> Result<[i32; 4]> {
-    if base64_str.is_empty() {
-        return Ok([0; 4]);
+const INVALID_DIGIT: u8 = u8::MAX;
+
+// Maps ASCII characters to their base64 values, so that decoding a digit is a single lookup.
+const DECODE_TABLE: [u8; 128] = {
+    let mut table = [INVALID_DIGIT; 128];
+    let mut index = 0;
+
+    while index < ALPHABET.len() {
+        table[ALPHABET[index] as usize] = index as u8;
+        index += 1;
     }
 
-    let base64_decoded = {
-        let mut result: Vec<u8> = vec![];
-        for byte in base64_str.chars() {
-            result.push(ALPHABET.find(byte).unwrap() as u8);
-        }
+    table
+};
+
+const CONTINUATION_BIT: u8 = 0b100000;
+const DIGIT_MASK: u8 = 0b11111;
 
-        result
-    };
+// Maximum amount of values in a single segment: generated column, source index, source line,
+// source column and name index.
+pub const MAX_SEGMENT_VALUES: usize = 5;
 
-    let mut vlqs: Vec<Vec<i8>> = vec![];
-    let mut current_vlq: usize = 0;
-    let mut vlq_sequence_ended = true;
+// Single decoded segment of "mappings". Values are deltas, exactly as they were encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub line: u32,
+    pub values: [i32; MAX_SEGMENT_VALUES],
+    pub len: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VlqErrorKind {
+    InvalidCharacter(char),
+    UnterminatedValue,
+    InvalidValueCount(usize),
+    Overflow,
+    // Accumulated value of a field turned negative, which cannot be a valid index or position.
+    NegativeValue(i64),
+}
 
-    for raw_value in base64_decoded.iter() {
-        if vlq_sequence_ended {
-            vlqs.push(vec![]);
-            vlq_sequence_ended = false;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VlqError {
+    pub line: u32,
+    pub segment: u32,
+    // Offset in bytes from the start of "mappings".
+    pub offset: usize,
+    pub kind: VlqErrorKind,
+}
+
+impl fmt::Display for VlqError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid mappings at line {}, segment {} (offset {}): ",
+            self.line + 1,
+            self.segment,
+            self.offset
+        )?;
+
+        match &self.kind {
+            VlqErrorKind::InvalidCharacter(c) => write!(f, "character {c:?} is not a base64 digit"),
+            VlqErrorKind::UnterminatedValue => write!(f, "last VLQ sequence never ended"),
+            VlqErrorKind::InvalidValueCount(count) => {
+                write!(f, "either 1, 4 or 5 VLQ values should be present, {count} values found")
+            }
+            VlqErrorKind::Overflow => write!(f, "VLQ value does not fit into 32 bits"),
+            VlqErrorKind::NegativeValue(value) => write!(f, "segment results in negative value {value}"),
         }
+    }
+}
+
+impl std::error::Error for VlqError {}
 
-        vlqs[current_vlq].push(*raw_value as i8);
+// Decodes whole "mappings" string in a single pass, yielding segments one by one without
+// allocating. Empty segments and lines are skipped.
+pub struct MappingsDecoder<'a> {
+    mappings: &'a str,
+    position: usize,
+    line: u32,
+    segment: u32,
+    // Start of the last decoded segment.
+    segment_start: usize,
+}
 
-        if (raw_value & 0b100000) == 0 {
-            // MSB decides whether this octet is the last octet of this number.
-            current_vlq += 1;
-            vlq_sequence_ended = true;
+impl<'a> MappingsDecoder<'a> {
+    pub fn new(mappings: &'a str) -> Self {
+        Self {
+            mappings,
+            position: 0,
+            line: 0,
+            segment: 0,
+            segment_start: 0,
         }
     }
 
-    if !vlq_sequence_ended {
-        return Err(anyhow!("Last VLQ sequence never ended."));
+    fn error(&self, kind: VlqErrorKind) -> VlqError {
+        VlqError {
+            line: self.line,
+            segment: self.segment,
+            offset: self.position,
+            kind,
+        }
     }
 
-    if vlqs.len() != 4 && vlqs.len() != 5 {
-        return Err(anyhow!(
-            "Either 4 or 5 VLQ values should be present, {} values found. Base64 value: {base64_str}",
-            vlqs.len()
-        ));
+    // Error for the last decoded segment, for values that turn out to be invalid only once
+    // they are accumulated.
+    pub fn segment_error(&self, kind: VlqErrorKind) -> VlqError {
+        VlqError {
+            offset: self.segment_start,
+            ..self.error(kind)
+        }
     }
 
-    let mut result = [0i32; 4];
-
-    for (index, vlq) in vlqs.iter().take(4).enumerate() {
-        let mut value = 0i32;
-        let mut negative = false;
-
-        for (index, vlq_val) in vlq.iter().enumerate().rev() {
-            let mut vlq_value = *vlq_val as i32;
-            if index == 0 {
-                // First value in VLQ sequence decides whether end number is positive or negative.
-                negative = (vlq_value & 1) == 1; // Number is negative if LSB is 1.
-                vlq_value >>= 1;
-                value <<= 4;
-                value |= vlq_value & 0b1111;
-            } else {
-                value <<= 5;
-                value |= vlq_value & 0b11111;
+    fn decode_segment(&mut self) -> Result<Segment, VlqError> {
+        let bytes = self.mappings.as_bytes();
+        let mut segment = Segment {
+            line: self.line,
+            values: [0; MAX_SEGMENT_VALUES],
+            len: 0,
+        };
+
+        while let Some(&byte) = bytes.get(self.position) {
+            if byte == b',' || byte == b';' {
+                break;
+            }
+
+            if segment.len as usize == MAX_SEGMENT_VALUES {
+                return Err(self.error(VlqErrorKind::InvalidValueCount(segment.len as usize + 1)));
             }
+
+            let value = self.decode_value()?;
+            segment.values[segment.len as usize] = value;
+            segment.len += 1;
+        }
+
+        if !matches!(segment.len, 1 | 4 | 5) {
+            return Err(self.error(VlqErrorKind::InvalidValueCount(segment.len as usize)));
         }
 
-        result[index] = if negative { -value } else { value };
+        Ok(segment)
     }
 
-    Ok(result)
+    fn decode_value(&mut self) -> Result<i32, VlqError> {
+        let bytes = self.mappings.as_bytes();
+        let mut value = 0u64;
+        let mut shift = 0u32;
+
+        loop {
+            let Some(&byte) = bytes.get(self.position).filter(|&&byte| byte != b',' && byte != b';') else {
+                return Err(self.error(VlqErrorKind::UnterminatedValue));
+            };
+
+            let digit = match DECODE_TABLE.get(byte as usize) {
+                Some(&digit) if digit != INVALID_DIGIT => digit,
+                _ => {
+                    let c = self
+                        .mappings
+                        .get(self.position..)
+                        .and_then(|rest| rest.chars().next())
+                        .unwrap_or(char::REPLACEMENT_CHARACTER);
+                    return Err(self.error(VlqErrorKind::InvalidCharacter(c)));
+                }
+            };
+
+            if shift > 32 {
+                return Err(self.error(VlqErrorKind::Overflow));
+            }
+
+            self.position += 1;
+            value |= ((digit & DIGIT_MASK) as u64) << shift;
+            shift += 5;
+
+            // MSB decides whether this digit is the last digit of this number.
+            if digit & CONTINUATION_BIT == 0 {
+                break;
+            }
+        }
+
+        // LSB of the first digit decides whether the number is negative.
+        let negative = value & 1 == 1;
+        let value = i32::try_from(value >> 1).map_err(|_| self.error(VlqErrorKind::Overflow))?;
+
+        Ok(if negative { -value } else { value })
+    }
+}
+
+impl Iterator for MappingsDecoder<'_> {
+    type Item = Result<Segment, VlqError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.mappings.as_bytes();
+
+        loop {
+            match bytes.get(self.position)? {
+                b';' => {
+                    self.line += 1;
+                    self.segment = 0;
+                }
+                b',' => self.segment += 1,
+                _ => break,
+            }
+
+            self.position += 1;
+        }
+
+        self.segment_start = self.position;
+        let result = self.decode_segment();
+
+        if result.is_err() {
+            // Decoding cannot continue after an error.
+            self.position = bytes.len();
+        }
+
+        Some(result)
+    }
 }
 
 #[cfg(any(test, rust_analyzer))]
 mod tests {
-    use crate::vlq::vlq_decode;
+    use crate::vlq::{MappingsDecoder, Segment, VlqError, VlqErrorKind};
+
+    fn decode(mappings: &str) -> Result<Vec<Segment>, VlqError> {
+        MappingsDecoder::new(mappings).collect()
+    }
 
     #[test]
     fn example() {
@@ -119,7 +269,53 @@ mod tests {
         ];
 
         for (index, value) in values.iter().enumerate() {
-            assert_eq!(vlq_decode(value).unwrap(), expected[index]);
+            let segments = decode(value).unwrap();
+            assert_eq!(segments.len(), 1);
+            assert_eq!(segments[0].len, 4);
+            assert_eq!(segments[0].values[..4], expected[index]);
+        }
+    }
+
+    #[test]
+    fn decodes_whole_mappings() {
+        let segments = decode("AAAAA,C;;EAAAD,HAAA").unwrap();
+
+        let decoded = segments
+            .iter()
+            .map(|segment| (segment.line, &segment.values[..segment.len as usize]))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            decoded,
+            [
+                (0, &[0, 0, 0, 0, 0][..]),
+                (0, &[1][..]),
+                (2, &[2, 0, 0, 0, -1][..]),
+                (2, &[-3, 0, 0, 0][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let test_mappings = [
+            ("AAAA;AA!A", 1, 0, 7, VlqErrorKind::InvalidCharacter('!')),
+            ("AAAA,AAg", 0, 1, 8, VlqErrorKind::UnterminatedValue),
+            ("AAAA;AAAA,AA;", 1, 1, 12, VlqErrorKind::InvalidValueCount(2)),
+            ("AAAAAA", 0, 0, 5, VlqErrorKind::InvalidValueCount(6)),
+            ("gggggggE", 0, 0, 7, VlqErrorKind::Overflow),
+        ];
+
+        for (mappings, line, segment, offset, kind) in test_mappings {
+            assert_eq!(
+                decode(mappings),
+                Err(VlqError {
+                    line,
+                    segment,
+                    offset,
+                    kind
+                })
+            );
         }
     }
 }
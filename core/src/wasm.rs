@@ -0,0 +1,287 @@
+use std::fmt;
+
+const MAGIC: &[u8; 4] = b"\0asm";
+const VERSION: &[u8; 4] = &[1, 0, 0, 0];
+pub const HEADER_LEN: usize = MAGIC.len() + VERSION.len();
+
+pub const CUSTOM_SECTION_ID: u8 = 0;
+pub const CODE_SECTION_ID: u8 = 10;
+pub const DATA_SECTION_ID: u8 = 11;
+
+pub const SOURCE_MAPPING_URL_SECTION: &str = "sourceMappingURL";
+pub const NAME_SECTION: &str = "name";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WasmErrorKind {
+    InvalidHeader,
+    UnexpectedEnd,
+    InvalidLeb128,
+    InvalidName,
+    MissingCodeSection,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasmError {
+    // Offset in bytes from the start of the module.
+    pub offset: usize,
+    pub kind: WasmErrorKind,
+}
+
+impl fmt::Display for WasmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid WebAssembly module at offset {}: ", self.offset)?;
+
+        match &self.kind {
+            WasmErrorKind::InvalidHeader => write!(f, "missing magic number or unsupported version"),
+            WasmErrorKind::UnexpectedEnd => write!(f, "module ends in the middle of a section"),
+            WasmErrorKind::InvalidLeb128 => write!(f, "LEB128 value does not fit into 32 bits"),
+            WasmErrorKind::InvalidName => write!(f, "custom section name is not valid UTF-8"),
+            WasmErrorKind::MissingCodeSection => write!(f, "module contains no code section"),
+        }
+    }
+}
+
+impl std::error::Error for WasmError {}
+
+// Single section of a module, all offsets are counted from the start of the module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmSection<'a> {
+    pub id: u8,
+    // Present only for custom sections.
+    pub name: Option<&'a str>,
+    // Start of the section, including section id and size.
+    pub start: usize,
+    // Start of the section contents, for custom sections after the name.
+    pub payload_start: usize,
+    pub end: usize,
+}
+
+impl WasmSection<'_> {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+pub fn is_wasm_module(contents: &[u8]) -> bool {
+    contents.starts_with(MAGIC)
+}
+
+// Splits module into sections without validating their contents.
+pub fn parse_sections(contents: &[u8]) -> Result<Vec<WasmSection<'_>>, WasmError> {
+    if contents.get(..HEADER_LEN) != Some(&[MAGIC.as_slice(), VERSION.as_slice()].concat()[..]) {
+        return Err(WasmError {
+            offset: 0,
+            kind: WasmErrorKind::InvalidHeader,
+        });
+    }
+
+    let mut sections = vec![];
+    let mut reader = Reader {
+        contents,
+        position: HEADER_LEN,
+    };
+
+    while reader.position < contents.len() {
+        let start = reader.position;
+        let id = reader.read_byte()?;
+        let size = reader.read_u32()? as usize;
+        let payload_start = reader.position;
+
+        let end = payload_start
+            .checked_add(size)
+            .filter(|end| *end <= contents.len())
+            .ok_or_else(|| reader.error(WasmErrorKind::UnexpectedEnd))?;
+
+        let mut section = WasmSection {
+            id,
+            name: None,
+            start,
+            payload_start,
+            end,
+        };
+
+        if id == CUSTOM_SECTION_ID {
+            let mut payload_reader = Reader {
+                contents: &contents[..end],
+                position: payload_start,
+            };
+            section.name = Some(payload_reader.read_name()?);
+            section.payload_start = payload_reader.position;
+        }
+
+        sections.push(section);
+        reader.position = end;
+    }
+
+    Ok(sections)
+}
+
+// URL stored in "sourceMappingURL" custom section, along with the section itself.
+pub fn find_source_mapping_url<'a>(
+    contents: &'a [u8],
+    sections: &[WasmSection<'a>],
+) -> Result<Option<(&'a str, WasmSection<'a>)>, WasmError> {
+    let Some(section) = sections
+        .iter()
+        .find(|section| section.name == Some(SOURCE_MAPPING_URL_SECTION))
+    else {
+        return Ok(None);
+    };
+
+    let mut reader = Reader {
+        contents: &contents[..section.end],
+        position: section.payload_start,
+    };
+
+    Ok(Some((reader.read_name()?, *section)))
+}
+
+struct Reader<'a> {
+    contents: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, kind: WasmErrorKind) -> WasmError {
+        WasmError {
+            offset: self.position,
+            kind,
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<u8, WasmError> {
+        let byte = *self
+            .contents
+            .get(self.position)
+            .ok_or_else(|| self.error(WasmErrorKind::UnexpectedEnd))?;
+        self.position += 1;
+
+        Ok(byte)
+    }
+
+    // Unsigned LEB128, at most 5 bytes long.
+    fn read_u32(&mut self) -> Result<u32, WasmError> {
+        let mut value = 0u64;
+
+        for shift in (0..35).step_by(7) {
+            let byte = self.read_byte()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return u32::try_from(value).map_err(|_| self.error(WasmErrorKind::InvalidLeb128));
+            }
+        }
+
+        Err(self.error(WasmErrorKind::InvalidLeb128))
+    }
+
+    fn read_name(&mut self) -> Result<&'a str, WasmError> {
+        let len = self.read_u32()? as usize;
+        let bytes = self
+            .position
+            .checked_add(len)
+            .and_then(|end| self.contents.get(self.position..end))
+            .ok_or_else(|| self.error(WasmErrorKind::UnexpectedEnd))?;
+        let name = std::str::from_utf8(bytes).map_err(|_| self.error(WasmErrorKind::InvalidName))?;
+        self.position += len;
+
+        Ok(name)
+    }
+}
+
+#[cfg(any(test, rust_analyzer))]
+pub mod tests {
+    use crate::wasm::{
+        find_source_mapping_url, parse_sections, WasmError, WasmErrorKind, WasmSection, CODE_SECTION_ID,
+        CUSTOM_SECTION_ID, DATA_SECTION_ID,
+    };
+
+    pub fn section(id: u8, payload: &[u8]) -> Vec<u8> {
+        assert!(payload.len() < 0x80);
+        [&[id, payload.len() as u8][..], payload].concat()
+    }
+
+    pub fn custom_section(name: &str, payload: &[u8]) -> Vec<u8> {
+        section(
+            CUSTOM_SECTION_ID,
+            &[&[name.len() as u8][..], name.as_bytes(), payload].concat(),
+        )
+    }
+
+    // Module with a single function, a data segment, names and a source map reference.
+    pub fn example_module(source_mapping_url: &str) -> Vec<u8> {
+        [
+            b"\0asm\x01\0\0\0".to_vec(),
+            // Type section: a single function type without parameters and results.
+            section(1, &[1, 0x60, 0, 0]),
+            section(3, &[1, 0]),
+            // Code section: a single function with no locals, "nop nop nop end".
+            section(CODE_SECTION_ID, &[1, 5, 0, 0x01, 0x01, 0x01, 0x0B]),
+            section(DATA_SECTION_ID, &[1, 0, 0x41, 0, 0x0B, 3, b'a', b'b', b'c']),
+            custom_section("name", &[1, 2, 1, 0]),
+            custom_section(
+                "sourceMappingURL",
+                &[&[source_mapping_url.len() as u8][..], source_mapping_url.as_bytes()].concat(),
+            ),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn splits_module_into_sections() {
+        let contents = example_module("module.wasm.map");
+        let sections = parse_sections(&contents).unwrap();
+
+        let ids = sections
+            .iter()
+            .map(|section| (section.id, section.name, section.len()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            ids,
+            [
+                (1, None, 6),
+                (3, None, 4),
+                (CODE_SECTION_ID, None, 9),
+                (DATA_SECTION_ID, None, 11),
+                (CUSTOM_SECTION_ID, Some("name"), 11),
+                (CUSTOM_SECTION_ID, Some("sourceMappingURL"), 35),
+            ]
+        );
+
+        let (url, section) = find_source_mapping_url(&contents, &sections).unwrap().unwrap();
+        assert_eq!(url, "module.wasm.map");
+        assert_eq!(section, sections[5]);
+    }
+
+    #[test]
+    fn reports_malformed_modules() {
+        let test_contents: [(&[u8], WasmError); 3] = [
+            (
+                b"\0asm\x02\0\0\0",
+                WasmError {
+                    offset: 0,
+                    kind: WasmErrorKind::InvalidHeader,
+                },
+            ),
+            (
+                b"\0asm\x01\0\0\0\x01\x05\0",
+                WasmError {
+                    offset: 10,
+                    kind: WasmErrorKind::UnexpectedEnd,
+                },
+            ),
+            (
+                b"\0asm\x01\0\0\0\x01\xFF\xFF\xFF\xFF\x7F",
+                WasmError {
+                    offset: 14,
+                    kind: WasmErrorKind::InvalidLeb128,
+                },
+            ),
+        ];
+
+        for (contents, expected) in test_contents {
+            assert_eq!(parse_sections(contents), Err::<Vec<WasmSection>, _>(expected));
+        }
+    }
+}
@@ -0,0 +1,31 @@
+#![no_main]
+
+use core::{
+    analyzer::{calculate_size_by_file, AnalyzeOptions},
+    parser::SourceMapping,
+};
+use libfuzzer_sys::fuzz_target;
+
+// Input is split on the first NUL byte into a generated file and its source map. Everything runs in
+// memory, locating source maps through annotations is covered by the parse_bundle target.
+fuzz_target!(|data: &[u8]| {
+    let (bundle, map) = match data.iter().position(|&byte| byte == 0) {
+        Some(position) => (&data[..position], &data[position + 1..]),
+        None => (data, &[][..]),
+    };
+
+    let json = String::from_utf8_lossy(map);
+
+    for strict in [false, true] {
+        // Errors are expected, only panics are reported.
+        let Ok(mapping) = SourceMapping::from_json(&json, "bundle.js.map".to_owned()) else {
+            return;
+        };
+
+        let options = AnalyzeOptions {
+            strict,
+            ..AnalyzeOptions::default()
+        };
+        let _ = calculate_size_by_file(bundle, mapping, &options);
+    }
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use core::{
+    analyzer::{calculate_size_by_file, AnalyzeOptions},
+    parser::{parse_file_contents, ParseContext},
+};
+use libfuzzer_sys::fuzz_target;
+
+// Input is a generated file, its source map is located through sourceMappingURL annotation or
+// WebAssembly section. Only inline source maps are followed, so nothing is read from disk.
+fuzz_target!(|data: &[u8]| {
+    let context = ParseContext::inline_only();
+
+    // Errors are expected, only panics are reported.
+    let Ok((contents, mapping)) = parse_file_contents("bundle.js", data.to_vec(), &context) else {
+        return;
+    };
+
+    let _ = calculate_size_by_file(&contents, mapping, &AnalyzeOptions::default());
+});
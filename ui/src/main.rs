@@ -1,20 +1,34 @@
-mod terminal;
 mod theme;
 mod tui;
 mod utils;
 
-use anyhow::{Error, Result};
-use clap::{arg, builder::ArgPredicate, Arg, Command};
-use core::analyze_path;
+use anyhow::{anyhow, Result};
+use clap::{arg, builder::ArgPredicate, value_parser, Arg, ArgAction, ArgMatches, Command};
+use core::{
+    analyze_path,
+    analyzer::{AnalyzeOptions, AttributionStrategy},
+    combined::CombinedAnalysis,
+    compression::SizeMetric,
+    diff::{diff_snapshots, load_snapshot, snapshot_path, Threshold},
+    discovery::DiscoveryOptions,
+    duplicates::{DuplicateIndex, SourceIndex},
+    identity::{HashPattern, IdentityOptions, DEFAULT_HASH_PATTERNS, DEFAULT_MIN_SIMILARITY},
+    packages::PackageReport,
+    validator::{validate_path, Severity},
+    Error,
+};
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
-use std::io;
+use std::{fs, io, str::FromStr};
 use tui::{run_tui_app, App};
-use ui::terminal::{get_default_styles, print_file_info};
+use ui::terminal::{
+    get_default_styles, print_combined_info, print_diff_report, print_duplicate_report, print_error_summary,
+    print_file_info, print_package_report, print_shared_sources, print_validation_error, print_validation_report,
+};
 
 fn main() -> Result<()> {
     let matches = Command::new("svis-tool")
@@ -25,16 +39,214 @@ fn main() -> Result<()> {
         ))
         .arg(arg!(-s --simple "run without tui").requires("path"))
         .arg(Arg::new("path").short('p').help("path to scan files for"))
+        .args(discovery_args())
+        .args(analyze_args())
+        .arg(arg!(-z --compressed "estimate gzip and brotli size of each source, takes much longer"))
+        .arg(
+            Arg::new("metric")
+                .short('m')
+                .long("metric")
+                .value_name("METRIC")
+                .value_parser(SizeMetric::ALL.map(|metric| metric.name()))
+                .default_value(SizeMetric::default().name())
+                .help("size used for sorting and percentages, compressed metrics imply --compressed"),
+        )
+        .subcommand(
+            Command::new("validate")
+                .about("check source maps for problems, exits with code 1 when errors are found")
+                .arg(
+                    Arg::new("path")
+                        .short('p')
+                        .required(true)
+                        .help("path to scan files for"),
+                )
+                .args(discovery_args())
+                .arg(arg!(--"deny-warnings" "exit with code 1 when warnings are found as well")),
+        )
+        .subcommand(
+            Command::new("snapshot")
+                .about("save sizes of all bundles and their sources, to be compared with a later build")
+                .arg(
+                    Arg::new("path")
+                        .short('p')
+                        .required(true)
+                        .help("path to scan files for"),
+                )
+                .args(discovery_args())
+                .args(analyze_args())
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("file to save the snapshot to, printed to stdout when missing"),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("compare bundle and source sizes of two builds")
+                .arg(
+                    Arg::new("old")
+                        .required(true)
+                        .help("path to scan files for or a saved .json snapshot of the old build"),
+                )
+                .arg(
+                    Arg::new("new")
+                        .required(true)
+                        .help("path to scan files for or a saved .json snapshot of the new build"),
+                )
+                .args(discovery_args())
+                .args(analyze_args())
+                .arg(
+                    Arg::new("threshold")
+                        .short('t')
+                        .long("threshold")
+                        .value_name("BYTES|PERCENT")
+                        .value_parser(Threshold::from_str)
+                        .help("only show changes bigger than given number of bytes or percentage, e.g. 500 or 2%"),
+                )
+                .arg(
+                    Arg::new("hash-pattern")
+                        .long("hash-pattern")
+                        .value_name("PATTERN")
+                        .action(ArgAction::Append)
+                        .value_parser(HashPattern::from_str)
+                        .help(format!(
+                            "bundle file name with content hash, e.g. [name].[hash:10].[ext], replaces default patterns {}",
+                            DEFAULT_HASH_PATTERNS.join(" ")
+                        )),
+                )
+                .arg(
+                    Arg::new("min-similarity")
+                        .long("min-similarity")
+                        .value_name("SHARE")
+                        .value_parser(parse_similarity)
+                        .help(format!(
+                            "share of sources, from 0 to 1, bundles with different names need in common to be compared, {} by default",
+                            DEFAULT_MIN_SIMILARITY
+                        )),
+                )
+                .arg(arg!(--json "print the diff as JSON")),
+        )
         .get_matches();
 
+    if let Some(matches) = matches.subcommand_matches("snapshot") {
+        return run_snapshot(
+            matches.get_one::<String>("path").unwrap(),
+            &discovery_options(matches),
+            &analyze_options(matches),
+            matches.get_one::<String>("output"),
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches("diff") {
+        return run_diff(
+            matches.get_one::<String>("old").unwrap(),
+            matches.get_one::<String>("new").unwrap(),
+            &discovery_options(matches),
+            &analyze_options(matches),
+            &identity_options(matches),
+            matches.get_one::<Threshold>("threshold").copied(),
+            matches.get_flag("json"),
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches("validate") {
+        return run_validate(
+            matches.get_one::<String>("path").unwrap(),
+            &discovery_options(matches),
+            matches.get_flag("deny-warnings"),
+        );
+    }
+
     let path = matches.get_one::<String>("path");
+    let options = discovery_options(&matches);
+    let metric = matches
+        .get_one::<String>("metric")
+        .and_then(|name| SizeMetric::from_name(name))
+        .unwrap_or_default();
+    let analyze_options = AnalyzeOptions {
+        compression: matches.get_flag("compressed") || metric.is_compressed(),
+        ..analyze_options(&matches)
+    };
     match matches.get_one::<bool>("tui") {
-        Some(_) => run_tui(path.map(|x| x.as_str())),
-        None => run_simple(path.unwrap()),
+        Some(_) => run_tui(path.map(|x| x.as_str()), options, analyze_options, metric),
+        None => run_simple(path.unwrap(), &options, &analyze_options, metric),
+    }
+}
+
+fn discovery_args() -> [Arg; 5] {
+    [
+        Arg::new("include")
+            .short('i')
+            .long("include")
+            .value_name("GLOB")
+            .action(ArgAction::Append)
+            .help("only check bundles matching glob, relative to scanned path"),
+        Arg::new("exclude")
+            .short('e')
+            .long("exclude")
+            .value_name("GLOB")
+            .action(ArgAction::Append)
+            .help("skip files and directories matching glob, relative to scanned path"),
+        Arg::new("max-depth")
+            .short('d')
+            .long("max-depth")
+            .value_name("DEPTH")
+            .value_parser(value_parser!(usize))
+            .help("maximum depth of directories to scan, 1 checks only files directly in path"),
+        arg!(--"no-gitignore" "do not skip files ignored by .gitignore files within scanned path"),
+        arg!(--"allow-external-maps" "follow sourceMappingURL annotations pointing outside of scanned path"),
+    ]
+}
+
+fn analyze_args() -> [Arg; 2] {
+    [
+        Arg::new("attribution")
+            .short('a')
+            .long("attribution")
+            .value_name("STRATEGY")
+            .value_parser(AttributionStrategy::ALL.map(|strategy| strategy.name()))
+            .default_value(AttributionStrategy::default().name())
+            .help("how bytes not covered by mappings directly are attributed to sources"),
+        arg!(--strict "fail on the first invalid mapping instead of skipping it with a warning"),
+    ]
+}
+
+fn discovery_options(matches: &ArgMatches) -> DiscoveryOptions {
+    let globs = |id: &str| {
+        matches
+            .get_many::<String>(id)
+            .map(|values| values.cloned().collect())
+            .unwrap_or_default()
+    };
+
+    DiscoveryOptions {
+        include: globs("include"),
+        exclude: globs("exclude"),
+        max_depth: matches.get_one::<usize>("max-depth").copied(),
+        respect_gitignore: !matches.get_flag("no-gitignore"),
+        allow_external_maps: matches.get_flag("allow-external-maps"),
     }
 }
 
-fn run_tui(path: Option<&str>) -> Result<()> {
+fn analyze_options(matches: &ArgMatches) -> AnalyzeOptions {
+    AnalyzeOptions {
+        strict: matches.get_flag("strict"),
+        attribution: matches
+            .get_one::<String>("attribution")
+            .and_then(|name| AttributionStrategy::from_name(name))
+            .unwrap_or_default(),
+        ..AnalyzeOptions::default()
+    }
+}
+
+fn run_tui(
+    path: Option<&str>,
+    options: DiscoveryOptions,
+    analyze_options: AnalyzeOptions,
+    metric: SizeMetric,
+) -> Result<()> {
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -52,7 +264,7 @@ fn run_tui(path: Option<&str>) -> Result<()> {
 
     // create app and run it
     let app = App::default();
-    let res = run_tui_app(&mut terminal, app, path);
+    let res = run_tui_app(&mut terminal, app, path, options, analyze_options, metric);
 
     // restore terminal
     disable_raw_mode()?;
@@ -66,29 +278,152 @@ fn run_tui(path: Option<&str>) -> Result<()> {
     Ok(())
 }
 
-fn run_simple(path: &str) -> Result<()> {
+fn run_simple(
+    path: &str,
+    options: &DiscoveryOptions,
+    analyze_options: &AnalyzeOptions,
+    metric: SizeMetric,
+) -> Result<()> {
     let styles = get_default_styles();
     let mut files_checked = 0u32;
     let mut files_with_errors: Vec<(String, Error)> = vec![];
+    let mut package_report = PackageReport::default();
+    let mut duplicate_index = DuplicateIndex::default();
+    let mut source_index = SourceIndex::default();
+    let mut combined_analysis = CombinedAnalysis::default();
 
-    analyze_path(path, |file, result| {
+    analyze_path(path, options, analyze_options, |file, result| {
         files_checked += 1;
         match result {
-            Ok(info) => print_file_info(&info),
+            Ok(info) => {
+                print_file_info(&info, metric);
+                package_report.add(&info);
+                duplicate_index.add(&info);
+                source_index.add(&info);
+                combined_analysis.add(&info);
+            }
             Err(err) => files_with_errors.push((file.to_owned(), err)),
         }
     })?;
 
-    for (file, err) in files_with_errors {
+    print_package_report(&package_report);
+    print_duplicate_report(&duplicate_index.report());
+    print_shared_sources(&source_index.shared_sources());
+    print_combined_info(&combined_analysis.info());
+
+    files_with_errors.sort_by_key(|(_, err)| err.kind());
+
+    for (file, err) in &files_with_errors {
         println!(
-            "{} Error when parsing file {}, make sure the sourcemap is correct:\n- {}",
+            "{} Error when parsing file {} ({}), make sure the sourcemap is correct:\n- {}",
             styles.error.apply_to("!"),
             styles.file.apply_to(file),
+            styles.error.apply_to(err.kind().label()),
             err,
         );
     }
 
+    print_error_summary(&files_with_errors);
+
     println!("Files checked: {}", styles.highlight.apply_to(files_checked));
 
     Ok(())
 }
+
+fn identity_options(matches: &ArgMatches) -> IdentityOptions {
+    let defaults = IdentityOptions::default();
+
+    IdentityOptions {
+        hash_patterns: matches
+            .get_many::<HashPattern>("hash-pattern")
+            .map(|patterns| patterns.cloned().collect())
+            .unwrap_or(defaults.hash_patterns),
+        min_similarity: matches
+            .get_one::<f64>("min-similarity")
+            .copied()
+            .unwrap_or(defaults.min_similarity),
+    }
+}
+
+fn parse_similarity(value: &str) -> Result<f64> {
+    match value.trim().parse::<f64>() {
+        Ok(similarity) if (0.0..=1.0).contains(&similarity) => Ok(similarity),
+        _ => Err(anyhow!("Expected share from 0 to 1, e.g. 0.5, got {value:?}")),
+    }
+}
+
+fn run_snapshot(
+    path: &str,
+    options: &DiscoveryOptions,
+    analyze_options: &AnalyzeOptions,
+    output: Option<&String>,
+) -> Result<()> {
+    let json = snapshot_path(path, options, analyze_options)?.to_json();
+
+    match output {
+        Some(output) => fs::write(output, json)?,
+        None => println!("{json}"),
+    }
+
+    Ok(())
+}
+
+fn run_diff(
+    old: &str,
+    new: &str,
+    options: &DiscoveryOptions,
+    analyze_options: &AnalyzeOptions,
+    identity_options: &IdentityOptions,
+    threshold: Option<Threshold>,
+    json: bool,
+) -> Result<()> {
+    let old = load_snapshot(old, options, analyze_options)?;
+    let new = load_snapshot(new, options, analyze_options)?;
+
+    let mut report = diff_snapshots(&old, &new, identity_options);
+    if let Some(threshold) = threshold {
+        report = report.with_threshold(threshold);
+    }
+
+    match json {
+        true => println!("{}", report.to_json()),
+        false => print_diff_report(&report, threshold),
+    }
+
+    Ok(())
+}
+
+fn run_validate(path: &str, options: &DiscoveryOptions, deny_warnings: bool) -> Result<()> {
+    let styles = get_default_styles();
+    let mut files_checked = 0u32;
+    let mut errors = 0usize;
+    let mut warnings = 0usize;
+
+    validate_path(path, options, |file, result| {
+        files_checked += 1;
+        match result {
+            Ok(report) => {
+                print_validation_report(&report);
+                errors += report.count(Severity::Error);
+                warnings += report.count(Severity::Warning);
+            }
+            Err(err) => {
+                print_validation_error(file, &err);
+                errors += 1;
+            }
+        }
+    })?;
+
+    println!(
+        "Files checked: {}, errors: {}, warnings: {}",
+        styles.highlight.apply_to(files_checked),
+        styles.error.apply_to(errors),
+        styles.warning.apply_to(warnings),
+    );
+
+    if errors > 0 || (deny_warnings && warnings > 0) {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
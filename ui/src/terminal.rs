@@ -1,14 +1,26 @@
+use std::collections::BTreeMap;
+
 use console::Style;
 
-use core::analyzer::{SourceMappingFileInfo, SourceMappingInfo};
+use core::{
+    analyzer::{GapKind, SourceMappingFileInfo, SourceMappingInfo},
+    combined::CombinedInfo,
+    compression::{Accuracy, SizeMetric, METHOD_NOTE},
+    diff::{ChangeKind, DiffReport, SizeChange, Threshold},
+    duplicates::{DuplicateReport, SharedSource, SourceCopy},
+    packages::PackageReport,
+    validator::{Severity, ValidationReport},
+    Error, ErrorKind,
+};
 
-use crate::utils::{format_bytes, format_percentage, without_relative_part};
+use crate::utils::{format_bytes, format_percentage};
 
 pub struct Styles {
     pub file: Style,
     pub highlight: Style,
     pub highlight2: Style,
     pub error: Style,
+    pub warning: Style,
 }
 
 pub fn get_default_styles() -> Styles {
@@ -17,12 +29,19 @@ pub fn get_default_styles() -> Styles {
         highlight: Style::new().cyan(),
         highlight2: Style::new().green(),
         error: Style::new().red(),
+        warning: Style::new().yellow(),
     }
 }
 
+// Identifiers are listed only for the biggest ones, otherwise output becomes unreadable.
+const MAX_NAMES_PER_FILE: usize = 10;
+const MAX_PRINTED_COVERAGE_GAPS: usize = 5;
+const MAX_WARNINGS: usize = 10;
+const MAX_BUNDLES_PER_COPY: usize = 3;
+
 // This function is very much used, not sure why rust-analyzer thinks otherwise.
 #[allow(dead_code)]
-pub fn print_file_info(info: &SourceMappingInfo) {
+pub fn print_file_info(info: &SourceMappingInfo, metric: SizeMetric) {
     let styles = get_default_styles();
 
     let mapping = &info.source_mapping;
@@ -39,46 +58,621 @@ pub fn print_file_info(info: &SourceMappingInfo) {
 
     let source_file_len = mapping.actual_source_file_len();
 
+    let compressed_sizes = SizeMetric::ALL
+        .into_iter()
+        .filter(|metric| metric.is_compressed())
+        .filter_map(|metric| {
+            let size = info.file_size(metric)?;
+            Some(format!(
+                ", {} {}",
+                metric.name(),
+                styles.highlight.apply_to(format_bytes(size))
+            ))
+        })
+        .collect::<String>();
+
     println!(
-        "File {}, total size {}.",
+        "File {}, total size {}{}.",
         styles.file.apply_to(&mapping.file),
-        styles.highlight.apply_to(format_bytes(source_file_len))
+        styles.highlight.apply_to(format_bytes(source_file_len)),
+        compressed_sizes
     );
+    print_warnings(info);
+
     println!(
         "Size contribution per file (all paths are relative to {}):",
         styles.file.apply_to(sources_root)
     );
 
+    if !info.info_by_name.is_empty() {
+        println!("Identifiers are credited only with code mapped to them directly, not with whole function bodies.");
+    }
+
     let mut info_by_file = info.info_by_file.iter().collect::<Vec<&SourceMappingFileInfo>>();
-    info_by_file.sort_by_key(|i| i.bytes);
+    info_by_file.sort_by_key(|i| (i.size(metric), i.bytes));
 
     for file_info in info_by_file.iter().rev() {
+        let accuracy = file_info
+            .compressed
+            .map(|estimate| format!(" [{}]", estimate.accuracy.label()))
+            .unwrap_or_default();
+
+        println!(
+            "- {}, {}{}",
+            styles.file.apply_to(info.get_file_name(file_info.file)),
+            format_sizes(info, |metric| file_info.size(metric), metric),
+            accuracy,
+        );
+
+        for name_info in file_info.info_by_name.iter().take(MAX_NAMES_PER_FILE) {
+            println!(
+                "  - {}, {}",
+                info.get_name(name_info.name).unwrap_or("(unknown name)"),
+                format_sizes(
+                    info,
+                    |metric| info.part_size(name_info.bytes, file_info.bytes, file_info.size(metric), metric),
+                    metric
+                ),
+            );
+        }
+
+        if file_info.info_by_name.len() > MAX_NAMES_PER_FILE {
+            println!(
+                "  - ... and {} more identifiers",
+                file_info.info_by_name.len() - MAX_NAMES_PER_FILE
+            );
+        }
+    }
+
+    let sum_bytes = info.sum_bytes as u64;
+
+    println!("Total: {}", format_sizes(info, |metric| info.sum_size(metric), metric));
+
+    if !info.unmapped.is_empty() {
+        let accuracy = info
+            .compression
+            .as_ref()
+            .map(|compression| format!(" [{}]", compression.unmapped.accuracy.label()))
+            .unwrap_or_default();
+
+        println!("Size not coming from any source{accuracy}:");
+    }
+
+    for unmapped_info in &info.unmapped {
+        println!(
+            "- {}, {}",
+            unmapped_info.category.label(),
+            format_sizes(
+                info,
+                |metric| info.part_size(
+                    unmapped_info.bytes,
+                    info.unmapped_bytes(),
+                    info.unmapped_size(metric),
+                    metric
+                ),
+                metric
+            ),
+        );
+    }
+
+    // Everything is classified for the supported bundle kinds, except for rounding of invalid files.
+    let rest = source_file_len.saturating_sub(sum_bytes + info.unmapped_bytes() as u64);
+    if rest > 0 {
+        println!(
+            "Remaining unclassified size: {} ({})",
+            styles.highlight.apply_to(format_bytes(rest)),
+            styles.highlight2.apply_to(format_percentage(rest, source_file_len))
+        );
+    }
+
+    print_compression_notes(info);
+    print_coverage(info);
+}
+
+// Sizes in all known metrics, compressed ones are estimates. Percentage of the whole file is
+// given only for the selected metric.
+fn format_sizes(info: &SourceMappingInfo, size: impl Fn(SizeMetric) -> Option<u64>, metric: SizeMetric) -> String {
+    let styles = get_default_styles();
+
+    SizeMetric::ALL
+        .into_iter()
+        .filter_map(|size_metric| {
+            let value = size(size_metric)?;
+            let (label, prefix) = match size_metric {
+                SizeMetric::Raw => ("size", ""),
+                _ => (size_metric.name(), "~"),
+            };
+
+            let mut text = format!(
+                "{label} {}",
+                styles.highlight.apply_to(format!("{prefix}{}", format_bytes(value)))
+            );
+
+            if let Some(total) = info.file_size(size_metric).filter(|_| size_metric == metric) {
+                text += &format!(" ({})", styles.highlight2.apply_to(format_percentage(value, total)));
+            }
+
+            Some(text)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn print_compression_notes(info: &SourceMappingInfo) {
+    let Some(compression) = &info.compression else {
+        return;
+    };
+
+    let mut accuracies = info
+        .info_by_file
+        .iter()
+        .filter_map(|file_info| file_info.compressed)
+        .map(|estimate| estimate.accuracy)
+        .chain([compression.unmapped.accuracy])
+        .collect::<Vec<Accuracy>>();
+    accuracies.sort();
+    accuracies.dedup();
+
+    println!("Compressed sizes are {METHOD_NOTE}:");
+
+    for accuracy in accuracies {
+        println!("- {}: {}", accuracy.label(), accuracy.note());
+    }
+}
+
+// Sizes of your own code and npm packages summed over all analyzed bundles.
+pub fn print_package_report(report: &PackageReport) {
+    let styles = get_default_styles();
+
+    let total_bytes = report.total_bytes();
+    if total_bytes == 0 {
+        return;
+    }
+
+    let packages = report.packages();
+
+    println!("Size by package across all bundles:");
+    println!(
+        "- your code, size {} ({})",
+        styles.highlight.apply_to(format_bytes(report.own_bytes)),
+        styles
+            .highlight2
+            .apply_to(format_percentage(report.own_bytes, total_bytes)),
+    );
+    println!(
+        "- third-party, size {} ({}), {} packages",
+        styles.highlight.apply_to(format_bytes(report.third_party_bytes)),
+        styles
+            .highlight2
+            .apply_to(format_percentage(report.third_party_bytes, total_bytes)),
+        styles.highlight.apply_to(packages.len()),
+    );
+
+    for package in packages {
         println!(
-            "- {}, size {} ({})",
+            "  - {}, size {} ({}), {} files in {} bundles",
+            styles.file.apply_to(&package.name),
+            styles.highlight.apply_to(format_bytes(package.bytes)),
+            styles
+                .highlight2
+                .apply_to(format_percentage(package.bytes, total_bytes)),
+            package.files,
+            package.bundles,
+        );
+    }
+}
+
+pub fn print_duplicate_report(report: &DuplicateReport) {
+    let styles = get_default_styles();
+
+    if report.is_empty() {
+        return;
+    }
+
+    println!(
+        "Bundled more than once, {} wasted:",
+        styles.error.apply_to(format_bytes(report.wasted_bytes()))
+    );
+
+    for package in &report.packages {
+        println!(
+            "- package {}, {} copies, {} wasted",
+            styles.file.apply_to(&package.name),
+            package.copies.len(),
+            styles.error.apply_to(format_bytes(package.wasted_bytes)),
+        );
+
+        for copy in &package.copies {
+            println!("  - {}: {}", copy.package.label(), format_copy(&copy.copy));
+        }
+    }
+
+    for module in &report.modules {
+        println!(
+            "- module {}, {} copies, {} wasted",
             styles
                 .file
-                .apply_to(without_relative_part(info.get_file_name(file_info.file))),
-            styles.highlight.apply_to(format_bytes(file_info.bytes as u64)),
+                .apply_to(module.copies[0].path.rsplit('/').next().unwrap_or_default()),
+            module.copies.len(),
+            styles.error.apply_to(format_bytes(module.wasted_bytes)),
+        );
+
+        for copy in &module.copies {
+            println!("  - {}", format_copy(copy));
+        }
+    }
+}
+
+// Sources compiled into several bundles, candidates for moving into a shared chunk.
+pub fn print_shared_sources(shared_sources: &[SharedSource]) {
+    let styles = get_default_styles();
+
+    if shared_sources.is_empty() {
+        return;
+    }
+
+    let duplicated_bytes = shared_sources.iter().map(|source| source.duplicated_bytes).sum::<u64>();
+
+    println!(
+        "Sources in more than one bundle, {} duplicated:",
+        styles.error.apply_to(format_bytes(duplicated_bytes))
+    );
+
+    for source in shared_sources {
+        let mut occurrences = source
+            .occurrences
+            .iter()
+            .take(MAX_BUNDLES_PER_COPY)
+            .map(|occurrence| {
+                format!(
+                    "{} {}",
+                    occurrence.bundle.rsplit('/').next().unwrap_or(&occurrence.bundle),
+                    styles.highlight.apply_to(format_bytes(occurrence.bytes))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        if source.occurrences.len() > MAX_BUNDLES_PER_COPY {
+            occurrences += &format!(" and {} more", source.occurrences.len() - MAX_BUNDLES_PER_COPY);
+        }
+
+        println!(
+            "- {}, in {} bundles, {} duplicated: {}",
+            styles.file.apply_to(&source.path),
+            source.occurrences.len(),
+            styles.error.apply_to(format_bytes(source.duplicated_bytes)),
+            occurrences,
+        );
+    }
+}
+
+// Sources of all bundles merged into one list, as if the whole build was a single file.
+pub fn print_combined_info(info: &CombinedInfo) {
+    let styles = get_default_styles();
+
+    if info.sources.is_empty() {
+        return;
+    }
+
+    println!(
+        "All {} bundles, total size {}, {} files (all paths are relative to {}):",
+        styles.highlight.apply_to(info.bundles),
+        styles.highlight.apply_to(format_bytes(info.file_bytes)),
+        styles.highlight.apply_to(info.sources.len()),
+        styles.file.apply_to(&info.sources_root),
+    );
+
+    for source in &info.sources {
+        println!(
+            "- {}, size {} ({}), in {} bundles",
+            styles.file.apply_to(info.relative_path(source)),
+            styles.highlight.apply_to(format_bytes(source.bytes)),
             styles
                 .highlight2
-                .apply_to(format_percentage(file_info.bytes as u64, source_file_len)),
+                .apply_to(format_percentage(source.bytes, info.file_bytes)),
+            source.bundles,
         );
     }
 
-    let sum_bytes = info.sum_bytes as u64;
-
     println!(
-        "Total: {} ({})",
-        styles.highlight.apply_to(format_bytes(sum_bytes)),
+        "Sum: {} ({})",
+        styles.highlight.apply_to(format_bytes(info.sum_bytes)),
         styles
             .highlight2
-            .apply_to(format_percentage(sum_bytes, source_file_len)),
+            .apply_to(format_percentage(info.sum_bytes, info.file_bytes)),
+    );
+    println!(
+        "Not coming from any source: {} ({})",
+        styles.highlight.apply_to(format_bytes(info.unmapped_bytes)),
+        styles
+            .highlight2
+            .apply_to(format_percentage(info.unmapped_bytes, info.file_bytes)),
+    );
+}
+
+fn format_copy(copy: &SourceCopy) -> String {
+    let styles = get_default_styles();
+
+    let mut bundles = copy
+        .bundles
+        .iter()
+        .take(MAX_BUNDLES_PER_COPY)
+        .map(|bundle| bundle.rsplit('/').next().unwrap_or(bundle))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if copy.bundles.len() > MAX_BUNDLES_PER_COPY {
+        bundles += &format!(" and {} more", copy.bundles.len() - MAX_BUNDLES_PER_COPY);
+    }
+
+    format!(
+        "{}, size {}, in {}",
+        copy.path,
+        styles.highlight.apply_to(format_bytes(copy.bytes)),
+        bundles
+    )
+}
+
+fn print_warnings(info: &SourceMappingInfo) {
+    let styles = get_default_styles();
+
+    if info.warnings.is_empty() {
+        return;
+    }
+
+    println!(
+        "{} {} invalid mappings were skipped, results might be incomplete (use --strict to fail instead):",
+        styles.warning.apply_to("Warning:"),
+        styles.warning.apply_to(info.warnings.len()),
+    );
+
+    for warning in info.warnings.iter().take(MAX_WARNINGS) {
+        println!("- {warning}");
+    }
+
+    if info.warnings.len() > MAX_WARNINGS {
+        println!("- ... and {} more", info.warnings.len() - MAX_WARNINGS);
+    }
+}
+
+fn print_coverage(info: &SourceMappingInfo) {
+    let styles = get_default_styles();
+
+    let coverage = &info.coverage;
+    let total_bytes = coverage.total_bytes() as u64;
+    let mapped_bytes = coverage.mapped_bytes as u64;
+
+    println!(
+        "Coverage: {} ({}) covered by mappings",
+        styles.highlight.apply_to(format_bytes(mapped_bytes)),
+        styles.highlight2.apply_to(format_percentage(mapped_bytes, total_bytes)),
     );
 
-    let rest = source_file_len - sum_bytes;
+    for kind in [GapKind::Attributed, GapKind::Unmapped] {
+        let bytes = coverage.gap_bytes(kind) as u64;
+
+        if bytes > 0 {
+            println!(
+                "- {}, size {} ({})",
+                kind.label(),
+                styles.highlight.apply_to(format_bytes(bytes)),
+                styles.highlight2.apply_to(format_percentage(bytes, total_bytes)),
+            );
+        }
+    }
+
+    let [unmapped_lines, sparse_lines, dense_lines, full_lines] = coverage.lines_by_density();
     println!(
-        "Remaining size taken by preamble, imports, whitespace, comments, etc.: {} ({})",
-        styles.highlight.apply_to(format_bytes(rest)),
-        styles.highlight2.apply_to(format_percentage(rest, source_file_len))
+        "Lines by mapped share: {} none, {} below 50%, {} at least 50%, {} fully mapped",
+        styles.highlight.apply_to(unmapped_lines),
+        styles.highlight.apply_to(sparse_lines),
+        styles.highlight.apply_to(dense_lines),
+        styles.highlight.apply_to(full_lines),
     );
+
+    if !coverage.gaps.is_empty() {
+        println!("Largest gaps (line:column):");
+    }
+
+    for gap in coverage.gaps.iter().take(MAX_PRINTED_COVERAGE_GAPS) {
+        println!(
+            "- {}:{} {}, size {}",
+            styles.file.apply_to(gap.line + 1),
+            styles.file.apply_to(gap.column + 1),
+            gap.kind.label(),
+            styles.highlight.apply_to(format_bytes(gap.bytes as u64)),
+        );
+    }
+}
+
+// Issues are printed one per line as "location: severity[code]: message", the format compilers
+// use, so that CI tools and editors can pick them up. Lines and columns are 1-based.
+pub fn print_validation_report(report: &ValidationReport) {
+    let styles = get_default_styles();
+
+    for issue in &report.issues {
+        let location = match issue.position() {
+            Some((line, column)) => format!("{}:{}:{}", report.file, line + 1, column + 1),
+            None => report.map_file.clone(),
+        };
+
+        let severity = match issue.severity() {
+            Severity::Error => styles.error.apply_to(issue.severity().label()),
+            Severity::Warning => styles.warning.apply_to(issue.severity().label()),
+        };
+
+        println!(
+            "{}: {}[{}]: {}",
+            styles.file.apply_to(location),
+            severity,
+            issue.code(),
+            issue
+        );
+    }
+}
+
+pub fn print_validation_error(file: &str, error: &Error) {
+    let styles = get_default_styles();
+
+    println!(
+        "{}: {}[parse]: {}",
+        styles.file.apply_to(file),
+        styles.error.apply_to(Severity::Error.label()),
+        error
+    );
+}
+
+// Table with number of failing files per kind of error, along with a hint how to fix them.
+pub fn print_error_summary(files_with_errors: &[(String, Error)]) {
+    let styles = get_default_styles();
+
+    if files_with_errors.is_empty() {
+        return;
+    }
+
+    let mut counts = BTreeMap::<ErrorKind, usize>::new();
+    for (_, error) in files_with_errors {
+        *counts.entry(error.kind()).or_default() += 1;
+    }
+
+    let kind_width = counts.keys().map(|kind| kind.label().len()).max().unwrap_or_default();
+
+    println!("Errors by kind:");
+    println!(
+        "{}",
+        styles
+            .file
+            .apply_to(format!("{:<kind_width$}  {:>5}  {}", "kind", "files", "hint"))
+    );
+
+    for (kind, count) in counts {
+        println!(
+            "{}  {}  {}",
+            styles.error.apply_to(format!("{:<kind_width$}", kind.label())),
+            styles.highlight.apply_to(format!("{count:>5}")),
+            kind.hint()
+        );
+    }
+}
+
+// Changes between two builds, bundles with their changed sources listed below them.
+pub fn print_diff_report(report: &DiffReport, threshold: Option<Threshold>) {
+    let styles = get_default_styles();
+
+    for (build, failed) in [("old", &report.old_failed), ("new", &report.new_failed)] {
+        if !failed.is_empty() {
+            println!(
+                "{} {} bundles of the {build} build could not be analyzed and are left out: {}",
+                styles.warning.apply_to("!"),
+                failed.len(),
+                failed.join(", "),
+            );
+        }
+    }
+
+    match threshold {
+        Some(threshold) => println!("Changes bigger than {threshold}:"),
+        None => println!("Changes:"),
+    }
+
+    if report.bundles.is_empty() {
+        println!("- none");
+    }
+
+    for bundle in &report.bundles {
+        // Actual file names are shown only when they differ from the identity, e.g. because of hashes.
+        let files = [&bundle.old_file, &bundle.new_file]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let files = match files.iter().all(|file| *file == bundle.identity) {
+            true => String::new(),
+            false => format!(" ({})", files.join(" -> ")),
+        };
+
+        let similarity = bundle
+            .similarity
+            .map(|similarity| format!(", {:.0}% of sources in common", similarity * 100.0))
+            .unwrap_or_default();
+
+        println!(
+            "{} {}{}, {}{}",
+            change_marker(&bundle.change),
+            styles.file.apply_to(&bundle.identity),
+            files,
+            format_change(&bundle.change),
+            similarity,
+        );
+
+        for source in &bundle.sources {
+            println!(
+                "  {} {}, {}",
+                change_marker(&source.change),
+                source.path,
+                format_change(&source.change),
+            );
+        }
+    }
+
+    let summary = &report.summary;
+
+    println!("Total: {}", format_change(&summary.total));
+    println!(
+        "Bundles: {} added, {} removed, {} grown, {} shrunk, {} unchanged",
+        styles.highlight.apply_to(summary.added),
+        styles.highlight.apply_to(summary.removed),
+        styles.highlight.apply_to(summary.grown),
+        styles.highlight.apply_to(summary.shrunk),
+        styles.highlight.apply_to(summary.unchanged),
+    );
+}
+
+fn change_marker(change: &SizeChange) -> &'static str {
+    match change.kind {
+        ChangeKind::Added => "+",
+        ChangeKind::Removed => "-",
+        ChangeKind::Grown | ChangeKind::Shrunk | ChangeKind::Unchanged => "~",
+    }
+}
+
+fn format_delta(delta: i64) -> String {
+    let sign = match delta {
+        delta if delta > 0 => "+",
+        delta if delta < 0 => "-",
+        _ => "",
+    };
+
+    format!("{sign}{}", format_bytes(delta.unsigned_abs()))
+}
+
+// Growth is shown as an error and shrinking as an improvement.
+fn format_change(change: &SizeChange) -> String {
+    let styles = get_default_styles();
+
+    let delta_style = match change.delta {
+        delta if delta > 0 => &styles.error,
+        delta if delta < 0 => &styles.highlight2,
+        _ => &styles.highlight,
+    };
+
+    let delta = delta_style.apply_to(format_delta(change.delta));
+
+    match (change.old, change.new, change.relative) {
+        (None, Some(new), _) => format!("added {}", styles.highlight.apply_to(format_bytes(new))),
+        (Some(old), None, _) => format!(
+            "removed, was {} ({delta})",
+            styles.highlight.apply_to(format_bytes(old))
+        ),
+        (Some(old), Some(new), relative) => format!(
+            "{} -> {}, {delta}{}",
+            styles.highlight.apply_to(format_bytes(old)),
+            styles.highlight.apply_to(format_bytes(new)),
+            relative
+                .map(|relative| format!(" ({})", delta_style.apply_to(format!("{relative:+.2}%"))))
+                .unwrap_or_default(),
+        ),
+        (None, None, _) => String::new(),
+    }
 }
@@ -13,4 +13,5 @@ pub const BACKGROUND: Color = convert(DEFAULT_COLORS.base);
 pub const HIGHLIGHT: Color = convert(DEFAULT_COLORS.teal);
 pub const HIGHLIGHT2: Color = convert(DEFAULT_COLORS.green);
 pub const ERROR: Color = convert(DEFAULT_COLORS.red);
+pub const WARNING: Color = convert(DEFAULT_COLORS.peach);
 pub const FOCUS: Color = convert(DEFAULT_COLORS.yellow);
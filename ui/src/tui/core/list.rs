@@ -144,7 +144,7 @@ impl<S: SelectableList, T> StatefulList<S, T> {
     pub fn prepare_scrollbar(&mut self, rect: Rect) -> &mut ScrollbarState {
         self.scrollbar_state = self
             .scrollbar_state
-            .content_length(self.items.len() - (rect.height as usize))
+            .content_length(self.items.len().saturating_sub(rect.height as usize))
             .position(self.state.offset());
 
         &mut self.scrollbar_state
@@ -165,25 +165,3 @@ impl SortOrder {
         }
     }
 }
-
-pub struct DummyList<S>
-where
-    S: SelectableList,
-{
-    pub state: S,
-    pub len: usize,
-}
-
-impl<S: SelectableList> ListOperations for DummyList<S> {
-    fn len(&self) -> usize {
-        self.len
-    }
-
-    fn selected(&self) -> Option<usize> {
-        self.state.selected()
-    }
-
-    fn select_inner(&mut self, index: Option<usize>) {
-        self.state.select(index);
-    }
-}
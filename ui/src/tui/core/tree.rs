@@ -171,14 +171,21 @@ impl Add for NoAggregation {
 
 impl<D: Debug> Tree<D, NoAggregation> {
     pub fn from(items: Vec<D>, get_path: impl Fn(&D) -> String) -> Self {
+        Self::from_parts(items, |item| {
+            get_path(item).split('/').map(|part| part.into()).collect()
+        })
+    }
+
+    // Like `from`, but a single part can contain slashes, e.g. scoped package names.
+    pub fn from_parts(items: Vec<D>, get_parts: impl Fn(&D) -> Vec<CompactString>) -> Self {
         let mut root_node: TreeNode<D> = TreeNode {
             location: TreeLocation::new("", String::new()),
             children: BTreeMap::new(),
         };
 
         for item in items {
-            let path = get_path(&item);
-            let mut path_parts = path.split('/').map(|part| part.into()).collect::<Vec<CompactString>>();
+            let mut path_parts = get_parts(&item);
+            let path = path_parts.join("/");
 
             let leaf = path_parts.pop().unwrap();
             let mut node = &mut root_node;
@@ -258,7 +265,7 @@ impl<D: Debug, A: Add<Output = A> + Copy> Tree<D, A> {
         &'tree self,
         state: &mut TreeState,
         data_mapper: impl Fn(&D) -> Vec<Span<'tree>>,
-    ) -> Vec<ListItem> {
+    ) -> Vec<ListItem<'tree>> {
         let mut paths = vec![];
         let mut items = vec![];
 
@@ -4,6 +4,7 @@ mod widgets;
 
 use std::time::Duration;
 
+use ::core::{analyzer::AnalyzeOptions, compression::SizeMetric, discovery::DiscoveryOptions};
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 use ratatui::{
@@ -24,10 +25,11 @@ use self::{
     },
     widgets::{
         dialog::DialogContent,
+        duplicates::DuplicatesState,
         file_list::{AnalyzeState, FileListState, FileListWidget},
         fps::FpsWidget,
         mapping_info::FileInfoState,
-        path_input::{PathInputWidget, PathState},
+        path_input::{format_filters, PathInputWidget, PathState},
         search_dialog::SearchDialogState,
     },
 };
@@ -38,6 +40,7 @@ pub enum FocusableWidget {
     FileList,
     FileInfo,
     SearchDialog,
+    Duplicates,
 }
 
 pub struct App {
@@ -47,6 +50,7 @@ pub struct App {
     file_info_state: FileInfoState,
     fps: FpsWidget,
     search_dialog: SearchDialogState,
+    duplicates_state: DuplicatesState,
 }
 
 impl Default for App {
@@ -54,10 +58,11 @@ impl Default for App {
         App {
             focused_widget: Some(FocusableWidget::PathInput),
             path_state: PathState::default(),
-            file_list_state: FileListState { analyze_state: None },
+            file_list_state: FileListState::default(),
             file_info_state: FileInfoState::default(),
             fps: FpsWidget::default(),
             search_dialog: SearchDialogState::default(),
+            duplicates_state: DuplicatesState::default(),
         }
     }
 }
@@ -69,6 +74,7 @@ impl App {
             Some(FocusableWidget::FileList) => Some(&mut self.file_list_state),
             Some(FocusableWidget::FileInfo) => Some(&mut self.file_info_state),
             Some(FocusableWidget::SearchDialog) => Some(&mut self.search_dialog),
+            Some(FocusableWidget::Duplicates) => Some(&mut self.duplicates_state),
             None => None,
         }
     }
@@ -86,14 +92,24 @@ impl App {
     }
 }
 
-pub fn run_tui_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App, initial_path: Option<&str>) -> Result<()> {
+pub fn run_tui_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    mut app: App,
+    initial_path: Option<&str>,
+    options: DiscoveryOptions,
+    analyze_options: AnalyzeOptions,
+    metric: SizeMetric,
+) -> Result<()> {
     app.path_state.path_input = app
         .path_state
         .path_input
         .with_value(initial_path.unwrap_or("./test_files/work").into());
+    app.path_state.filters_input = app.path_state.filters_input.with_value(format_filters(&options));
+    app.file_list_state.analyze_options = analyze_options;
+    app.file_list_state.metric = metric;
 
     if let Some(path) = initial_path {
-        app.file_list_state.analyze_path(path.into());
+        app.file_list_state.analyze_path(path.into(), options);
         app.focused_widget = Some(FocusableWidget::FileList);
     }
 
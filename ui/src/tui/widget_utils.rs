@@ -5,7 +5,7 @@ use ratatui::{
     Frame,
 };
 
-use crate::theme::{ERROR, FOCUS, HIGHLIGHT, HIGHLIGHT2, TEXT};
+use crate::theme::{ERROR, FOCUS, HIGHLIGHT, HIGHLIGHT2, TEXT, WARNING};
 
 pub fn centered_text(f: &mut Frame, text: &str, rect: Rect) {
     f.render_widget(
@@ -43,6 +43,10 @@ pub trait CustomStyles<'a, T>: Stylize<'a, T> {
         self.fg(ERROR)
     }
 
+    fn warning(self) -> T {
+        self.fg(WARNING)
+    }
+
     fn key(self) -> T {
         self.fg(FOCUS)
     }
@@ -0,0 +1,242 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Margin, Rect},
+    style::*,
+    text::{Line, Span, Text},
+    widgets::{
+        block::{Position, Title},
+        *,
+    },
+};
+
+use core::duplicates::{find_duplicates, index_sources, DuplicateReport, SharedSource, SourceCopy};
+
+use crate::{
+    keybindings,
+    theme::FOCUS,
+    tui::{
+        core::{
+            custom_widget::{CustomWidget, RenderContext},
+            FocusableWidgetState, HandleEventResult,
+        },
+        widget_utils::{default_block, default_scrollbar, CustomStyles},
+        App, FocusableWidget,
+    },
+    utils::format_bytes,
+};
+
+use super::{
+    file_list::{AnalyzeState, FileInfoType},
+    mapping_info::calculate_height,
+};
+
+// Packages and modules bundled more than once and sources compiled into several bundles, across
+// all analyzed bundles. Shown in place of the selected bundle info while focused.
+#[derive(Default)]
+pub struct DuplicatesState {
+    report: DuplicateReport,
+    shared_sources: Vec<SharedSource>,
+    scroll: u16,
+    text_height: u16,
+    max_height: u16,
+}
+
+impl DuplicatesState {
+    // Report is collected when the panel opens, so that it matches the current analysis.
+    pub fn open(app: &mut App) -> HandleEventResult {
+        let Some(AnalyzeState::Done(state)) = &app.file_list_state.analyze_state else {
+            return HandleEventResult::KeepFocus;
+        };
+
+        let infos = state
+            .file_infos
+            .items
+            .iter()
+            .filter_map(|info| match info {
+                FileInfoType::Info(info) => Some(info),
+                FileInfoType::Err(_) | FileInfoType::Combined(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        app.duplicates_state = DuplicatesState {
+            report: find_duplicates(infos.iter().copied()),
+            shared_sources: index_sources(infos).shared_sources(),
+            ..DuplicatesState::default()
+        };
+
+        HandleEventResult::ChangeFocus(FocusableWidget::Duplicates)
+    }
+
+    fn max_scroll(&self) -> u16 {
+        self.text_height.saturating_sub(self.max_height)
+    }
+
+    fn text(&self) -> Text<'static> {
+        let report = &self.report;
+
+        if report.is_empty() && self.shared_sources.is_empty() {
+            return "No package, module or source is bundled more than once.".into();
+        }
+
+        let mut lines = vec![];
+
+        if !report.is_empty() {
+            lines.push(Line::from(vec![
+                "Bundled more than once, ".into(),
+                format_bytes(report.wasted_bytes()).error(),
+                " wasted:".into(),
+            ]));
+        }
+
+        for package in &report.packages {
+            lines.push(Line::from(vec![
+                "- package ".into(),
+                package.name.clone().bold(),
+                format!(", {} copies, ", package.copies.len()).into(),
+                format_bytes(package.wasted_bytes).error(),
+                " wasted".into(),
+            ]));
+
+            for copy in &package.copies {
+                let mut spans = vec!["  - ".into(), copy.package.label().into(), ": ".into()];
+                spans.extend(copy_spans(&copy.copy));
+                lines.push(spans.into());
+            }
+        }
+
+        for module in &report.modules {
+            lines.push(Line::from(vec![
+                "- module ".into(),
+                file_name(&module.copies[0].path).to_owned().bold(),
+                format!(", {} copies, ", module.copies.len()).into(),
+                format_bytes(module.wasted_bytes).error(),
+                " wasted".into(),
+            ]));
+
+            for copy in &module.copies {
+                let mut spans = vec!["  - ".into()];
+                spans.extend(copy_spans(copy));
+                lines.push(spans.into());
+            }
+        }
+
+        if !self.shared_sources.is_empty() {
+            let duplicated_bytes = self.shared_sources.iter().map(|source| source.duplicated_bytes).sum();
+
+            lines.push(Line::from(vec![
+                "Sources in more than one bundle, ".into(),
+                format_bytes(duplicated_bytes).error(),
+                " duplicated:".into(),
+            ]));
+        }
+
+        for source in &self.shared_sources {
+            lines.push(Line::from(vec![
+                "- ".into(),
+                source.path.clone().bold(),
+                format!(", in {} bundles, ", source.occurrences.len()).into(),
+                format_bytes(source.duplicated_bytes).error(),
+                " duplicated".into(),
+            ]));
+
+            for occurrence in &source.occurrences {
+                lines.push(Line::from(vec![
+                    "  - ".into(),
+                    file_name(&occurrence.bundle).to_owned().into(),
+                    ", size ".into(),
+                    format_bytes(occurrence.bytes).highlight(),
+                ]));
+            }
+        }
+
+        lines.into()
+    }
+}
+
+fn copy_spans(copy: &SourceCopy) -> Vec<Span<'static>> {
+    let bundles = copy
+        .bundles
+        .iter()
+        .map(|bundle| file_name(bundle))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    vec![
+        copy.path.clone().into(),
+        ", size ".into(),
+        format_bytes(copy.bytes).highlight(),
+        ", in ".into(),
+        bundles.dark_gray(),
+    ]
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+impl FocusableWidgetState for DuplicatesState {
+    fn handle_events(&mut self, event: KeyEvent) -> HandleEventResult {
+        let max_scroll = self.max_scroll();
+
+        match event.code {
+            KeyCode::Esc | KeyCode::Char('d') => return HandleEventResult::ChangeFocus(FocusableWidget::FileList),
+            KeyCode::Down | KeyCode::Char('j') if max_scroll > 0 => {
+                self.scroll = if self.scroll == max_scroll { 0 } else { self.scroll + 1 };
+            }
+            KeyCode::Up | KeyCode::Char('k') if max_scroll > 0 => {
+                self.scroll = if self.scroll == 0 { max_scroll } else { self.scroll - 1 };
+            }
+            _ => {}
+        }
+
+        HandleEventResult::KeepFocus
+    }
+}
+
+pub struct DuplicatesWidget;
+
+impl CustomWidget for DuplicatesWidget {
+    fn bound_state(&self) -> Option<FocusableWidget> {
+        Some(FocusableWidget::Duplicates)
+    }
+
+    fn render<'widget, 'app: 'widget>(&self, mut context: RenderContext<'app, '_>, rect: Rect) {
+        let (app, frame) = context.app_frame_mut();
+        let state = &mut app.duplicates_state;
+
+        let block = default_block()
+            .border_style(Style::default().fg(FOCUS))
+            .title(Line::from(" duplicates across all bundles "))
+            .title(
+                Title::from(Line::from(keybindings!(
+                    "↑↓ jk"" scroll"; " | ".dark_gray();,
+                    "d"" close"
+                )))
+                .position(Position::Bottom),
+            );
+
+        let text = state.text();
+        state.max_height = block.inner(rect).height;
+        state.text_height = calculate_height(&text, block.clone(), rect);
+
+        frame.render_widget(
+            // Not trimmed, so that copies stay indented under their package.
+            Paragraph::new(text)
+                .block(block)
+                .wrap(Wrap { trim: false })
+                .scroll((state.scroll, 0)),
+            rect,
+        );
+
+        let mut scrollbar_state = ScrollbarState::new(state.max_scroll() as usize).position(state.scroll as usize);
+
+        frame.render_stateful_widget(
+            default_scrollbar(),
+            rect.inner(&Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut scrollbar_state,
+        );
+    }
+}
@@ -1,13 +1,13 @@
 use std::{
     cmp::Ordering as CmpOrdering,
     fmt::Debug,
+    path::Path,
     sync::{
         atomic::{AtomicU16, AtomicU8, Ordering},
         mpsc, Arc, Mutex,
     },
 };
 
-use anyhow::Error;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     prelude::*,
@@ -19,7 +19,15 @@ use ratatui::{
 };
 use threadpool::Builder as ThreadPoolBuilder;
 
-use core::{analyzer::SourceMappingInfo, discover_files, handle_file};
+use core::{
+    analyzer::{AnalyzeOptions, SourceMappingInfo},
+    combined::{combine_infos, CombinedInfo},
+    compression::SizeMetric,
+    discovery::{discover_files, relative_path, BundleKind, DiscoveryOptions},
+    handle_file,
+    parser::ParseContext,
+    Error,
+};
 
 use crate::{
     keybindings,
@@ -41,7 +49,10 @@ use crate::tui::{
     App, FocusableWidget,
 };
 
-use super::mapping_info::MappingInfoWidget;
+use super::{
+    duplicates::{DuplicatesState, DuplicatesWidget},
+    mapping_info::MappingInfoWidget,
+};
 
 pub enum AnalyzeState {
     Pending(AnalyzePendingState),
@@ -49,13 +60,22 @@ pub enum AnalyzeState {
     Err(Box<anyhow::Error>),
 }
 
+#[derive(Default)]
 pub struct FileListState {
     pub analyze_state: Option<AnalyzeState>,
+    pub analyze_options: AnalyzeOptions,
+    // Size driving sorting and percentages.
+    pub metric: SizeMetric,
+    // Path and discovery options of the last scan, so that it can be repeated with other analyze options.
+    last_scan: Option<(String, DiscoveryOptions)>,
 }
 
 impl FileListState {
-    pub fn analyze_path(&mut self, path: String) {
-        let pending_state = AnalyzePendingState::default();
+    pub fn analyze_path(&mut self, path: String, options: DiscoveryOptions) {
+        self.last_scan = Some((path.clone(), options.clone()));
+
+        let analyze_options = self.analyze_options.clone();
+        let pending_state = AnalyzePendingState::new(path.clone());
         let files_checked_atomic = pending_state.count.clone();
         let file_infos = pending_state.file_infos.clone();
         let state_atomic = pending_state.state.clone();
@@ -63,15 +83,16 @@ impl FileListState {
         self.analyze_state = Some(AnalyzeState::Pending(pending_state));
 
         std::thread::spawn(move || {
-            let files_to_check = match discover_files(&path) {
+            let files_to_check = match discover_files(&path, &options) {
                 Ok(files_to_check) => files_to_check,
                 Err(err) => {
-                    *error.lock().unwrap() = err.into();
+                    **error.lock().unwrap() = err.into();
                     state_atomic.store(OperationState::Err as u8, Ordering::Relaxed);
                     return;
                 }
             };
 
+            let context = Arc::new(ParseContext::new(options.map_root(&path)));
             let thread_pool = ThreadPoolBuilder::new().build();
 
             let (sender, receiver) = mpsc::channel::<FileInfoType>();
@@ -79,9 +100,11 @@ impl FileListState {
             for file in files_to_check {
                 let sender = sender.clone();
                 let files_checked_atomic = files_checked_atomic.clone();
+                let analyze_options = analyze_options.clone();
+                let context = context.clone();
 
                 thread_pool.execute(move || {
-                    let file_info = match handle_file(&file) {
+                    let file_info = match handle_file(&file, &context, &analyze_options) {
                         Ok(info) => FileInfoType::Info(info),
                         Err(err) => FileInfoType::Err(SourceMappingErrorInfo::new(file.to_owned(), err)),
                     };
@@ -101,6 +124,32 @@ impl FileListState {
 
 impl FocusableWidgetState for FileListState {
     fn handle_events(&mut self, event: KeyEvent) -> HandleEventResult {
+        if let (KeyCode::Char('a'), Some((path, options))) = (event.code, self.last_scan.clone()) {
+            let attribution = &mut self.analyze_options.attribution;
+            *attribution = attribution.next();
+            self.analyze_path(path, options);
+            return HandleEventResult::Callback(Box::new(Self::callback));
+        }
+
+        if let KeyCode::Char('m') = event.code {
+            self.metric = self.metric.next();
+
+            // Compressed sizes are estimated only on demand, as it takes a while.
+            match (&mut self.analyze_state, self.last_scan.clone()) {
+                (_, Some((path, options))) if self.metric.is_compressed() && !self.analyze_options.compression => {
+                    self.analyze_options.compression = true;
+                    self.analyze_path(path, options);
+                }
+                (Some(AnalyzeState::Done(state)), _) => {
+                    state.sort_with_order(state.sort, state.sort_order, self.metric)
+                }
+                _ => {}
+            }
+
+            return HandleEventResult::Callback(Box::new(Self::callback));
+        }
+
+        let metric = self.metric;
         if let Some(AnalyzeState::Done(state)) = &mut self.analyze_state {
             match event.code {
                 KeyCode::Esc => {
@@ -116,18 +165,23 @@ impl FocusableWidgetState for FileListState {
                     return HandleEventResult::Callback(Box::new(Self::callback));
                 }
                 KeyCode::Char('s') => {
-                    state.sort(FileInfoSort::Size);
+                    state.sort(FileInfoSort::Size, metric);
                     return HandleEventResult::Callback(Box::new(Self::callback));
                 }
                 KeyCode::Char('n') => {
-                    state.sort(FileInfoSort::Name);
+                    state.sort(FileInfoSort::Name, metric);
                     return HandleEventResult::Callback(Box::new(Self::callback));
                 }
                 KeyCode::Char('o') => {
-                    state.sort(FileInfoSort::NoFiles);
+                    state.sort(FileInfoSort::NoFiles, metric);
+                    return HandleEventResult::Callback(Box::new(Self::callback));
+                }
+                KeyCode::Char('t') => {
+                    state.sort(FileInfoSort::Type, metric);
                     return HandleEventResult::Callback(Box::new(Self::callback));
                 }
                 KeyCode::Char('f') => return HandleEventResult::ChangeFocus(FocusableWidget::SearchDialog),
+                KeyCode::Char('d') => return HandleEventResult::Callback(Box::new(DuplicatesState::open)),
                 KeyCode::Enter => return HandleEventResult::ChangeFocus(FocusableWidget::FileInfo),
                 _ => {}
             }
@@ -153,24 +207,25 @@ pub enum OperationState {
 }
 
 pub struct AnalyzePendingState {
+    // Path the scan started from, discovered files are presented relative to it.
+    pub root: String,
     pub count: Arc<AtomicU16>,
     pub state: Arc<AtomicU8>,
     pub error: Arc<Mutex<Box<anyhow::Error>>>,
     pub file_infos: Arc<Mutex<Vec<FileInfoType>>>,
 }
 
-impl Default for AnalyzePendingState {
-    fn default() -> Self {
+impl AnalyzePendingState {
+    pub fn new(root: String) -> Self {
         AnalyzePendingState {
+            root,
             count: Arc::default(),
             state: Arc::default(),
             error: Arc::new(Mutex::new(Box::new(anyhow::anyhow!("")))),
             file_infos: Arc::default(),
         }
     }
-}
 
-impl AnalyzePendingState {
     pub fn get_state(&self) -> OperationState {
         match self.state.load(Ordering::Relaxed) {
             0 => OperationState::Pending,
@@ -182,62 +237,82 @@ impl AnalyzePendingState {
 }
 
 pub struct AnalyzeDoneState {
-    pub files_checked: u16,
+    pub root: String,
     pub file_infos: StatefulList<TableState, FileInfoType>,
     pub sort: FileInfoSort,
     pub sort_order: SortOrder,
 }
 
 impl AnalyzeDoneState {
-    pub fn new(files_checked: u16, file_infos: Vec<FileInfoType>) -> Self {
+    pub fn new(root: String, mut file_infos: Vec<FileInfoType>) -> Self {
+        let infos = file_infos.iter().filter_map(|info| match info {
+            FileInfoType::Info(info) => Some(info),
+            _ => None,
+        });
+        let combined = combine_infos(infos);
+
+        if combined.bundles > 0 {
+            file_infos.insert(0, FileInfoType::Combined(combined));
+        }
+
         AnalyzeDoneState {
-            files_checked,
+            root,
             file_infos: StatefulList::with_items(file_infos),
             sort: FileInfoSort::Name,
             sort_order: SortOrder::Asc,
         }
     }
 
-    pub fn sort(&mut self, sort: FileInfoSort) {
+    pub fn sort(&mut self, sort: FileInfoSort, metric: SizeMetric) {
         let sort_order = if self.sort == sort {
             self.sort_order.reverse()
         } else {
             SortOrder::Asc
         };
 
-        self.sort_with_order(sort, sort_order)
+        self.sort_with_order(sort, sort_order, metric)
     }
 
-    pub fn sort_with_order(&mut self, sort: FileInfoSort, sort_order: SortOrder) {
+    pub fn sort_with_order(&mut self, sort: FileInfoSort, sort_order: SortOrder, metric: SizeMetric) {
         self.sort = sort;
         self.sort_order = sort_order;
 
-        let sort_function = match self.sort {
-            FileInfoSort::Size => Self::sort_by_size,
-            FileInfoSort::Name => Self::sort_by_name,
-            FileInfoSort::NoFiles => Self::sort_by_no_files,
-        };
+        self.file_infos.sort(
+            |a, b| match (a, b) {
+                // Combined entry stays at the top in both orders, so its ordering is reversed in advance.
+                (FileInfoType::Combined(_), _) => Self::pinned_first(sort_order),
+                (_, FileInfoType::Combined(_)) => Self::pinned_first(sort_order).reverse(),
+                _ => match sort {
+                    FileInfoSort::Size => Self::sort_by_size(a, b, metric),
+                    FileInfoSort::Name => Self::sort_by_name(a, b),
+                    FileInfoSort::NoFiles => Self::sort_by_no_files(a, b),
+                    FileInfoSort::Type => Self::sort_by_type(a, b),
+                },
+            },
+            sort_order,
+        );
+    }
 
-        self.file_infos.sort(sort_function, self.sort_order);
+    fn pinned_first(sort_order: SortOrder) -> CmpOrdering {
+        match sort_order {
+            SortOrder::Asc => CmpOrdering::Less,
+            SortOrder::Desc => CmpOrdering::Greater,
+        }
     }
 
-    fn sort_by_size(a: &FileInfoType, b: &FileInfoType) -> CmpOrdering {
+    fn sort_by_size(a: &FileInfoType, b: &FileInfoType, metric: SizeMetric) -> CmpOrdering {
         match (a, b) {
-            (FileInfoType::Info(a), FileInfoType::Info(b)) => a
-                .source_mapping
-                .actual_source_file_len()
-                .cmp(&b.source_mapping.actual_source_file_len()),
+            (FileInfoType::Info(a), FileInfoType::Info(b)) => a.file_size(metric).cmp(&b.file_size(metric)),
             (_, _) => Self::base_sort(a, b),
         }
     }
 
     fn sort_by_name(a: &FileInfoType, b: &FileInfoType) -> CmpOrdering {
-        let values = [a, b].map(|val| match val {
-            FileInfoType::Info(v) => &v.source_mapping.file_name,
-            FileInfoType::Err(v) => &v.file_name,
-        });
+        a.path().cmp(b.path())
+    }
 
-        values[0].cmp(values[1])
+    fn sort_by_type(a: &FileInfoType, b: &FileInfoType) -> CmpOrdering {
+        a.kind().cmp(&b.kind()).then_with(|| Self::sort_by_name(a, b))
     }
 
     fn sort_by_no_files(a: &FileInfoType, b: &FileInfoType) -> CmpOrdering {
@@ -251,34 +326,57 @@ impl AnalyzeDoneState {
         match (a, b) {
             (FileInfoType::Info(_), FileInfoType::Err(_)) => CmpOrdering::Greater,
             (FileInfoType::Err(_), FileInfoType::Info(_)) => CmpOrdering::Less,
-            (FileInfoType::Err(_), FileInfoType::Err(_)) => CmpOrdering::Equal,
+            // Failing bundles are grouped by the kind of error.
+            (FileInfoType::Err(a), FileInfoType::Err(b)) => {
+                a.error.kind().cmp(&b.error.kind()).then_with(|| a.file.cmp(&b.file))
+            }
             (_, _) => unreachable!(),
         }
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum FileInfoType {
     Info(SourceMappingInfo),
     Err(SourceMappingErrorInfo),
+    // Virtual "All bundles" entry, always listed first.
+    Combined(CombinedInfo),
+}
+
+impl FileInfoType {
+    pub const COMBINED_LABEL: &'static str = "All bundles";
+
+    // Path of the analyzed generated file, as it was discovered. Empty for the combined entry.
+    pub fn path(&self) -> &str {
+        match self {
+            FileInfoType::Info(info) => &info.source_mapping.file,
+            FileInfoType::Err(error_info) => &error_info.file,
+            FileInfoType::Combined(_) => "",
+        }
+    }
+
+    // Combined entry can be made of bundles of different kinds.
+    pub fn kind(&self) -> Option<BundleKind> {
+        match self {
+            FileInfoType::Info(info) => Some(info.source_mapping.kind),
+            FileInfoType::Err(error_info) => {
+                Some(BundleKind::from_path(Path::new(&error_info.file)).unwrap_or_default())
+            }
+            FileInfoType::Combined(_) => None,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct SourceMappingErrorInfo {
     pub file: String,
     pub error: Error,
-    pub file_name: String,
 }
 
 impl SourceMappingErrorInfo {
     pub fn new(file: String, error: Error) -> Self {
-        let file_name = match file.rfind('/') {
-            Some(pos) => file.get((pos + 1)..).unwrap_or(&file),
-            None => &file,
-        }
-        .to_string();
-
-        SourceMappingErrorInfo { file, error, file_name }
+        SourceMappingErrorInfo { file, error }
     }
 }
 
@@ -287,6 +385,7 @@ pub enum FileInfoSort {
     Size,
     Name,
     NoFiles,
+    Type,
 }
 
 pub struct FileListWidget;
@@ -299,6 +398,8 @@ impl CustomWidget for FileListWidget {
     fn render<'widget, 'app: 'widget>(&self, mut context: RenderContext<'app, '_>, rect: Rect) {
         let is_focused = context.is_focused();
 
+        let metric = context.app().file_list_state.metric;
+
         // Looks kinda funny, but allows for mutex value to be moved out of struct.
         let mut analyze_state = context.app_mut().file_list_state.analyze_state.take();
 
@@ -310,9 +411,9 @@ impl CustomWidget for FileListWidget {
                 match pending_state.get_state() {
                     OperationState::Done => {
                         let file_infos = Arc::try_unwrap(pending_state.file_infos).unwrap().into_inner().unwrap();
-                        let mut done_state = AnalyzeDoneState::new(files_checked, file_infos);
+                        let mut done_state = AnalyzeDoneState::new(pending_state.root, file_infos);
                         done_state.file_infos.next();
-                        done_state.sort_with_order(done_state.sort, done_state.sort_order);
+                        done_state.sort_with_order(done_state.sort, done_state.sort_order, metric);
                         analyze_state = Some(AnalyzeState::Done(done_state));
                     }
                     OperationState::Pending => {
@@ -345,27 +446,46 @@ impl CustomWidget for FileListWidget {
                     .items
                     .iter()
                     .map(|info| {
-                        let file_name = match info {
-                            FileInfoType::Info(info) => &info.source_mapping.file_name,
-                            FileInfoType::Err(error_info) => &error_info.file_name,
+                        let mut name = match info {
+                            FileInfoType::Combined(_) => Line::from(FileInfoType::COMBINED_LABEL.bold()),
+                            _ => Line::from(vec!["./".into(), relative_path(&state.root, info.path()).into()]),
+                        };
+
+                        match info {
+                            FileInfoType::Info(info) if !info.warnings.is_empty() => {
+                                name.spans.push(format!(" ⚠ {}", info.warnings.len()).warning());
+                            }
+                            FileInfoType::Err(error_info) => {
+                                name.spans.push(format!(" {}", error_info.error.kind().label()).error());
+                            }
+                            _ => {}
+                        }
+
+                        let kind = info.kind().map(|kind| kind.label()).unwrap_or_default();
+                        let mut cells: Vec<Cell> = vec![name.into(), kind.dark_gray().into()];
+
+                        // Only raw sizes are summed up for the combined entry.
+                        let sizes_and_files = match info {
+                            FileInfoType::Info(info) => Some((
+                                SizeMetric::ALL.map(|size_metric| info.file_size(size_metric)),
+                                info.info_by_file.len(),
+                            )),
+                            FileInfoType::Combined(combined) => {
+                                Some(([Some(combined.file_bytes), None, None], combined.sources.len()))
+                            }
+                            FileInfoType::Err(_) => None,
                         };
-                        let mut cells: Vec<Cell> = vec![Line::from(vec!["./".into(), file_name.into()]).into()];
-
-                        if let FileInfoType::Info(info) = info {
-                            cells.push(
-                                format_bytes(info.source_mapping.actual_source_file_len())
-                                    .highlight()
-                                    .to_right_aligned_line()
-                                    .into(),
-                            );
-                            cells.push(
-                                info.info_by_file
-                                    .len()
-                                    .to_string()
-                                    .highlight2()
-                                    .to_right_aligned_line()
-                                    .into(),
-                            );
+
+                        if let Some((sizes, files)) = sizes_and_files {
+                            cells.extend(sizes.map(|size| {
+                                match size {
+                                    Some(size) => format_bytes(size).highlight(),
+                                    None => "-".dark_gray(),
+                                }
+                                .to_right_aligned_line()
+                                .into()
+                            }));
+                            cells.push(files.to_string().highlight2().to_right_aligned_line().into());
                         } else {
                             cells.push("!".error().to_right_aligned_line().into());
                         }
@@ -375,17 +495,28 @@ impl CustomWidget for FileListWidget {
                     .collect();
 
                 let label = Line::from(keybindings!("f""ile list"));
+                let options_label = keybindings!(
+                    "a""ttribution: ";
+                    context.app().file_list_state.analyze_options.attribution.label().highlight(),
+                    " | ".dark_gray();,
+                    "m""etric: ";
+                    metric.name().highlight();
+                );
                 // + additional padding for scrollbar
-                let mut block = default_block().title(label).padding(Padding::right(1));
+                let mut block = default_block()
+                    .title(label)
+                    .title(Title::from(Line::from(options_label)).alignment(Alignment::Right))
+                    .padding(Padding::right(1));
 
                 if has_selection {
                     let title_contents = keybindings!(
                         "↑↓ jk"" select ";
                         "|".dark_gray(),
                         " sort: ".white();,
-                        "s""ize, ", "n""ame, n", "o"". files";
+                        "s""ize, ", "n""ame, n", "o"". files, ", "t""ype";
                         "| ".dark_gray();,
-                        "f""ind source file"
+                        "f""ind source file"; " | ".dark_gray();,
+                        "d""uplicates"
                     );
 
                     block = block
@@ -408,13 +539,31 @@ impl CustomWidget for FileListWidget {
                     block = block.border_style(Style::default().fg(FOCUS));
                 }
 
-                let table_widths = [Constraint::Fill(1), Constraint::Length(10), Constraint::Length(10)];
-                let table_header = Row::new(vec![
-                    "name".into(),
-                    Span::from("size").to_right_aligned_line(),
-                    Span::from("no. files").to_right_aligned_line(),
-                ])
-                .style(Style::new().bold());
+                let table_widths = [
+                    Constraint::Fill(1),
+                    Constraint::Length(4),
+                    Constraint::Length(10),
+                    Constraint::Length(10),
+                    Constraint::Length(10),
+                    Constraint::Length(10),
+                ];
+                let mut header_cells: Vec<Cell> = vec!["name".into(), "type".into()];
+                header_cells.extend(SizeMetric::ALL.map(|size_metric| {
+                    let header = match size_metric {
+                        SizeMetric::Raw => Span::from("size"),
+                        _ => Span::from(size_metric.name()),
+                    };
+
+                    // Selected metric drives sorting by size.
+                    match size_metric == metric {
+                        true => header.highlight(),
+                        false => header,
+                    }
+                    .to_right_aligned_line()
+                    .into()
+                }));
+                header_cells.push(Span::from("no. files").to_right_aligned_line().into());
+                let table_header = Row::new(header_cells).style(Style::new().bold());
 
                 let (app, frame) = context.app_frame_mut();
 
@@ -433,7 +582,10 @@ impl CustomWidget for FileListWidget {
                     state.file_infos.prepare_scrollbar(chunks[0]),
                 );
 
-                if let Some(item) = state.file_infos.selected_item() {
+                if app.focused_widget == Some(FocusableWidget::Duplicates) {
+                    let context = RenderContext::new(app, frame, Some(FocusableWidget::Duplicates));
+                    DuplicatesWidget.render(context, chunks[1]);
+                } else if let Some(item) = state.file_infos.selected_item() {
                     let context = RenderContext::new(app, frame, Some(FocusableWidget::FileInfo));
                     MappingInfoWidget { info: item }.render(context, chunks[1]);
                 }
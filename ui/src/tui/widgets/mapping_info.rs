@@ -1,17 +1,24 @@
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     buffer::Buffer,
-    layout::{Margin, Rect},
+    layout::{Alignment, Margin, Rect},
     style::*,
-    text::{Line, Text},
+    text::{Line, Span, Text},
     widgets::{
         block::{Position, Title},
         *,
     },
+    Frame,
 };
 
-use core::analyzer::{SourceMappingFileInfo, SourceMappingInfo};
-use std::{ops::Add, rc::Rc};
+use compact_str::CompactString;
+use core::{
+    analyzer::{GapKind, SourceMappingFileInfo, SourceMappingInfo},
+    combined::CombinedInfo,
+    compression::{SizeMetric, METHOD_NOTE},
+    packages::find_package,
+};
+use std::{borrow::Cow, fmt::Debug, ops::Add, rc::Rc};
 
 use crate::{
     keybindings,
@@ -24,7 +31,7 @@ use crate::{
         },
         widget_utils::default_scrollbar,
     },
-    utils::{format_bytes, format_percentage, without_relative_part},
+    utils::{format_bytes, format_percentage},
 };
 
 use crate::tui::{
@@ -50,10 +57,16 @@ impl CustomWidget for MappingInfoWidget<'_> {
         let file_info_state = &mut context.app_mut().file_info_state;
 
         match file_info_state.view_type {
-            FileInfoViewType::Tree if matches!(self.info, FileInfoType::Info(info) if !info.source_mapping.is_empty()) =>
+            FileInfoViewType::Coverage if matches!(self.info, FileInfoType::Info(_)) => {
+                CoverageInfoWidget { info: self.info }.render(context, rect);
+            }
+            FileInfoViewType::Tree | FileInfoViewType::Packages if matches!(self.info, FileInfoType::Info(info) if !info.source_mapping.is_empty()) =>
             {
                 TreeInfoWidget { info: self.info }.render(context, rect);
             }
+            FileInfoViewType::Tree | FileInfoViewType::Packages if matches!(self.info, FileInfoType::Combined(_)) => {
+                CombinedTreeWidget { info: self.info }.render(context, rect);
+            }
             _ => {
                 ParagraphInfoWidget { info: self.info }.render(context, rect);
             }
@@ -61,19 +74,217 @@ impl CustomWidget for MappingInfoWidget<'_> {
     }
 }
 
+// Compressed sizes are missing unless they were estimated during analysis.
 #[derive(Clone, Copy)]
 struct TreeAggregation {
     bytes: u64,
+    gzip: Option<u64>,
+    brotli: Option<u64>,
+}
+
+impl TreeAggregation {
+    fn size(&self, metric: SizeMetric) -> Option<u64> {
+        match metric {
+            SizeMetric::Raw => Some(self.bytes),
+            SizeMetric::Gzip => self.gzip,
+            SizeMetric::Brotli => self.brotli,
+        }
+    }
 }
 
 impl Add for TreeAggregation {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
+        let add = |a: Option<u64>, b: Option<u64>| Some(a? + b?);
+
         Self {
             bytes: self.bytes + rhs.bytes,
+            gzip: add(self.gzip, rhs.gzip),
+            brotli: add(self.brotli, rhs.brotli),
+        }
+    }
+}
+
+// Leaf of the sources tree. Files with known original identifiers become nodes, with each
+// identifier and the rest of the file not attributed to any identifier as leaves. Bytes not
+// coming from any source are grouped under a separate node.
+#[derive(Debug, Clone, Copy)]
+enum TreeInfoLeaf {
+    File(usize),
+    Name(usize, usize),
+    Unnamed(usize),
+    Unmapped(usize),
+}
+
+impl TreeInfoLeaf {
+    const UNNAMED_LABEL: &'static str = "(unnamed)";
+    const UNKNOWN_NAME_LABEL: &'static str = "(unknown name)";
+    const UNMAPPED_LABEL: &'static str = "(unmapped)";
+    const OWN_CODE_LABEL: &'static str = "(your code)";
+    const THIRD_PARTY_LABEL: &'static str = "(third-party)";
+
+    fn leaves(info: &SourceMappingInfo) -> Vec<TreeInfoLeaf> {
+        let mut leaves = vec![];
+
+        for (index, file_info) in info.info_by_file.iter().enumerate() {
+            if file_info.info_by_name.is_empty() {
+                leaves.push(TreeInfoLeaf::File(index));
+                continue;
+            }
+
+            leaves.extend((0..file_info.info_by_name.len()).map(|name_index| TreeInfoLeaf::Name(index, name_index)));
+
+            if TreeInfoLeaf::Unnamed(index).bytes(info) > 0 {
+                leaves.push(TreeInfoLeaf::Unnamed(index));
+            }
+        }
+
+        leaves.extend((0..info.unmapped.len()).map(TreeInfoLeaf::Unmapped));
+
+        leaves
+    }
+
+    fn path(&self, info: &SourceMappingInfo) -> String {
+        match self {
+            TreeInfoLeaf::File(index) => info.get_file_name(info.info_by_file[*index].file).to_owned(),
+            TreeInfoLeaf::Name(index, _) | TreeInfoLeaf::Unnamed(index) => {
+                format!(
+                    "{}/{}",
+                    info.get_file_name(info.info_by_file[*index].file),
+                    self.label(info)
+                )
+            }
+            TreeInfoLeaf::Unmapped(_) => format!("{}/{}", Self::UNMAPPED_LABEL, self.label(info)),
+        }
+    }
+
+    // Path in the packages view, with sources grouped under the npm package they come from.
+    fn package_path(&self, info: &SourceMappingInfo) -> Vec<CompactString> {
+        let index = match self {
+            TreeInfoLeaf::File(index) | TreeInfoLeaf::Name(index, _) | TreeInfoLeaf::Unnamed(index) => *index,
+            TreeInfoLeaf::Unmapped(_) => return vec![Self::UNMAPPED_LABEL.into(), self.label(info).into()],
+        };
+
+        let file = info.info_by_file[index].file;
+        let mut parts = Self::package_parts(&info.get_full_file_name(file), info.get_file_name(file));
+
+        if !matches!(self, TreeInfoLeaf::File(_)) {
+            parts.push(self.label(info).into());
+        }
+
+        parts
+    }
+
+    // Own code keeps the path relative to sources root, packages start from their own folder.
+    fn package_parts(full_file_name: &str, file_name: &str) -> Vec<CompactString> {
+        match find_package(full_file_name) {
+            Some(package) => [Self::THIRD_PARTY_LABEL.into(), package.label().into()]
+                .into_iter()
+                .chain(package.inner_path(full_file_name).split('/').map(Into::into))
+                .collect(),
+            None => [Self::OWN_CODE_LABEL]
+                .into_iter()
+                .chain(file_name.split('/'))
+                .map(Into::into)
+                .collect(),
+        }
+    }
+
+    fn label<'info>(&self, info: &'info SourceMappingInfo) -> Cow<'info, str> {
+        match self {
+            TreeInfoLeaf::File(index) => info
+                .get_file_name(info.info_by_file[*index].file)
+                .split('/')
+                .next_back()
+                .unwrap()
+                .into(),
+            TreeInfoLeaf::Name(index, name_index) => info
+                .get_name(info.info_by_file[*index].info_by_name[*name_index].name)
+                .unwrap_or(Self::UNKNOWN_NAME_LABEL)
+                .into(),
+            TreeInfoLeaf::Unnamed(_) => Self::UNNAMED_LABEL.into(),
+            TreeInfoLeaf::Unmapped(index) => info.unmapped[*index].category.label().into(),
+        }
+    }
+
+    fn bytes(&self, info: &SourceMappingInfo) -> u32 {
+        match self {
+            TreeInfoLeaf::File(index) => info.info_by_file[*index].bytes,
+            TreeInfoLeaf::Name(index, name_index) => info.info_by_file[*index].info_by_name[*name_index].bytes,
+            TreeInfoLeaf::Unnamed(index) => {
+                let file_info = &info.info_by_file[*index];
+                let named_bytes = file_info
+                    .info_by_name
+                    .iter()
+                    .map(|name_info| name_info.bytes)
+                    .sum::<u32>();
+                file_info.bytes.saturating_sub(named_bytes)
+            }
+            TreeInfoLeaf::Unmapped(index) => info.unmapped[*index].bytes,
+        }
+    }
+
+    fn size(&self, info: &SourceMappingInfo, metric: SizeMetric) -> Option<u64> {
+        match self {
+            TreeInfoLeaf::File(index) => info.info_by_file[*index].size(metric),
+            TreeInfoLeaf::Name(index, _) | TreeInfoLeaf::Unnamed(index) => {
+                let file_info = &info.info_by_file[*index];
+                info.part_size(self.bytes(info), file_info.bytes, file_info.size(metric), metric)
+            }
+            TreeInfoLeaf::Unmapped(_) => info.part_size(
+                self.bytes(info),
+                info.unmapped_bytes(),
+                info.unmapped_size(metric),
+                metric,
+            ),
+        }
+    }
+
+    fn aggregation(&self, info: &SourceMappingInfo) -> TreeAggregation {
+        TreeAggregation {
+            bytes: self.bytes(info) as u64,
+            gzip: self.size(info, SizeMetric::Gzip),
+            brotli: self.size(info, SizeMetric::Brotli),
+        }
+    }
+}
+
+// All known sizes, with the selected metric highlighted and shown as a share of the whole file.
+fn size_spans(
+    size: impl Fn(SizeMetric) -> Option<u64>,
+    file_size: impl Fn(SizeMetric) -> Option<u64>,
+    selected: SizeMetric,
+) -> Vec<Span<'static>> {
+    let mut spans = vec![];
+
+    for metric in SizeMetric::ALL {
+        let Some(value) = size(metric) else {
+            continue;
+        };
+
+        if !spans.is_empty() {
+            spans.push(", ".into());
+        }
+
+        let formatted = match metric {
+            SizeMetric::Raw => format_bytes(value),
+            _ => format!("{} ~{}", metric.name(), format_bytes(value)),
+        };
+
+        if metric != selected {
+            spans.push(formatted.dark_gray());
+            continue;
+        }
+
+        spans.push(formatted.highlight());
+
+        if let Some(total) = file_size(metric) {
+            spans.extend([" (".into(), format_percentage(value, total).highlight2(), ")".into()]);
         }
     }
+
+    spans
 }
 
 struct TreeInfoWidget<'info> {
@@ -88,38 +299,84 @@ impl CustomWidget for TreeInfoWidget<'_> {
     fn render<'widget, 'app: 'widget>(&self, mut context: RenderContext<'app, '_>, rect: Rect) {
         let is_focused = context.is_focused();
         let (app, frame) = context.app_frame_mut();
-        let file_info_state = &mut app.file_info_state;
 
         let FileInfoType::Info(info) = self.info else {
             unreachable!()
         };
 
-        let mapping = &info.source_mapping;
-        let source_file_len = mapping.actual_source_file_len();
+        let metric = app.file_list_state.metric;
+        let file_info_state = &mut app.file_info_state;
+        let tree = file_info_state.build_tree(info, metric);
+
+        let list_items = tree.as_list_items(&mut file_info_state.tree_state, |leaf| {
+            let label = match leaf {
+                TreeInfoLeaf::Unnamed(_) | TreeInfoLeaf::Unmapped(_) => Span::from(leaf.label(info)).dark_gray(),
+                _ => leaf.label(info).into(),
+            };
+
+            let mut spans = vec![label, " ".into()];
+            spans.extend(size_spans(
+                |size_metric| leaf.size(info, size_metric),
+                |size_metric| info.file_size(size_metric),
+                metric,
+            ));
+            spans
+        });
+
+        let block = get_block(is_focused, info.warnings.len());
 
-        let tree = file_info_state.build_tree(info);
+        frame.render_stateful_widget(
+            List::new(list_items)
+                .block(block)
+                .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)),
+            rect,
+            &mut file_info_state.tree_state.list_state,
+        );
+    }
+}
+
+// Sizes of the combined entry are summed up raw bytes only.
+fn combined_size(bytes: u64) -> impl Fn(SizeMetric) -> Option<u64> {
+    move |metric| (metric == SizeMetric::Raw).then_some(bytes)
+}
+
+struct CombinedTreeWidget<'info> {
+    info: &'info FileInfoType,
+}
+
+impl CustomWidget for CombinedTreeWidget<'_> {
+    fn bound_state(&self) -> Option<FocusableWidget> {
+        Some(FocusableWidget::FileInfo)
+    }
+
+    fn render<'widget, 'app: 'widget>(&self, mut context: RenderContext<'app, '_>, rect: Rect) {
+        let is_focused = context.is_focused();
+        let (app, frame) = context.app_frame_mut();
+
+        let FileInfoType::Combined(info) = self.info else {
+            unreachable!()
+        };
+
+        let file_info_state = &mut app.file_info_state;
+        let tree = file_info_state.build_combined_tree(info);
 
         let list_items = tree.as_list_items(&mut file_info_state.tree_state, |index| {
-            let file_info = &info.info_by_file[*index];
-            vec![
-                without_relative_part(info.get_file_name(file_info.file))
-                    .split('/')
-                    .last()
-                    .unwrap()
-                    .into(),
-                " ".into(),
-                format_bytes(file_info.bytes as u64).highlight(),
-                " (".into(),
-                format_percentage(file_info.bytes as u64, source_file_len).highlight2(),
-                ")".into(),
-            ]
+            let source = &info.sources[*index];
+            let file_name = info.relative_path(source).rsplit('/').next().unwrap_or_default();
+
+            let mut spans = vec![file_name.to_owned().into(), " ".into()];
+            spans.extend(size_spans(
+                combined_size(source.bytes),
+                combined_size(info.file_bytes),
+                SizeMetric::Raw,
+            ));
+            spans.push(format!(", in {} bundles", source.bundles).dark_gray());
+            spans
         });
 
-        let block = get_block(is_focused);
-
         frame.render_stateful_widget(
             List::new(list_items)
-                .block(block)
+                .block(get_block(is_focused, 0))
                 .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD)),
             rect,
             &mut file_info_state.tree_state.list_state,
@@ -139,6 +396,7 @@ impl CustomWidget for ParagraphInfoWidget<'_> {
     fn render<'widget, 'app: 'widget>(&self, mut context: RenderContext<'app, '_>, rect: Rect) {
         let is_focused = context.is_focused();
         let (app, frame) = context.app_frame_mut();
+        let metric = app.file_list_state.metric;
         let file_info_state = &mut app.file_info_state;
 
         let text = match self.info {
@@ -153,114 +411,345 @@ impl CustomWidget for ParagraphInfoWidget<'_> {
 
                     let source_file_len = mapping.actual_source_file_len();
 
+                    let mut file_size_line =
+                        Line::from(vec!["File size: ".into(), format_bytes(source_file_len).highlight()]);
+                    if let Some(compression) = &info.compression {
+                        file_size_line.spans.extend([
+                            ", gzip ".into(),
+                            format_bytes(compression.total.gzip).highlight(),
+                            ", brotli ".into(),
+                            format_bytes(compression.total.brotli).highlight(),
+                        ]);
+                    }
+                    file_size_line.spans.push(".".into());
+
                     let mut lines = vec![
-                        Line::from(vec![
-                            "File size: ".into(),
-                            format_bytes(source_file_len).highlight(),
-                            ".".into(),
-                        ]),
+                        file_size_line,
                         Line::from(vec![
                             "Number of files: ".into(),
                             info.info_by_file.len().to_string().highlight(),
                             ".".into(),
                         ]),
-                        Line::from(vec![
-                            "Size contribution per file (all paths are relative to ".into(),
-                            sources_root.bold(),
-                            "):".into(),
-                        ]),
                     ];
 
+                    if !info.warnings.is_empty() {
+                        lines.push(Line::from(vec![
+                            "Invalid mappings skipped: ".into(),
+                            info.warnings.len().to_string().warning(),
+                            ", results might be incomplete.".into(),
+                        ]));
+
+                        for warning in info.warnings.iter().take(MAX_WARNINGS) {
+                            lines.push(format!("- {warning}").warning().into());
+                        }
+
+                        if info.warnings.len() > MAX_WARNINGS {
+                            lines.push(format!("... and {} more warnings", info.warnings.len() - MAX_WARNINGS).into());
+                        }
+                    }
+
+                    lines.push(Line::from(vec![
+                        "Size contribution per file (all paths are relative to ".into(),
+                        sources_root.bold(),
+                        "):".into(),
+                    ]));
+
+                    if !info.info_by_name.is_empty() {
+                        lines.push(
+                            "Identifiers in the tree view are sized by the code mapped to them directly, usually \
+                             just their name."
+                                .dark_gray()
+                                .into(),
+                        );
+                    }
+
                     let mut info_by_file = info.info_by_file.iter().collect::<Vec<&SourceMappingFileInfo>>();
                     info_by_file.sort_by_key(|i| i.bytes);
 
+                    let file_size = |size_metric| info.file_size(size_metric);
+
                     for file_info in info_by_file.iter().rev() {
+                        let mut spans = vec!["- ".into(), info.get_file_name(file_info.file).bold(), ", size ".into()];
+                        spans.extend(size_spans(|size_metric| file_info.size(size_metric), file_size, metric));
+
+                        if let Some(estimate) = file_info.compressed {
+                            spans.push(format!(" [{}]", estimate.accuracy.label()).dark_gray());
+                        }
+
+                        lines.push(spans.into());
+                    }
+
+                    let sum_bytes = info.sum_bytes as u64;
+
+                    let mut spans = vec!["Sum: ".into()];
+                    spans.extend(size_spans(|size_metric| info.sum_size(size_metric), file_size, metric));
+                    lines.push(spans.into());
+
+                    if !info.unmapped.is_empty() {
+                        lines.push("Size not coming from any source:".into());
+                    }
+
+                    for unmapped_info in &info.unmapped {
+                        let size = |size_metric| {
+                            info.part_size(
+                                unmapped_info.bytes,
+                                info.unmapped_bytes(),
+                                info.unmapped_size(size_metric),
+                                size_metric,
+                            )
+                        };
+
+                        let mut spans = vec!["- ".into(), unmapped_info.category.label().into(), ", size ".into()];
+                        spans.extend(size_spans(size, file_size, metric));
+                        lines.push(spans.into());
+                    }
+
+                    let rest = source_file_len.saturating_sub(sum_bytes + info.unmapped_bytes() as u64);
+
+                    if rest > 0 {
                         lines.push(
                             vec![
-                                "- ".into(),
-                                without_relative_part(info.get_file_name(file_info.file)).bold(),
-                                ", size ".into(),
-                                format_bytes(file_info.bytes as u64).highlight(),
+                                "Remaining unclassified size: ".into(),
+                                format_bytes(rest).highlight(),
                                 " (".into(),
-                                format_percentage(file_info.bytes as u64, source_file_len).highlight2(),
+                                format_percentage(rest, source_file_len).highlight2(),
                                 ")".into(),
                             ]
                             .into(),
                         );
                     }
 
-                    let sum_bytes = info.sum_bytes as u64;
-
-                    lines.push(
-                        vec![
-                            "Sum: ".into(),
-                            format_bytes(sum_bytes).highlight(),
-                            " (".into(),
-                            format_percentage(sum_bytes, source_file_len).highlight2(),
-                            ")".into(),
-                        ]
-                        .into(),
-                    );
-
-                    let rest = source_file_len - sum_bytes;
-
-                    lines.push(
-                        vec![
-                            "Remaining size taken by preamble, imports, whitespace, comments, etc.: ".into(),
-                            format_bytes(rest).highlight(),
-                            " (".into(),
-                            format_percentage(rest, source_file_len).highlight2(),
-                            ")".into(),
-                        ]
-                        .into(),
-                    );
+                    if info.compression.is_some() {
+                        lines.push(format!("Compressed sizes are {}.", METHOD_NOTE).dark_gray().into());
+                    }
 
                     lines.into()
                 };
 
                 text
             }
-            FileInfoType::Err(error_info) => error_info.error.to_string().into(),
+            FileInfoType::Combined(info) => combined_text(info),
+            FileInfoType::Err(error_info) => {
+                let kind = error_info.error.kind();
+
+                vec![
+                    Line::from(vec!["Error: ".into(), kind.label().error()]),
+                    error_info.error.to_string().into(),
+                    Line::from(vec!["Hint: ".into(), kind.hint().dark_gray()]),
+                ]
+                .into()
+            }
         };
 
-        let block = get_block(is_focused);
+        let warnings = match self.info {
+            FileInfoType::Info(info) => info.warnings.len(),
+            FileInfoType::Err(_) | FileInfoType::Combined(_) => 0,
+        };
 
-        let block_inner = block.inner(rect);
+        render_paragraph(frame, file_info_state, text, is_focused, warnings, rect);
+    }
+}
 
-        let height = calculate_height(&text, block.clone(), rect);
+fn combined_text(info: &CombinedInfo) -> Text<'static> {
+    let file_size = combined_size(info.file_bytes);
+
+    let mut lines = vec![
+        Line::from(vec![
+            "Bundles: ".into(),
+            info.bundles.to_string().highlight(),
+            ", total size ".into(),
+            format_bytes(info.file_bytes).highlight(),
+            ".".into(),
+        ]),
+        Line::from(vec![
+            "Number of files: ".into(),
+            info.sources.len().to_string().highlight(),
+            ".".into(),
+        ]),
+        Line::from(vec![
+            "Size contribution per file across all bundles (all paths are relative to ".into(),
+            info.sources_root.clone().bold(),
+            "):".into(),
+        ]),
+    ];
+
+    for source in &info.sources {
+        let mut spans = vec![
+            "- ".into(),
+            info.relative_path(source).to_owned().bold(),
+            ", size ".into(),
+        ];
+        spans.extend(size_spans(combined_size(source.bytes), &file_size, SizeMetric::Raw));
+        spans.push(format!(", in {} bundles", source.bundles).into());
+        lines.push(spans.into());
+    }
 
-        file_info_state.max_height = block_inner.height;
-        file_info_state.text_height = height;
+    for (label, bytes) in [
+        ("Sum: ", info.sum_bytes),
+        ("Size not coming from any source: ", info.unmapped_bytes),
+    ] {
+        let mut spans = vec![label.into()];
+        spans.extend(size_spans(combined_size(bytes), &file_size, SizeMetric::Raw));
+        lines.push(spans.into());
+    }
 
-        frame.render_widget(
-            Paragraph::new(text)
-                .block(block)
-                .wrap(Wrap { trim: true })
-                .scroll((file_info_state.scroll, 0)),
-            rect,
-        );
+    lines.into()
+}
 
-        let mut scrollbar_state =
-            ScrollbarState::new(file_info_state.max_scroll() as usize).position(file_info_state.scroll as usize);
+// Warnings are listed in order of mappings, long lists are cut the same way as coverage gaps.
+const MAX_WARNINGS: usize = 200;
 
-        frame.render_stateful_widget(
-            default_scrollbar(),
-            rect.inner(&Margin {
-                vertical: 1,
-                horizontal: 0,
-            }),
-            &mut scrollbar_state,
+// Largest gaps are listed first, the rest is rarely interesting and only slows rendering down.
+const MAX_LISTED_COVERAGE_GAPS: usize = 200;
+
+struct CoverageInfoWidget<'info> {
+    info: &'info FileInfoType,
+}
+
+impl CustomWidget for CoverageInfoWidget<'_> {
+    fn bound_state(&self) -> Option<FocusableWidget> {
+        Some(FocusableWidget::FileInfo)
+    }
+
+    fn render<'widget, 'app: 'widget>(&self, mut context: RenderContext<'app, '_>, rect: Rect) {
+        let is_focused = context.is_focused();
+        let (app, frame) = context.app_frame_mut();
+        let file_info_state = &mut app.file_info_state;
+
+        let FileInfoType::Info(info) = self.info else {
+            unreachable!()
+        };
+
+        let coverage = &info.coverage;
+        let total_bytes = coverage.total_bytes() as u64;
+        let mapped_bytes = coverage.mapped_bytes as u64;
+
+        let mut lines = vec![Line::from(vec![
+            "Covered by mappings: ".into(),
+            format_bytes(mapped_bytes).highlight(),
+            " (".into(),
+            format_percentage(mapped_bytes, total_bytes).highlight2(),
+            ")".into(),
+        ])];
+
+        for kind in [GapKind::Attributed, GapKind::Unmapped] {
+            let bytes = coverage.gap_bytes(kind) as u64;
+
+            if bytes > 0 {
+                lines.push(Line::from(vec![
+                    "- ".into(),
+                    kind.label().into(),
+                    ", size ".into(),
+                    format_bytes(bytes).highlight(),
+                    " (".into(),
+                    format_percentage(bytes, total_bytes).highlight2(),
+                    ")".into(),
+                ]));
+            }
+        }
+
+        let [unmapped_lines, sparse_lines, dense_lines, full_lines] = coverage.lines_by_density();
+        lines.push(Line::from(vec![
+            "Lines by mapped share: ".into(),
+            unmapped_lines.to_string().highlight(),
+            " none, ".into(),
+            sparse_lines.to_string().highlight(),
+            " below 50%, ".into(),
+            dense_lines.to_string().highlight(),
+            " at least 50%, ".into(),
+            full_lines.to_string().highlight(),
+            " fully mapped".into(),
+        ]));
+
+        if !coverage.gaps.is_empty() {
+            lines.push("Largest gaps (line:column):".into());
+        }
+
+        for gap in coverage.gaps.iter().take(MAX_LISTED_COVERAGE_GAPS) {
+            let kind = match gap.kind {
+                GapKind::Unmapped => gap.kind.label().into(),
+                GapKind::Attributed => gap.kind.label().dark_gray(),
+            };
+
+            lines.push(Line::from(vec![
+                "- ".into(),
+                format!("{}:{}", gap.line + 1, gap.column + 1).bold(),
+                " ".into(),
+                kind,
+                ", size ".into(),
+                format_bytes(gap.bytes as u64).highlight(),
+            ]));
+        }
+
+        if coverage.gaps.len() > MAX_LISTED_COVERAGE_GAPS {
+            lines.push(format!("... and {} more gaps", coverage.gaps.len() - MAX_LISTED_COVERAGE_GAPS).into());
+        }
+
+        render_paragraph(
+            frame,
+            file_info_state,
+            lines.into(),
+            is_focused,
+            info.warnings.len(),
+            rect,
         );
     }
 }
 
-fn get_block<'a>(is_focused: bool) -> Block<'a> {
+fn render_paragraph(
+    frame: &mut Frame,
+    file_info_state: &mut FileInfoState,
+    text: Text,
+    is_focused: bool,
+    warnings: usize,
+    rect: Rect,
+) {
+    let block = get_block(is_focused, warnings);
+
+    let block_inner = block.inner(rect);
+
+    let height = calculate_height(&text, block.clone(), rect);
+
+    file_info_state.max_height = block_inner.height;
+    file_info_state.text_height = height;
+
+    frame.render_widget(
+        Paragraph::new(text)
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .scroll((file_info_state.scroll, 0)),
+        rect,
+    );
+
+    let mut scrollbar_state =
+        ScrollbarState::new(file_info_state.max_scroll() as usize).position(file_info_state.scroll as usize);
+
+    frame.render_stateful_widget(
+        default_scrollbar(),
+        rect.inner(&Margin {
+            vertical: 1,
+            horizontal: 0,
+        }),
+        &mut scrollbar_state,
+    );
+}
+
+fn get_block<'a>(is_focused: bool, warnings: usize) -> Block<'a> {
     let mut block = default_block();
+    if warnings > 0 {
+        block = block.title(
+            Title::from(Line::from(format!("⚠ {warnings} skipped mappings").warning())).alignment(Alignment::Right),
+        );
+    }
     if is_focused {
         block = block.border_style(Style::default().fg(FOCUS)).title(
-            Title::from(Line::from(
-                keybindings!("<Enter>"" toggle"; " | ".dark_gray();, "e""xpand descendants"; " | ".dark_gray();, "t""ree toggle"),
-            ))
+            Title::from(Line::from(keybindings!(
+                "<Enter>"" toggle"; " | ".dark_gray();,
+                "e""xpand descendants"; " | ".dark_gray();,
+                "t""ree toggle"; " | ".dark_gray();,
+                "p""ackages"; " | ".dark_gray();,
+                "c""overage"
+            )))
             .position(Position::Bottom),
         );
     }
@@ -270,13 +759,18 @@ fn get_block<'a>(is_focused: bool) -> Block<'a> {
 
 pub enum FileInfoViewType {
     Tree,
+    // Tree of sources grouped by npm package.
+    Packages,
     Paragraph,
+    Coverage,
 }
 
 pub struct FileInfoState {
     pub view_type: FileInfoViewType,
     pub tree_state: TreeState,
-    tree: Option<Rc<Tree<usize, TreeAggregation>>>,
+    tree: Option<Rc<Tree<TreeInfoLeaf, TreeAggregation>>>,
+    // Tree of the combined entry, leaves are indices of its sources.
+    combined_tree: Option<Rc<Tree<usize, u64>>>,
     // paragraph state
     pub scroll: u16,
     pub text_height: u16,
@@ -288,32 +782,69 @@ impl FileInfoState {
         self.text_height.saturating_sub(self.max_height)
     }
 
-    fn build_tree(&mut self, info: &SourceMappingInfo) -> Rc<Tree<usize, TreeAggregation>> {
+    // Paths differ between tree views, so neither the tree nor its expanded nodes can be reused.
+    fn reset_tree(&mut self) {
+        let is_focused = self.tree_state.list_state.selected().is_some();
+
+        self.tree = None;
+        self.combined_tree = None;
+        self.tree_state = TreeState::default().initial_expansion_depth(2);
+
+        if is_focused {
+            self.tree_state.list_state.select(Some(0));
+        }
+    }
+
+    // Tree is dropped whenever the file, the metric or the tree view changes, so they are captured once.
+    fn build_tree(&mut self, info: &SourceMappingInfo, metric: SizeMetric) -> Rc<Tree<TreeInfoLeaf, TreeAggregation>> {
         self.tree
             .get_or_insert_with(|| {
-                let mapping = &info.source_mapping;
-                let source_file_len = mapping.actual_source_file_len();
-                let aggregator_source_file_len = source_file_len;
-
-                Tree::from((0..info.info_by_file.len()).collect::<Vec<_>>(), |index| {
-                    without_relative_part(info.get_file_name(info.info_by_file[*index].file)).to_owned()
-                })
-                .with_aggregator::<TreeAggregation>(
-                    info.info_by_file
-                        .iter()
-                        .map(|file_info| TreeAggregation {
-                            bytes: file_info.bytes as u64,
-                        })
-                        .collect::<Vec<_>>()
-                        .as_slice(),
-                    |leaf_aggregations, index| leaf_aggregations[*index],
+                let file_sizes = SizeMetric::ALL.map(|size_metric| info.file_size(size_metric));
+                let file_size = move |size_metric: SizeMetric| file_sizes[size_metric as usize];
+
+                let leaves = TreeInfoLeaf::leaves(info);
+                let tree = match self.view_type {
+                    FileInfoViewType::Packages => Tree::from_parts(leaves, |leaf| leaf.package_path(info)),
+                    _ => Tree::from(leaves, |leaf| leaf.path(info)),
+                };
+
+                tree.with_aggregator::<TreeAggregation>(
+                    &[],
+                    |_, leaf| leaf.aggregation(info),
                     move |aggregation| {
-                        vec![
-                            format_bytes(aggregation.bytes).highlight(),
-                            " (".into(),
-                            format_percentage(aggregation.bytes, aggregator_source_file_len).highlight2(),
-                            ") ".into(),
-                        ]
+                        let mut spans = size_spans(|size_metric| aggregation.size(size_metric), file_size, metric);
+                        spans.push(" ".into());
+                        spans
+                    },
+                )
+                .into()
+            })
+            .clone()
+    }
+}
+
+impl FileInfoState {
+    fn build_combined_tree(&mut self, info: &CombinedInfo) -> Rc<Tree<usize, u64>> {
+        self.combined_tree
+            .get_or_insert_with(|| {
+                let file_size = combined_size(info.file_bytes);
+
+                let leaves = (0..info.sources.len()).collect();
+                let tree = match self.view_type {
+                    FileInfoViewType::Packages => Tree::from_parts(leaves, |index| {
+                        let source = &info.sources[*index];
+                        TreeInfoLeaf::package_parts(&source.path, info.relative_path(source))
+                    }),
+                    _ => Tree::from(leaves, |index| info.relative_path(&info.sources[*index]).to_owned()),
+                };
+
+                tree.with_aggregator::<u64>(
+                    &[],
+                    |_, index| info.sources[*index].bytes,
+                    move |bytes| {
+                        let mut spans = size_spans(combined_size(*bytes), &file_size, SizeMetric::Raw);
+                        spans.push(" ".into());
+                        spans
                     },
                 )
                 .into()
@@ -329,6 +860,7 @@ impl Default for FileInfoState {
         Self {
             view_type: FileInfoViewType::Tree,
             tree: None,
+            combined_tree: None,
             tree_state,
             scroll: 0,
             text_height: 0,
@@ -343,12 +875,30 @@ impl FocusableWidgetState for FileInfoState {
             KeyCode::Char('t') => {
                 self.view_type = match self.view_type {
                     FileInfoViewType::Tree => FileInfoViewType::Paragraph,
-                    FileInfoViewType::Paragraph => FileInfoViewType::Tree,
+                    FileInfoViewType::Packages => {
+                        self.reset_tree();
+                        FileInfoViewType::Tree
+                    }
+                    FileInfoViewType::Paragraph | FileInfoViewType::Coverage => FileInfoViewType::Tree,
+                };
+            }
+            KeyCode::Char('p') => {
+                self.reset_tree();
+                self.view_type = match self.view_type {
+                    FileInfoViewType::Packages => FileInfoViewType::Tree,
+                    _ => FileInfoViewType::Packages,
+                };
+            }
+            KeyCode::Char('c') => {
+                self.scroll = 0;
+                self.view_type = match self.view_type {
+                    FileInfoViewType::Coverage => FileInfoViewType::Tree,
+                    _ => FileInfoViewType::Coverage,
                 };
             }
             _ => match self.view_type {
-                FileInfoViewType::Tree => self.handle_tree_events(event),
-                FileInfoViewType::Paragraph => self.handle_paragraph_events(event),
+                FileInfoViewType::Tree | FileInfoViewType::Packages => self.handle_tree_events(event),
+                FileInfoViewType::Paragraph | FileInfoViewType::Coverage => self.handle_paragraph_events(event),
             },
         }
 
@@ -361,7 +911,7 @@ impl FocusableWidgetState for FileInfoState {
     }
 
     fn on_focus(&mut self) {
-        if matches!(self.view_type, FileInfoViewType::Tree) {
+        if matches!(self.view_type, FileInfoViewType::Tree | FileInfoViewType::Packages) {
             self.tree_state.list_state.select(Some(0));
         }
     }
@@ -380,25 +930,20 @@ impl FileInfoState {
                 self.tree_state.toggle_selected();
             }
             KeyCode::Char('e') => {
-                let Some(tree) = self.tree.as_ref().cloned() else {
-                    return;
-                };
-
                 let path = self.tree_state.selected_path();
-                let Some(TreeItem::Node(node)) = tree.get_item_by_path(path) else {
-                    return;
+                let paths_to_toggle = match (&self.tree, &self.combined_tree) {
+                    (Some(tree), _) => node_paths(tree, path),
+                    (_, Some(tree)) => node_paths(tree, path),
+                    _ => return,
                 };
 
-                let mut paths_to_toggle = vec![node.location.path.as_str()];
-                paths_to_toggle.append(&mut tree.get_node_descendant_paths(node));
-
                 let expand = !self.tree_state.is_selected_expanded();
 
                 for path in paths_to_toggle {
                     if expand {
-                        self.tree_state.expanded.insert(path.to_owned());
+                        self.tree_state.expanded.insert(path);
                     } else {
-                        self.tree_state.expanded.remove(path);
+                        self.tree_state.expanded.remove(&path);
                     }
                 }
             }
@@ -432,7 +977,18 @@ impl FileInfoState {
     }
 }
 
-fn calculate_height(text: &Text, block: Block, area: Rect) -> u16 {
+// Path of the node and all of its descendants, empty if the path does not point to a node.
+fn node_paths<D: Debug, A: Add<Output = A> + Copy>(tree: &Tree<D, A>, path: &str) -> Vec<String> {
+    let Some(TreeItem::Node(node)) = tree.get_item_by_path(path) else {
+        return vec![];
+    };
+
+    let mut paths = vec![node.location.path.clone()];
+    paths.extend(tree.get_node_descendant_paths(node).into_iter().map(str::to_owned));
+    paths
+}
+
+pub fn calculate_height(text: &Text, block: Block, area: Rect) -> u16 {
     // Total area of a paragraph must fit into u16, so height of the rect is computed
     // accordingly using max line width.
     let area = Rect::new(area.x, area.y, area.width, u16::MAX / area.width);
@@ -445,7 +1001,8 @@ fn calculate_height(text: &Text, block: Block, area: Rect) -> u16 {
 
     paragraph.render(area, &mut buffer);
 
-    for y in buffer.area.top()..buffer.area.bottom() {
+    // Top border is skipped, as it can contain a title.
+    for y in buffer.area.top() + 1..buffer.area.bottom() {
         let x = buffer.area.left() + 1;
 
         if buffer.get(x, y).symbol() == " " {
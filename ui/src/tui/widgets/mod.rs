@@ -1,4 +1,5 @@
 pub mod dialog;
+pub mod duplicates;
 pub mod file_list;
 pub mod fps;
 pub mod input;
@@ -1,40 +1,106 @@
+use anyhow::{anyhow, Result};
 use crossterm::event::{KeyCode, KeyEvent};
-use ratatui::layout::Rect;
+use ratatui::layout::{Constraint, Layout, Rect};
 use ratatui::{style::*, text::Line};
 
+use core::discovery::DiscoveryOptions;
+
 use crate::keybindings;
 
 use crate::tui::core::custom_widget::{CustomWidget, RenderContext};
 use crate::tui::{
     core::{FocusableWidgetState, HandleEventResult},
     widget_utils::CustomStyles,
+    widgets::file_list::AnalyzeState,
     App, FocusableWidget,
 };
 
 use super::input::{InputWidget, InputWidgetState};
 
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum PathInputField {
+    #[default]
+    Path,
+    Filters,
+}
+
 #[derive(Default)]
 pub struct PathState {
     pub path_input: InputWidgetState,
+    // Discovery options in the same form as they are given in the CLI, see parse_filters.
+    pub filters_input: InputWidgetState,
+    pub focused_field: PathInputField,
 }
 
 impl FocusableWidgetState for PathState {
     fn handle_events(&mut self, event: KeyEvent) -> HandleEventResult {
         match event.code {
             KeyCode::Enter => HandleEventResult::Callback(Box::new(Self::callback)),
-            _ => self.path_input.handle_events(event),
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focused_field = match self.focused_field {
+                    PathInputField::Path => PathInputField::Filters,
+                    PathInputField::Filters => PathInputField::Path,
+                };
+                HandleEventResult::KeepFocus
+            }
+            _ => match self.focused_field {
+                PathInputField::Path => self.path_input.handle_events(event),
+                PathInputField::Filters => self.filters_input.handle_events(event),
+            },
         }
     }
 
     fn callback(app: &mut App) -> HandleEventResult {
         let path = app.path_state.path_input.value().to_owned();
 
-        app.file_list_state.analyze_path(path);
+        match parse_filters(app.path_state.filters_input.value()) {
+            Ok(options) => app.file_list_state.analyze_path(path, options),
+            Err(err) => app.file_list_state.analyze_state = Some(AnalyzeState::Err(Box::new(err))),
+        }
 
         HandleEventResult::ChangeFocus(FocusableWidget::FileList)
     }
 }
 
+// Filters are separated by whitespace: "!glob" excludes paths, "depth:N" limits directory depth,
+// "no-gitignore" disables .gitignore handling and any other value is a glob of included bundles.
+pub fn parse_filters(value: &str) -> Result<DiscoveryOptions> {
+    let mut options = DiscoveryOptions::default();
+
+    for filter in value.split_whitespace() {
+        if let Some(glob) = filter.strip_prefix('!') {
+            options.exclude.push(glob.to_owned());
+        } else if let Some(depth) = filter.strip_prefix("depth:") {
+            let depth = depth
+                .parse::<usize>()
+                .map_err(|_| anyhow!("Invalid depth filter {filter:?}, expected e.g. \"depth:3\""))?;
+            options.max_depth = Some(depth);
+        } else if filter == "no-gitignore" {
+            options.respect_gitignore = false;
+        } else {
+            options.include.push(filter.to_owned());
+        }
+    }
+
+    Ok(options)
+}
+
+pub fn format_filters(options: &DiscoveryOptions) -> String {
+    let mut filters = options.include.clone();
+
+    filters.extend(options.exclude.iter().map(|glob| format!("!{glob}")));
+
+    if let Some(depth) = options.max_depth {
+        filters.push(format!("depth:{depth}"));
+    }
+
+    if !options.respect_gitignore {
+        filters.push("no-gitignore".to_owned());
+    }
+
+    filters.join(" ")
+}
+
 pub struct PathInputWidget;
 
 impl CustomWidget for PathInputWidget {
@@ -43,12 +109,24 @@ impl CustomWidget for PathInputWidget {
     }
 
     fn render<'widget, 'app: 'widget>(&self, mut context: RenderContext<'app, '_>, rect: Rect) {
-        let label = Line::from(keybindings!("p""ath"));
+        let [path_rect, filters_rect] = Layout::horizontal([Constraint::Fill(3), Constraint::Fill(2)]).areas(rect);
+
+        let is_focused = context.is_focused();
+        let focused_field = context.app().path_state.focused_field;
+
+        let path_label = Line::from(keybindings!("p""ath"));
+        let path_input = InputWidget::new(is_focused && focused_field == PathInputField::Path).label(path_label);
 
-        let input = InputWidget::new(context.is_focused()).label(label);
+        let filters_label = Line::from(keybindings!(
+            "tab"" filters ";
+            "(glob !exclude depth:N no-gitignore)".dark_gray();
+        ));
+        let filters_input =
+            InputWidget::new(is_focused && focused_field == PathInputField::Filters).label(filters_label);
 
         let (app, frame) = context.app_frame_mut();
 
-        InputWidget::frame_render(frame, input, rect, &mut app.path_state.path_input);
+        InputWidget::frame_render(frame, path_input, path_rect, &mut app.path_state.path_input);
+        InputWidget::frame_render(frame, filters_input, filters_rect, &mut app.path_state.filters_input);
     }
 }
@@ -1,9 +1,6 @@
-use crate::{
-    tui::{
-        core::{FocusableWidgetState, HandleEventResult, ListOperations},
-        App, FocusableWidget,
-    },
-    utils::without_relative_part,
+use crate::tui::{
+    core::{FocusableWidgetState, HandleEventResult, ListOperations},
+    App, FocusableWidget,
 };
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
@@ -70,7 +67,7 @@ impl FocusableWidgetState for SearchDialogState {
 
                 let file = info
                     .source_mapping
-                    .sources
+                    .source_paths
                     .iter()
                     .find(|source| source.to_lowercase().contains(&search_value));
 
@@ -82,7 +79,7 @@ impl FocusableWidgetState for SearchDialogState {
             }
 
             if let Some((pos, file)) = found_file {
-                let file = without_relative_part(file).to_owned();
+                let file = file.to_owned();
                 done_state.file_infos.select(pos);
                 app.search_dialog.path_input.reset();
 